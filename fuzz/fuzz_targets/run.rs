@@ -0,0 +1,58 @@
+#![no_main]
+use chip_8_emulator::chip8::{Chip8, Fault};
+use chip_8_emulator::fontset::Font;
+use chip_8_emulator::quirks::Platform;
+use libfuzzer_sys::fuzz_target;
+
+const INSTRUCTIONS_PER_KEY_STATE: usize = 64;
+
+// Input layout: a platform byte, a key state count N, N little-endian key
+// masks, and the rest is the ROM.
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+    let platform = Platform::ALL[data[0] as usize % Platform::ALL.len()];
+    let key_states = (data[1] % 16) as usize;
+    let keys_end = (2 + 2 * key_states).min(data.len());
+    let keys: Vec<u16> = data[2..keys_end]
+        .chunks_exact(2)
+        .map(|mask| u16::from_le_bytes([mask[0], mask[1]]))
+        .collect();
+    let rom = &data[keys_end..];
+
+    let mut cpu = Chip8::with_seed(0);
+    cpu.set_memory_layout(
+        platform.memory_size(),
+        cpu.program_start,
+        cpu.font_address,
+    );
+    cpu.quirks = platform.quirks();
+    cpu.load_font_set(&Font::for_platform(Some(platform)));
+    let start = cpu.program_start as usize;
+    let rom = &rom[..rom.len().min(cpu.memory.len() - start)];
+    cpu.memory[start..start + rom.len()].copy_from_slice(rom);
+
+    let memory_size = cpu.memory.len();
+    for &mask in keys.iter().chain(Some(&0)) {
+        cpu.set_keys_mask(mask);
+        for _ in 0..INSTRUCTIONS_PER_KEY_STATE {
+            let faulted = cpu.fault;
+            let (pc, cycles) = (cpu.pc, cpu.cycles);
+            cpu.run();
+            assert_eq!(cpu.memory.len(), memory_size);
+            assert!((cpu.pc as usize) < memory_size);
+            assert!((cpu.sp as usize) <= cpu.stack.len());
+            match cpu.fault {
+                Some(Fault::StackOverflow) => assert_eq!(cpu.sp as usize, cpu.stack.len()),
+                Some(Fault::StackUnderflow) => assert_eq!(cpu.sp, 0),
+                None => {}
+            }
+            // A faulted machine stays halted on the same instruction.
+            if faulted.is_some() {
+                assert_eq!(cpu.fault, faulted);
+                assert_eq!((cpu.pc, cpu.cycles), (pc, cycles));
+            }
+        }
+    }
+});
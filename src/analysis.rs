@@ -0,0 +1,459 @@
+use crate::disassembler;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+const STACK_SIZE: usize = 16;
+const MIN_UNREACHABLE_CODE: usize = 6;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EdgeKind {
+    Fallthrough,
+    Jump,
+    Call,
+    Skip,
+}
+
+pub struct BasicBlock {
+    pub start: u16,
+    pub instructions: Vec<u16>,
+    pub successors: Vec<(u16, EdgeKind)>,
+}
+
+pub enum Finding {
+    InvalidOpcode { at: u16, opcode: u16 },
+    TargetOutsideRom { at: u16, target: u16 },
+    IndirectJump { at: u16 },
+    UnreachableCode { start: u16, end: u16 },
+    SelfModifyingWrite { at: u16, target: u16 },
+    UnresolvedWrite { at: u16 },
+    Recursion { function: u16 },
+    DeepStack { path: Vec<u16> },
+}
+
+pub struct Analysis {
+    pub start: u16,
+    pub end: usize,
+    pub instructions: BTreeSet<u16>,
+    pub code: Vec<bool>,
+    pub blocks: BTreeMap<u16, BasicBlock>,
+    pub functions: BTreeSet<u16>,
+    pub max_call_depth: Option<usize>,
+    pub findings: Vec<Finding>,
+}
+
+pub fn analyze(memory: &[u8], start: u16, end: usize) -> Analysis {
+    let end = end.min(memory.len());
+    let mut analysis = Analysis {
+        start,
+        end,
+        instructions: BTreeSet::new(),
+        code: vec![false; memory.len()],
+        blocks: BTreeMap::new(),
+        functions: BTreeSet::new(),
+        max_call_depth: None,
+        findings: Vec::new(),
+    };
+    analysis.functions.insert(start);
+    let mut leaders: BTreeSet<u16> = BTreeSet::new();
+    leaders.insert(start);
+    let mut pending = vec![start];
+    while let Some(address) = pending.pop() {
+        if address as usize + 1 >= end || !analysis.instructions.insert(address) {
+            continue;
+        }
+        analysis.code[address as usize] = true;
+        analysis.code[address as usize + 1] = true;
+        let opcode = read_opcode(memory, address);
+        if disassembler::disassemble(opcode).starts_with("DW") {
+            analysis.findings.push(Finding::InvalidOpcode {
+                at: address,
+                opcode,
+            });
+            continue;
+        }
+        if opcode & 0xF000 == 0xB000 {
+            analysis
+                .findings
+                .push(Finding::IndirectJump { at: address });
+        }
+        let edges = successors(address, opcode);
+        let branches = edges.len() != 1 || edges[0].1 != EdgeKind::Fallthrough;
+        for (target, kind) in edges {
+            if target < start || target as usize + 1 >= end {
+                analysis.findings.push(Finding::TargetOutsideRom {
+                    at: address,
+                    target,
+                });
+                continue;
+            }
+            if kind == EdgeKind::Call {
+                analysis.functions.insert(target);
+            }
+            if branches {
+                leaders.insert(target);
+            }
+            pending.push(target);
+        }
+    }
+    analysis.build_blocks(memory, &leaders);
+    analysis.find_unreachable_code(memory);
+    analysis.find_self_modifying_writes(memory);
+    analysis.check_call_depth(memory);
+    analysis
+}
+
+fn successors(address: u16, opcode: u16) -> Vec<(u16, EdgeKind)> {
+    let next = address.wrapping_add(2);
+    let nnn = opcode & 0x0FFF;
+    match opcode & 0xF000 {
+        0x0000 if opcode == 0x00EE => vec![],
+        0x1000 => vec![(nnn, EdgeKind::Jump)],
+        0x2000 => vec![(nnn, EdgeKind::Call), (next, EdgeKind::Fallthrough)],
+        0x3000 | 0x4000 | 0x5000 | 0x9000 | 0xE000 => {
+            vec![
+                (next, EdgeKind::Fallthrough),
+                (next.wrapping_add(2), EdgeKind::Skip),
+            ]
+        }
+        0xB000 => vec![],
+        _ => vec![(next, EdgeKind::Fallthrough)],
+    }
+}
+
+fn read_opcode(memory: &[u8], address: u16) -> u16 {
+    (memory[address as usize] as u16) << 8 | memory[address as usize + 1] as u16
+}
+
+fn writes_memory(opcode: u16) -> Option<u16> {
+    match (opcode & 0xF000, opcode & 0x00FF) {
+        (0xF000, 0x33) => Some(3),
+        (0xF000, 0x55) => Some(((opcode & 0x0F00) >> 8) + 1),
+        _ => None,
+    }
+}
+
+fn changes_index(opcode: u16) -> bool {
+    matches!(
+        (opcode & 0xF000, opcode & 0x00FF),
+        (0xF000, 0x1E) | (0xF000, 0x29) | (0xF000, 0x30) | (0xF000, 0x55) | (0xF000, 0x65)
+    )
+}
+
+impl Analysis {
+    fn build_blocks(&mut self, memory: &[u8], leaders: &BTreeSet<u16>) {
+        let leaders: Vec<u16> = leaders
+            .iter()
+            .copied()
+            .filter(|leader| self.instructions.contains(leader))
+            .collect();
+        for &leader in &leaders {
+            let mut block = BasicBlock {
+                start: leader,
+                instructions: Vec::new(),
+                successors: Vec::new(),
+            };
+            let mut address = leader;
+            loop {
+                block.instructions.push(address);
+                let opcode = read_opcode(memory, address);
+                let edges = successors(address, opcode);
+                let next = address.wrapping_add(2);
+                let falls_through = edges.len() == 1 && edges[0] == (next, EdgeKind::Fallthrough);
+                if !falls_through || leaders.contains(&next) || !self.instructions.contains(&next) {
+                    block.successors = edges
+                        .into_iter()
+                        .filter(|(target, _)| self.instructions.contains(target))
+                        .collect();
+                    break;
+                }
+                address = next;
+            }
+            self.blocks.insert(leader, block);
+        }
+    }
+
+    fn find_unreachable_code(&mut self, memory: &[u8]) {
+        let mut address = self.start as usize;
+        let end = self.end;
+        while address < end {
+            if self.code[address] {
+                address += 1;
+                continue;
+            }
+            let gap_start = address;
+            while address < end && !self.code[address] {
+                address += 1;
+            }
+            let referenced = self.instructions.iter().any(|&at| {
+                let opcode = read_opcode(memory, at);
+                let target = (opcode & 0x0FFF) as usize;
+                opcode & 0xF000 == 0xA000 && target >= gap_start && target < address
+            });
+            if referenced {
+                continue;
+            }
+            let words = (address - gap_start) / 2;
+            let decodes = (0..words).all(|word| {
+                let opcode = read_opcode(memory, (gap_start + word * 2) as u16);
+                let text = disassembler::disassemble(opcode);
+                !text.starts_with("DW") && !text.starts_with("SYS")
+            });
+            if words * 2 >= MIN_UNREACHABLE_CODE && decodes {
+                self.findings.push(Finding::UnreachableCode {
+                    start: gap_start as u16,
+                    end: (gap_start + words * 2) as u16,
+                });
+            }
+        }
+    }
+
+    fn find_self_modifying_writes(&mut self, memory: &[u8]) {
+        let mut findings = Vec::new();
+        for block in self.blocks.values() {
+            let mut index: Option<u16> = None;
+            for &address in &block.instructions {
+                let opcode = read_opcode(memory, address);
+                if let Some(length) = writes_memory(opcode) {
+                    match index {
+                        Some(target) => {
+                            let overlaps = (target..target + length)
+                                .any(|byte| self.code.get(byte as usize) == Some(&true));
+                            if overlaps {
+                                findings.push(Finding::SelfModifyingWrite {
+                                    at: address,
+                                    target,
+                                });
+                            }
+                        }
+                        None => findings.push(Finding::UnresolvedWrite { at: address }),
+                    }
+                }
+                if opcode & 0xF000 == 0xA000 {
+                    index = Some(opcode & 0x0FFF);
+                } else if changes_index(opcode) {
+                    index = None;
+                }
+            }
+        }
+        self.findings.extend(findings);
+    }
+
+    fn function_calls(&self, memory: &[u8], function: u16) -> BTreeSet<u16> {
+        let mut calls = BTreeSet::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![function];
+        while let Some(address) = pending.pop() {
+            if !self.instructions.contains(&address) || !visited.insert(address) {
+                continue;
+            }
+            for (target, kind) in successors(address, read_opcode(memory, address)) {
+                if kind == EdgeKind::Call {
+                    calls.insert(target);
+                } else {
+                    pending.push(target);
+                }
+            }
+        }
+        calls
+    }
+
+    fn check_call_depth(&mut self, memory: &[u8]) {
+        let calls: BTreeMap<u16, BTreeSet<u16>> = self
+            .functions
+            .iter()
+            .map(|&function| (function, self.function_calls(memory, function)))
+            .collect();
+        let mut longest = HashMap::new();
+        let mut recursive = BTreeSet::new();
+        let depth = longest_call_chain(
+            &calls,
+            self.start,
+            &mut longest,
+            &mut HashSet::new(),
+            &mut recursive,
+        );
+        for function in recursive {
+            self.findings.push(Finding::Recursion { function });
+        }
+        if depth > STACK_SIZE {
+            let mut path = vec![self.start];
+            while let Some(&(_, Some(callee))) = longest.get(path.last().unwrap()) {
+                path.push(callee);
+            }
+            self.findings.push(Finding::DeepStack { path });
+        }
+        self.max_call_depth = Some(depth);
+    }
+
+    pub fn report(&self) -> String {
+        let code_bytes = self.code[self.start as usize..self.end]
+            .iter()
+            .filter(|&&code| code)
+            .count();
+        let mut report = format!(
+            "{} instructions in {} blocks, {} subroutines\n{} bytes of code, {} bytes of data\n",
+            self.instructions.len(),
+            self.blocks.len(),
+            self.functions.len() - 1,
+            code_bytes,
+            self.end - self.start as usize - code_bytes
+        );
+        if let Some(depth) = self.max_call_depth {
+            report.push_str(&format!(
+                "deepest call chain: {} of {} stack levels\n",
+                depth, STACK_SIZE
+            ));
+        }
+        for finding in &self.findings {
+            let line = match finding {
+                Finding::InvalidOpcode { at, opcode } => {
+                    format!("0x{:03X}: reached invalid opcode 0x{:04X}", at, opcode)
+                }
+                Finding::TargetOutsideRom { at, target } => {
+                    format!(
+                        "0x{:03X}: branches to 0x{:03X} outside of the ROM",
+                        at, target
+                    )
+                }
+                Finding::IndirectJump { at } => {
+                    format!(
+                        "0x{:03X}: indirect jump through V0, targets are not followed",
+                        at
+                    )
+                }
+                Finding::UnreachableCode { start, end } => {
+                    format!(
+                        "0x{:03X}-0x{:03X}: looks like code but is never reached",
+                        start,
+                        end - 1
+                    )
+                }
+                Finding::SelfModifyingWrite { at, target } => {
+                    format!("0x{:03X}: writes to code at 0x{:03X}", at, target)
+                }
+                Finding::UnresolvedWrite { at } => {
+                    format!("0x{:03X}: writes through a computed I", at)
+                }
+                Finding::Recursion { function } => {
+                    format!(
+                        "0x{:03X}: subroutine is recursive, stack depth is unbounded",
+                        function
+                    )
+                }
+                Finding::DeepStack { path } => {
+                    let chain: Vec<String> = path.iter().map(|a| format!("0x{:03X}", a)).collect();
+                    format!(
+                        "call chain of {} levels overflows the stack: {}",
+                        path.len() - 1,
+                        chain.join(" -> ")
+                    )
+                }
+            };
+            report.push_str(&line);
+            report.push('\n');
+        }
+        report
+    }
+
+    pub fn listing(&self, memory: &[u8]) -> String {
+        let mut listing = String::new();
+        let mut address = self.start as usize;
+        while address < self.end {
+            let key = address as u16;
+            if self.functions.contains(&key) {
+                listing.push_str(&format!("\nsub_{:03X}:\n", address));
+            } else if self.blocks.contains_key(&key) {
+                listing.push_str(&format!("label_{:03X}:\n", address));
+            }
+            if self.instructions.contains(&key) {
+                let opcode = read_opcode(memory, key);
+                listing.push_str(&format!(
+                    "    0x{:03X}: {:04X}  {}\n",
+                    address,
+                    opcode,
+                    disassembler::disassemble(opcode)
+                ));
+                address += 2;
+            } else {
+                let byte = memory[address];
+                listing.push_str(&format!(
+                    "    0x{:03X}: {:02X}    DB 0x{:02X}\n",
+                    address, byte, byte
+                ));
+                address += 1;
+            }
+        }
+        listing
+    }
+
+    pub fn to_dot(&self, memory: &[u8]) -> String {
+        let mut dot =
+            String::from("digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for block in self.blocks.values() {
+            let mut label = format!("0x{:03X}:\\l", block.start);
+            for &address in &block.instructions {
+                let opcode = read_opcode(memory, address);
+                label.push_str(&format!(
+                    "{:03X}  {}\\l",
+                    address,
+                    disassembler::disassemble(opcode)
+                ));
+            }
+            let style = if self.functions.contains(&block.start) {
+                ", style=bold"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    b{:03X} [label=\"{}\"{}];\n",
+                block.start, label, style
+            ));
+        }
+        for block in self.blocks.values() {
+            for (target, kind) in &block.successors {
+                let attributes = match kind {
+                    EdgeKind::Fallthrough => "",
+                    EdgeKind::Jump => " [label=\"jump\"]",
+                    EdgeKind::Call => " [label=\"call\", style=dashed, color=blue]",
+                    EdgeKind::Skip => " [label=\"skip\", color=red]",
+                };
+                dot.push_str(&format!(
+                    "    b{:03X} -> b{:03X}{};\n",
+                    block.start, target, attributes
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// Returns how many calls deep the longest chain below the function goes,
+// remembering it with the callee that starts it so that a subroutine shared
+// by many callers is only explored once. A call to a function that is still
+// on the path is recursion; it is recorded and not followed.
+fn longest_call_chain(
+    calls: &BTreeMap<u16, BTreeSet<u16>>,
+    function: u16,
+    longest: &mut HashMap<u16, (usize, Option<u16>)>,
+    on_path: &mut HashSet<u16>,
+    recursive: &mut BTreeSet<u16>,
+) -> usize {
+    if let Some(&(depth, _)) = longest.get(&function) {
+        return depth;
+    }
+    on_path.insert(function);
+    let mut deepest = (0, None);
+    for &callee in calls.get(&function).into_iter().flatten() {
+        if on_path.contains(&callee) {
+            recursive.insert(callee);
+            continue;
+        }
+        let depth = 1 + longest_call_chain(calls, callee, longest, on_path, recursive);
+        if depth > deepest.0 {
+            deepest = (depth, Some(callee));
+        }
+    }
+    on_path.remove(&function);
+    longest.insert(function, deepest);
+    deepest.0
+}
@@ -0,0 +1,242 @@
+use crate::graphics;
+use crate::text;
+use chip_8_emulator::quirks::Platform;
+use chip_8_emulator::recent;
+use chip_8_emulator::rom::Rom;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::render::WindowCanvas;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SCALE: u32 = 2;
+const LINE_HEIGHT: i32 = ((text::GLYPH_HEIGHT + 2) * SCALE) as i32;
+const ADVANCE: u32 = (text::GLYPH_WIDTH + 1) * SCALE;
+const MARGIN: i32 = 4;
+const VISIBLE_ENTRIES: usize = 13;
+const DETAIL_LINES: usize = 4;
+const TEXT_COLOR: Color = Color::RGB(0x40, 0xFF, 0x40);
+const SELECTED_COLOR: Color = Color::RGB(0xFF, 0xFF, 0x40);
+const HINT_COLOR: Color = Color::RGB(0x20, 0x80, 0x20);
+const BACKGROUND: Color = Color::RGB(0, 0, 0);
+// Files larger than the 64 KB of XO-CHIP memory cannot be ROMs.
+const MAX_ROM_SIZE: u64 = 0x10000;
+
+enum Entry {
+    Recent(PathBuf),
+    Parent(PathBuf),
+    Directory(PathBuf),
+    Rom(PathBuf),
+}
+
+// A menu drawn over the screen that lists the recently opened ROMs and the
+// files in a directory, shows what the database knows about the selected
+// one and opens it.
+pub struct RomBrowser {
+    pub visible: bool,
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    selected: usize,
+    scroll: usize,
+    details: Vec<String>,
+}
+
+impl RomBrowser {
+    pub fn new(dir: &str) -> RomBrowser {
+        RomBrowser {
+            visible: false,
+            dir: PathBuf::from(dir),
+            entries: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            details: Vec::new(),
+        }
+    }
+
+    pub fn show(&mut self) {
+        self.visible = true;
+        self.list();
+    }
+
+    // Returns the ROM to open when one is chosen, which also closes the menu.
+    pub fn handle_key(&mut self, keycode: Keycode) -> Option<String> {
+        let last = self.entries.len().saturating_sub(1);
+        match keycode {
+            Keycode::Escape => self.visible = false,
+            Keycode::Up => self.select(self.selected.saturating_sub(1)),
+            Keycode::Down => self.select((self.selected + 1).min(last)),
+            Keycode::PageUp => self.select(self.selected.saturating_sub(VISIBLE_ENTRIES)),
+            Keycode::PageDown => self.select((self.selected + VISIBLE_ENTRIES).min(last)),
+            Keycode::Home => self.select(0),
+            Keycode::End => self.select(last),
+            Keycode::Backspace => {
+                if let Some(parent) = self.dir.parent() {
+                    self.dir = parent.to_path_buf();
+                    self.list();
+                }
+            }
+            Keycode::Return | Keycode::KpEnter => match self.entries.get(self.selected) {
+                Some(Entry::Parent(dir)) | Some(Entry::Directory(dir)) => {
+                    self.dir = dir.clone();
+                    self.list();
+                }
+                Some(Entry::Recent(file)) | Some(Entry::Rom(file)) => {
+                    self.visible = false;
+                    return Some(file.to_string_lossy().into_owned());
+                }
+                None => {}
+            },
+            _ => {}
+        }
+        None
+    }
+
+    pub fn draw(&self, canvas: &mut WindowCanvas) {
+        let (width, _) = canvas.output_size().unwrap_or((0, 0));
+        let columns = (width.saturating_sub(2 * MARGIN as u32) / ADVANCE) as usize;
+        canvas.set_draw_color(BACKGROUND);
+        canvas.clear();
+        let header = format!("OPEN ROM: {}", self.dir.display());
+        draw_line(canvas, &header, 0, columns, TEXT_COLOR);
+        if self.entries.is_empty() {
+            draw_line(canvas, "NO ROMS HERE", 2, columns, TEXT_COLOR);
+        }
+        for (row, (index, entry)) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(VISIBLE_ENTRIES)
+            .enumerate()
+        {
+            let (marker, color) = if index == self.selected {
+                ("> ", SELECTED_COLOR)
+            } else {
+                ("  ", TEXT_COLOR)
+            };
+            let label = format!("{}{}", marker, entry.label());
+            draw_line(canvas, &label, 2 + row, columns, color);
+        }
+        let details_row = 3 + VISIBLE_ENTRIES;
+        for (row, detail) in self.details.iter().take(DETAIL_LINES).enumerate() {
+            draw_line(canvas, detail, details_row + row, columns, TEXT_COLOR);
+        }
+        draw_line(
+            canvas,
+            "ENTER: OPEN  BACKSPACE: UP  ESC: CLOSE",
+            details_row + DETAIL_LINES + 1,
+            columns,
+            HINT_COLOR,
+        );
+    }
+
+    fn list(&mut self) {
+        self.dir = fs::canonicalize(&self.dir).unwrap_or_else(|_| self.dir.clone());
+        let mut entries: Vec<Entry> = recent::load()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|file| file.is_file())
+            .map(Entry::Recent)
+            .collect();
+        if let Some(parent) = self.dir.parent() {
+            entries.push(Entry::Parent(parent.to_path_buf()));
+        }
+        let mut listed: Vec<Entry> = fs::read_dir(&self.dir)
+            .map(|dir| {
+                dir.filter_map(Result::ok)
+                    .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+                    .filter_map(|entry| {
+                        let path = entry.path();
+                        let metadata = entry.metadata().ok()?;
+                        if metadata.is_dir() {
+                            Some(Entry::Directory(path))
+                        } else if metadata.len() <= MAX_ROM_SIZE {
+                            Some(Entry::Rom(path))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Directories first, then files, each by name.
+        listed.sort_by_key(|entry| (matches!(entry, Entry::Rom(_)), entry.label()));
+        entries.extend(listed);
+        self.entries = entries;
+        self.scroll = 0;
+        self.select(0);
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + VISIBLE_ENTRIES {
+            self.scroll = self.selected + 1 - VISIBLE_ENTRIES;
+        }
+        self.details = match self.entries.get(self.selected) {
+            Some(Entry::Recent(file)) | Some(Entry::Rom(file)) => describe(file),
+            Some(Entry::Parent(_)) => vec!["PARENT DIRECTORY".to_string()],
+            Some(Entry::Directory(_)) => vec!["DIRECTORY".to_string()],
+            None => Vec::new(),
+        };
+    }
+}
+
+impl Entry {
+    fn label(&self) -> String {
+        match self {
+            Entry::Recent(file) => format!("[RECENT] {}", file_name(file)),
+            Entry::Parent(_) => "../".to_string(),
+            Entry::Directory(dir) => format!("{}/", file_name(dir)),
+            Entry::Rom(file) => file_name(file),
+        }
+    }
+}
+
+// Long lines keep their end, which is the part of a path that differs.
+fn draw_line(canvas: &mut WindowCanvas, line: &str, row: usize, columns: usize, color: Color) {
+    let length = line.chars().count();
+    let line = if length > columns {
+        let kept: String = line.chars().skip(length + 3 - columns).collect();
+        format!("...{}", kept)
+    } else {
+        line.to_string()
+    };
+    let y = MARGIN + row as i32 * LINE_HEIGHT;
+    graphics::draw_text(canvas, &line, MARGIN, y, SCALE, color);
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+// Loads the ROM as it would be opened, with its patch and database entry.
+fn describe(file: &Path) -> Vec<String> {
+    let rom = match Rom::new(&file.to_string_lossy()) {
+        Ok(rom) => rom,
+        Err(error) => return vec![format!("CANNOT OPEN: {}", error)],
+    };
+    let mut details = vec![rom
+        .settings
+        .title
+        .clone()
+        .unwrap_or_else(|| "NOT IN THE DATABASE".to_string())];
+    if !rom.settings.authors.is_empty() {
+        details.push(format!("BY {}", rom.settings.authors.join(" / ")));
+    }
+    details.push(format!(
+        "{}  {} BYTES  {} INSTRUCTIONS PER FRAME",
+        rom.settings.platform.map_or("chip-8", Platform::id),
+        rom.size(),
+        rom.settings.tick_rate
+    ));
+    if let Some(patch) = &rom.patch {
+        details.push(format!("PATCHED BY {}", file_name(patch)));
+    }
+    details.extend(rom.warnings());
+    details
+}
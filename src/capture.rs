@@ -0,0 +1,285 @@
+use crate::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use gif::{Encoder, Frame, Repeat};
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+
+pub const DEFAULT_SCALE: u32 = 10;
+// Every capture scales a whole frame in memory, and Y4M keeps three planes of
+// it; 32 already gives 2048x1024 pixels.
+pub const MAX_SCALE: u32 = 32;
+const FRAMES_PER_SECOND: u64 = 60;
+const GIF_TICKS_PER_SECOND: u64 = 100;
+pub const SAMPLE_RATE: u32 = 44_100;
+pub const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE as u64 / FRAMES_PER_SECOND) as usize;
+const TONE_HZ: u32 = 440;
+const TONE_AMPLITUDE: i16 = 4096;
+const WAV_HEADER_SIZE: u32 = 44;
+
+pub type Video = [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT];
+
+fn check_scale(scale: u32) -> Result<(), Box<dyn Error>> {
+    if scale == 0 || scale > MAX_SCALE {
+        return Err(format!("the scale must be between 1 and {}", MAX_SCALE).into());
+    }
+    Ok(())
+}
+
+// One palette index per pixel, with every CHIP-8 pixel blown up to a
+// scale x scale square.
+fn scaled_indices(video: &Video, scale: u32) -> Vec<u8> {
+    let scale = scale as usize;
+    let mut indices = Vec::with_capacity(SCREEN_WIDTH * SCREEN_HEIGHT * scale * scale);
+    for row in video.iter() {
+        let line: Vec<u8> = row
+            .iter()
+            .flat_map(|&pixel| std::iter::repeat_n(pixel & 1, scale))
+            .collect();
+        for _ in 0..scale {
+            indices.extend_from_slice(&line);
+        }
+    }
+    indices
+}
+
+fn rgb(color: u32) -> [u8; 3] {
+    [(color >> 16) as u8, (color >> 8) as u8, color as u8]
+}
+
+pub fn write_png(
+    file: &str,
+    video: &Video,
+    palette: [u32; 2],
+    scale: u32,
+) -> Result<(), Box<dyn Error>> {
+    check_scale(scale)?;
+    let width = SCREEN_WIDTH as u32 * scale;
+    let height = SCREEN_HEIGHT as u32 * scale;
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(file)?), width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette([rgb(palette[0]), rgb(palette[1])].concat());
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&scaled_indices(video, scale))?;
+    Ok(())
+}
+
+// Records one frame per emulated 60 Hz frame. Runs of identical frames are
+// merged into one longer GIF frame, and delays are rounded so that the
+// total length always matches the emulated time.
+pub struct GifRecorder {
+    encoder: Encoder<BufWriter<File>>,
+    scale: u32,
+    width: u16,
+    height: u16,
+    pending: Option<Video>,
+    pending_since: u64,
+    frames: u64,
+}
+
+impl GifRecorder {
+    pub fn new(file: &str, palette: [u32; 2], scale: u32) -> Result<GifRecorder, Box<dyn Error>> {
+        check_scale(scale)?;
+        let width = (SCREEN_WIDTH as u32 * scale) as u16;
+        let height = (SCREEN_HEIGHT as u32 * scale) as u16;
+        let global_palette = [rgb(palette[0]), rgb(palette[1])].concat();
+        let mut encoder = Encoder::new(
+            BufWriter::new(File::create(file)?),
+            width,
+            height,
+            &global_palette,
+        )?;
+        encoder.set_repeat(Repeat::Infinite)?;
+        Ok(GifRecorder {
+            encoder,
+            scale,
+            width,
+            height,
+            pending: None,
+            pending_since: 0,
+            frames: 0,
+        })
+    }
+
+    pub fn push_frame(&mut self, video: &Video) -> Result<(), Box<dyn Error>> {
+        if self.pending.as_ref() != Some(video) {
+            self.flush()?;
+            self.pending = Some(*video);
+            self.pending_since = self.frames;
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        let video = match self.pending.take() {
+            Some(video) => video,
+            None => return Ok(()),
+        };
+        let buffer = scaled_indices(&video, self.scale);
+        let mut delay = ticks(self.frames) - ticks(self.pending_since);
+        // A screen held for longer than the longest GIF delay, almost 11
+        // minutes, is repeated for the rest of the time.
+        while delay > 0 {
+            let part = delay.min(u16::MAX as u64);
+            let frame = Frame {
+                width: self.width,
+                height: self.height,
+                delay: part as u16,
+                buffer: Cow::Borrowed(&buffer),
+                ..Frame::default()
+            };
+            self.encoder.write_frame(&frame)?;
+            delay -= part;
+        }
+        Ok(())
+    }
+}
+
+// GIF delays are in hundredths of a second.
+fn ticks(frames: u64) -> u64 {
+    (frames * GIF_TICKS_PER_SECOND + FRAMES_PER_SECOND / 2) / FRAMES_PER_SECOND
+}
+
+// Writes every emulated frame to an uncompressed YUV4MPEG2 stream at exactly
+// 60 fps. 4:4:4 sampling keeps the pixel edges sharp.
+pub struct Y4mWriter {
+    output: BufWriter<File>,
+    scale: u32,
+    // The Y, Cb and Cr values of the background and foreground colours.
+    colors: [[u8; 3]; 2],
+}
+
+impl Y4mWriter {
+    pub fn new(file: &str, palette: [u32; 2], scale: u32) -> Result<Y4mWriter, Box<dyn Error>> {
+        check_scale(scale)?;
+        let mut output = BufWriter::new(File::create(file)?);
+        writeln!(
+            output,
+            "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+            SCREEN_WIDTH as u32 * scale,
+            SCREEN_HEIGHT as u32 * scale,
+            FRAMES_PER_SECOND
+        )?;
+        Ok(Y4mWriter {
+            output,
+            scale,
+            colors: [ycbcr(palette[0]), ycbcr(palette[1])],
+        })
+    }
+
+    pub fn push_frame(&mut self, video: &Video) -> Result<(), Box<dyn Error>> {
+        let indices = scaled_indices(video, self.scale);
+        self.output.write_all(b"FRAME\n")?;
+        for plane in 0..3 {
+            let samples: Vec<u8> = indices
+                .iter()
+                .map(|&index| self.colors[index as usize][plane])
+                .collect();
+            self.output.write_all(&samples)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+// BT.601 with studio range, which is what players assume for Y4M.
+fn ycbcr(color: u32) -> [u8; 3] {
+    let [r, g, b] = rgb(color);
+    let (r, g, b) = (r as f64, g as f64, b as f64);
+    let y = 16.0 + (65.481 * r + 128.553 * g + 24.966 * b) / 255.0;
+    let cb = 128.0 + (-37.797 * r - 74.203 * g + 112.0 * b) / 255.0;
+    let cr = 128.0 + (112.0 * r - 93.786 * g - 18.214 * b) / 255.0;
+    [y.round() as u8, cb.round() as u8, cr.round() as u8]
+}
+
+// The beeper as a square wave, one frame's worth of samples at a time. The
+// phase carries over between frames so the tone has no clicks.
+#[derive(Default)]
+pub struct Beeper {
+    samples: u64,
+}
+
+impl Beeper {
+    pub fn new() -> Beeper {
+        Beeper::default()
+    }
+
+    pub fn frame(&mut self, sound_active: bool) -> [i16; SAMPLES_PER_FRAME] {
+        let mut samples = [0; SAMPLES_PER_FRAME];
+        for sample in samples.iter_mut() {
+            let half_period = self.samples * TONE_HZ as u64 * 2 / SAMPLE_RATE as u64;
+            *sample = if !sound_active {
+                0
+            } else if half_period.is_multiple_of(2) {
+                TONE_AMPLITUDE
+            } else {
+                -TONE_AMPLITUDE
+            };
+            self.samples += 1;
+        }
+        samples
+    }
+}
+
+// Writes the beeper as a 16-bit mono WAV file, one frame's worth of samples
+// per emulated frame, so it stays in sync with the Y4M stream.
+pub struct WavWriter {
+    output: BufWriter<File>,
+    beeper: Beeper,
+    samples: u32,
+}
+
+impl WavWriter {
+    pub fn new(file: &str) -> Result<WavWriter, Box<dyn Error>> {
+        let mut output = BufWriter::new(File::create(file)?);
+        // The sizes are filled in by finish.
+        write_wav_header(&mut output, 0)?;
+        Ok(WavWriter {
+            output,
+            beeper: Beeper::new(),
+            samples: 0,
+        })
+    }
+
+    pub fn push_frame(&mut self, sound_active: bool) -> Result<(), Box<dyn Error>> {
+        for sample in self.beeper.frame(sound_active).iter() {
+            self.output.write_all(&sample.to_le_bytes())?;
+        }
+        self.samples += SAMPLES_PER_FRAME as u32;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        self.output.seek(SeekFrom::Start(0))?;
+        write_wav_header(&mut self.output, self.samples * 2)?;
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+fn write_wav_header(output: &mut impl Write, data_size: u32) -> Result<(), Box<dyn Error>> {
+    output.write_all(b"RIFF")?;
+    output.write_all(&(WAV_HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+    output.write_all(b"WAVEfmt ")?;
+    output.write_all(&16u32.to_le_bytes())?;
+    // PCM, one channel.
+    output.write_all(&1u16.to_le_bytes())?;
+    output.write_all(&1u16.to_le_bytes())?;
+    output.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    output.write_all(&(SAMPLE_RATE * 2).to_le_bytes())?;
+    output.write_all(&2u16.to_le_bytes())?;
+    output.write_all(&16u16.to_le_bytes())?;
+    output.write_all(b"data")?;
+    output.write_all(&data_size.to_le_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use crate::chip8::Chip8;
+use crate::config;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+const CHEATS_DIR: &str = "cheats";
+
+#[derive(Clone, Copy)]
+pub enum Comparison {
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    Equals(u8),
+}
+
+impl Comparison {
+    pub fn parse(name: &str, value: Option<&str>) -> Option<Comparison> {
+        match name {
+            "changed" => Some(Comparison::Changed),
+            "unchanged" => Some(Comparison::Unchanged),
+            "increased" => Some(Comparison::Increased),
+            "decreased" => Some(Comparison::Decreased),
+            "equals" => parse_byte(value?).map(Comparison::Equals),
+            _ => None,
+        }
+    }
+
+    fn matches(self, previous: u8, current: u8) -> bool {
+        match self {
+            Comparison::Changed => current != previous,
+            Comparison::Unchanged => current == previous,
+            Comparison::Increased => current > previous,
+            Comparison::Decreased => current < previous,
+            Comparison::Equals(value) => current == value,
+        }
+    }
+}
+
+// Narrows memory down to the addresses whose values behave a certain way,
+// comparing each time against the memory seen by the previous filter.
+pub struct MemorySearch {
+    snapshot: Vec<u8>,
+    candidates: Vec<usize>,
+}
+
+impl MemorySearch {
+    pub fn new(cpu: &Chip8) -> MemorySearch {
+        MemorySearch {
+            snapshot: cpu.memory.clone(),
+            candidates: (0..cpu.memory.len()).collect(),
+        }
+    }
+
+    pub fn filter(&mut self, cpu: &Chip8, comparison: Comparison) -> usize {
+        let snapshot = &self.snapshot;
+        self.candidates
+            .retain(|&address| comparison.matches(snapshot[address], cpu.memory[address]));
+        self.snapshot.copy_from_slice(&cpu.memory);
+        self.candidates.len()
+    }
+
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Cheat {
+    pub address: u16,
+    pub value: u8,
+    // Frozen values are written back every frame; the others are written
+    // once when the cheat is added or the ROM is loaded.
+    pub freeze: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+// The cheats for one ROM, stored in the config directory by ROM hash. They
+// live outside the machine state, so save states never contain them and
+// loading a state keeps the current cheats active.
+pub struct Cheats {
+    file: Option<PathBuf>,
+    pub cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub fn empty() -> Cheats {
+        Cheats {
+            file: None,
+            cheats: Vec::new(),
+        }
+    }
+
+    pub fn load(rom_hash: &str) -> Result<Cheats, Box<dyn Error>> {
+        let file = config::config_file(CHEATS_DIR)
+            .map(|dir| dir.join(format!("{}.json", rom_hash.to_lowercase())));
+        let cheats = match &file {
+            Some(file) if file.exists() => {
+                let contents = fs::read_to_string(file)?;
+                serde_json::from_str(&contents).map_err(|e| format!("{}: {}", file.display(), e))?
+            }
+            _ => Vec::new(),
+        };
+        Ok(Cheats { file, cheats })
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(file, serde_json::to_string_pretty(&self.cheats)?)?;
+        Ok(())
+    }
+
+    // Replaces any cheat on the same address.
+    pub fn add(&mut self, cpu: &mut Chip8, cheat: Cheat) {
+        self.remove(cheat.address);
+        write(cpu, &cheat);
+        self.cheats.push(cheat);
+    }
+
+    pub fn remove(&mut self, address: u16) -> bool {
+        let count = self.cheats.len();
+        self.cheats.retain(|cheat| cheat.address != address);
+        self.cheats.len() != count
+    }
+
+    pub fn clear(&mut self) {
+        self.cheats.clear();
+    }
+
+    // Called once after the ROM is loaded.
+    pub fn apply_all(&self, cpu: &mut Chip8) {
+        for cheat in self.cheats.iter() {
+            write(cpu, cheat);
+        }
+    }
+
+    // Called before every frame, including the first after a state is loaded.
+    pub fn apply_frozen(&self, cpu: &mut Chip8) {
+        for cheat in self.cheats.iter().filter(|cheat| cheat.freeze) {
+            write(cpu, cheat);
+        }
+    }
+}
+
+fn write(cpu: &mut Chip8, cheat: &Cheat) {
+    let address = cpu.wrap_address(cheat.address as usize);
+    cpu.memory[address] = cheat.value;
+}
+
+fn parse_byte(value: &str) -> Option<u8> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
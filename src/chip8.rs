@@ -1,10 +1,20 @@
-use crate::graphics;
+use crate::fontset::{self, Font};
 use crate::instructions;
+use crate::quirks::{Platform, Quirks};
+use crate::sprites::DrawRecord;
+use rand::{random, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Result};
 
+pub const MEMORY_SIZE: usize = 0x1000;
+pub const PROGRAM_START: u16 = 0x200;
+pub const FONT_ADDRESS: u16 = 0x050;
+pub const SCREEN_WIDTH: usize = 64;
+pub const SCREEN_HEIGHT: usize = 32;
+
 pub struct Chip8 {
-    pub memory: [u8; 0xFFF],
+    pub memory: Vec<u8>,
     pub registers: [u8; 16],
     pub keys: [bool; 16],
     pub index: u16,
@@ -15,7 +25,31 @@ pub struct Chip8 {
     pub sound_timer: u8,
     pub instruction_fns: HashMap<u16, fn(&mut Chip8, OpCode)>,
     pub update_display: bool,
-    pub video: [[u8; graphics::SCREEN_WIDTH]; graphics::SCREEN_HEIGHT],
+    pub video: [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT],
+    pub seed: u64,
+    // The generator behind rand's StdRng, named so that save states can
+    // store how far into its stream it is.
+    pub rng: ChaCha20Rng,
+    pub quirks: Quirks,
+    pub tick_rate: u32,
+    pub vblank_wait: bool,
+    pub program_start: u16,
+    pub font_address: u16,
+    pub large_font_address: u16,
+    pub cycles: u64,
+    pub record_draws: bool,
+    pub draw_log: Vec<DrawRecord>,
+    // Set when an instruction could not run. The machine stays halted at it,
+    // with pc pointing at the instruction, until it is rebooted.
+    pub fault: Option<Fault>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fault {
+    // A call with all 16 return addresses in use.
+    StackOverflow,
+    // A return with no return address on the stack.
+    StackUnderflow,
 }
 
 pub struct OpCode {
@@ -24,37 +58,91 @@ pub struct OpCode {
     pub data: u16,
 }
 
+// A generator seeded with `seed` that has used `position` words of its
+// stream. Seeking also fills its buffer, which get_word_pos needs even at
+// the start of the stream.
+pub fn seeded_rng(seed: u64, position: u128) -> ChaCha20Rng {
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    rng.set_word_pos(position);
+    rng
+}
+
 impl Chip8 {
     pub fn new() -> Chip8 {
+        Chip8::with_seed(random())
+    }
+
+    pub fn with_seed(seed: u64) -> Chip8 {
         Chip8 {
-            memory: [0; 0xFFF],
+            memory: vec![0; MEMORY_SIZE],
             registers: [0; 16],
             keys: [false; 16],
             index: 0,
-            pc: 0x200,
+            pc: PROGRAM_START,
             sp: 0,
             stack: [0; 16],
             delay_timer: 0,
             sound_timer: 0,
             instruction_fns: instructions::create_opcode_instructions_map(),
             update_display: false,
-            video: [[0; graphics::SCREEN_WIDTH]; graphics::SCREEN_HEIGHT],
+            video: [[0; SCREEN_WIDTH]; SCREEN_HEIGHT],
+            seed,
+            rng: seeded_rng(seed, 0),
+            quirks: Quirks::default(),
+            tick_rate: Platform::ModernChip8.tick_rate(),
+            vblank_wait: false,
+            program_start: PROGRAM_START,
+            font_address: FONT_ADDRESS,
+            large_font_address: FONT_ADDRESS + fontset::SMALL_FONT_SIZE as u16,
+            cycles: 0,
+            record_draws: false,
+            draw_log: Vec::new(),
+            fault: None,
         }
     }
 
     pub fn get_next_opcode(&mut self) -> OpCode {
-        let opcode =
-            (self.memory[self.pc as usize] as u16) << 8 | self.memory[self.pc as usize + 1] as u16;
-        self.pc += 2;
+        let pc = self.pc as usize;
+        let opcode = (self.memory[self.wrap_address(pc)] as u16) << 8
+            | self.memory[self.wrap_address(pc + 1)] as u16;
+        self.pc = self.wrap_address(pc + 2) as u16;
         OpCode {
             code: opcode,
             decoded: opcode & 0xF000,
             data: opcode & 0x0FFF,
         }
     }
-    pub fn load_font_set(&mut self, fontset: [u8; 80]) {
-        for offset in 0..80 {
-            self.memory[0x050 + offset] = fontset[offset];
+    // Addresses past the end of memory wrap around to the start, so a large
+    // I or PC never reads or writes outside of it.
+    pub fn wrap_address(&self, address: usize) -> usize {
+        address % self.memory.len()
+    }
+
+    pub fn set_memory_layout(&mut self, memory_size: usize, program_start: u16, font_address: u16) {
+        self.memory = vec![0; memory_size];
+        self.program_start = program_start;
+        self.pc = program_start;
+        self.font_address = font_address;
+    }
+
+    pub fn load_font_set(&mut self, font: &Font) {
+        let start = self.font_address as usize;
+        let large_start = start + font.small.len();
+        self.memory[start..large_start].copy_from_slice(&font.small);
+        self.memory[large_start..large_start + font.large.len()].copy_from_slice(&font.large);
+        self.large_font_address = large_start as u16;
+    }
+
+    pub fn keys_mask(&self) -> u16 {
+        self.keys
+            .iter()
+            .enumerate()
+            .fold(0, |mask, (key, &pressed)| mask | (pressed as u16) << key)
+    }
+
+    pub fn set_keys_mask(&mut self, mask: u16) {
+        for (key, pressed) in self.keys.iter_mut().enumerate() {
+            *pressed = mask & (1 << key) != 0;
         }
     }
 
@@ -66,20 +154,63 @@ impl Chip8 {
             self.sound_timer -= 1;
         }
     }
-    pub fn run(&mut self) {
+    pub fn run_frame(&mut self) {
+        self.run_frame_until(|_| false);
+    }
+
+    // Runs a frame like run_frame, but checks stop before every instruction
+    // and returns true as soon as it asks to halt.
+    pub fn run_frame_until(&mut self, mut stop: impl FnMut(&Chip8) -> bool) -> bool {
+        if self.fault.is_some() {
+            return false;
+        }
+        self.draw_log.clear();
         self.update_timers();
+        for _ in 0..self.tick_rate {
+            if stop(self) {
+                return true;
+            }
+            self.run();
+            if self.fault.is_some() {
+                break;
+            }
+            if self.vblank_wait {
+                self.vblank_wait = false;
+                break;
+            }
+        }
+        false
+    }
+
+    // Does nothing once the machine has faulted.
+    pub fn run(&mut self) {
+        if self.fault.is_some() {
+            return;
+        }
+        self.cycles += 1;
         let opcode = self.get_next_opcode();
-        let handler_key = match opcode.decoded {
-            0x0000 | 0xE000 | 0xF000 => opcode.code & 0xF0FF,
-            0x8000 => opcode.code & 0xF00F,
-            code => code,
-        };
+        let handler_key = instructions::handler_key(opcode.code);
         if let Some(handler) = self.instruction_fns.get(&handler_key) {
             handler(self, opcode);
         }
     }
 }
 
+impl Default for Chip8 {
+    fn default() -> Chip8 {
+        Chip8::new()
+    }
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Fault::StackOverflow => write!(f, "stack overflow"),
+            Fault::StackUnderflow => write!(f, "stack underflow"),
+        }
+    }
+}
+
 impl Display for OpCode {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         write!(
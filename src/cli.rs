@@ -0,0 +1,190 @@
+use chip_8_emulator::capture;
+use chip_8_emulator::patch::PatchSource;
+use chip_8_emulator::watch::WatchMode;
+use std::error::Error;
+
+const DEFAULT_ROM: &str = "chip8-roms/TICTAC";
+const DEFAULT_ROM_DIR: &str = "chip8-roms";
+const DEFAULT_REWIND_MEMORY_MB: usize = 16;
+
+pub struct Options {
+    pub rom: String,
+    pub seed: Option<u64>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub rewind_memory: usize,
+    pub load_address: Option<u16>,
+    pub font_address: Option<u16>,
+    pub font: Option<String>,
+    pub font_file: Option<String>,
+    pub memory_viewer: bool,
+    pub hud: bool,
+    pub sprite_inspector: bool,
+    pub dump_sprites: Option<String>,
+    pub analyze: bool,
+    pub dot: Option<String>,
+    pub disassemble: bool,
+    pub gdb_port: Option<u16>,
+    pub profile: Option<String>,
+    pub tui: bool,
+    pub headless: bool,
+    pub frames: Option<usize>,
+    pub screenshot: Option<String>,
+    pub gif: Option<String>,
+    pub y4m: Option<String>,
+    pub wav: Option<String>,
+    pub scale: u32,
+    pub patch: PatchSource,
+    pub create_patch: Option<(String, String)>,
+    pub watch: Option<WatchMode>,
+    pub rom_dir: String,
+}
+
+pub fn parse(args: impl Iterator<Item = String>) -> Result<Options, Box<dyn Error>> {
+    let mut options = Options {
+        rom: DEFAULT_ROM.to_string(),
+        seed: None,
+        record: None,
+        replay: None,
+        rewind_memory: DEFAULT_REWIND_MEMORY_MB * 1024 * 1024,
+        load_address: None,
+        font_address: None,
+        font: None,
+        font_file: None,
+        memory_viewer: false,
+        hud: false,
+        sprite_inspector: false,
+        dump_sprites: None,
+        analyze: false,
+        dot: None,
+        disassemble: false,
+        gdb_port: None,
+        profile: None,
+        tui: false,
+        headless: false,
+        frames: None,
+        screenshot: None,
+        gif: None,
+        y4m: None,
+        wav: None,
+        scale: capture::DEFAULT_SCALE,
+        patch: PatchSource::Auto,
+        create_patch: None,
+        watch: None,
+        rom_dir: DEFAULT_ROM_DIR.to_string(),
+    };
+    let mut args = args.skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => options.seed = Some(next_value(&mut args, &arg)?.parse()?),
+            "--record" => options.record = Some(next_value(&mut args, &arg)?),
+            "--replay" => options.replay = Some(next_value(&mut args, &arg)?),
+            "--rewind-memory" => {
+                let megabytes: usize = next_value(&mut args, &arg)?.parse()?;
+                options.rewind_memory = megabytes * 1024 * 1024;
+            }
+            "--load-address" => {
+                options.load_address = Some(parse_address(&next_value(&mut args, &arg)?)?)
+            }
+            "--font-address" => {
+                options.font_address = Some(parse_address(&next_value(&mut args, &arg)?)?)
+            }
+            "--font" => options.font = Some(next_value(&mut args, &arg)?),
+            "--font-file" => options.font_file = Some(next_value(&mut args, &arg)?),
+            "--memory-viewer" => options.memory_viewer = true,
+            "--hud" => options.hud = true,
+            "--tui" => options.tui = true,
+            "--headless" => options.headless = true,
+            "--frames" => options.frames = Some(next_value(&mut args, &arg)?.parse()?),
+            "--screenshot" => options.screenshot = Some(next_value(&mut args, &arg)?),
+            "--gif" => options.gif = Some(next_value(&mut args, &arg)?),
+            "--y4m" => options.y4m = Some(next_value(&mut args, &arg)?),
+            "--wav" => options.wav = Some(next_value(&mut args, &arg)?),
+            "--patch" => options.patch = PatchSource::File(next_value(&mut args, &arg)?),
+            "--no-patch" => options.patch = PatchSource::None,
+            "--create-patch" => {
+                let modified = next_value(&mut args, &arg)?;
+                let output = next_value(&mut args, &arg)?;
+                options.create_patch = Some((modified, output));
+            }
+            "--watch" => {
+                let mode = next_value(&mut args, &arg)?;
+                options.watch = Some(WatchMode::parse(&mode).ok_or_else(|| {
+                    format!(
+                        "unknown watch mode {}, expected one of {}",
+                        mode,
+                        WatchMode::NAMES.join(", ")
+                    )
+                })?);
+            }
+            "--rom-dir" => options.rom_dir = next_value(&mut args, &arg)?,
+            "--scale" => options.scale = next_value(&mut args, &arg)?.parse()?,
+            "--sprite-inspector" => options.sprite_inspector = true,
+            "--analyze" => options.analyze = true,
+            "--dot" => options.dot = Some(next_value(&mut args, &arg)?),
+            "--disassemble" => options.disassemble = true,
+            "--gdb" => options.gdb_port = Some(next_value(&mut args, &arg)?.parse()?),
+            "--profile" => options.profile = Some(next_value(&mut args, &arg)?),
+            "--dump-sprites" => options.dump_sprites = Some(next_value(&mut args, &arg)?),
+            flag if flag.starts_with("--") => {
+                return Err(format!("unknown option {}", flag).into());
+            }
+            _ => options.rom = arg,
+        }
+    }
+    if options.font.is_some() && options.font_file.is_some() {
+        return Err("--font and --font-file cannot be used together".into());
+    }
+    if options.record.is_some() && options.replay.is_some() {
+        return Err("--record and --replay cannot be used together".into());
+    }
+    // A movie only plays back on the ROM it was recorded with, and a profile
+    // only describes one ROM.
+    if options.watch.is_some()
+        && (options.record.is_some() || options.replay.is_some() || options.profile.is_some())
+    {
+        return Err("--watch cannot be used with --record, --replay or --profile".into());
+    }
+    if options.tui && (options.memory_viewer || options.hud || options.sprite_inspector) {
+        return Err(
+            "--memory-viewer, --hud and --sprite-inspector are not available with --tui".into(),
+        );
+    }
+    if options.headless
+        && (options.tui || options.memory_viewer || options.hud || options.sprite_inspector)
+    {
+        return Err(
+            "--tui, --memory-viewer, --hud and --sprite-inspector are not available with --headless"
+                .into(),
+        );
+    }
+    if options.headless && options.frames.is_none() && options.replay.is_none() {
+        return Err("--headless needs --frames or --replay to know when to stop".into());
+    }
+    if options.frames.is_some() && !options.headless {
+        return Err("--frames is only available with --headless".into());
+    }
+    if options.scale == 0 || options.scale > capture::MAX_SCALE {
+        return Err(format!("--scale must be between 1 and {}", capture::MAX_SCALE).into());
+    }
+    Ok(options)
+}
+
+fn next_value(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<String, Box<dyn Error>> {
+    args.next()
+        .ok_or_else(|| format!("{} expects a value", flag).into())
+}
+
+fn parse_address(value: &str) -> Result<u16, Box<dyn Error>> {
+    let address = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16)?,
+        None => value.parse()?,
+    };
+    Ok(address)
+}
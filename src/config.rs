@@ -0,0 +1,15 @@
+use std::env;
+use std::path::PathBuf;
+
+const APP_DIR: &str = "chip-8-emulator";
+
+pub fn config_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join(APP_DIR)),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join(APP_DIR)),
+    }
+}
+
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(name))
+}
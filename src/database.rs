@@ -0,0 +1,223 @@
+use crate::chip8::{self, Chip8};
+use crate::config;
+use crate::quirks::{Platform, Quirks};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+const BUNDLED_DATABASE: &str = include_str!("../chip8-database/programs.json");
+const OVERRIDE_FILE: &str = "programs.json";
+pub const DEFAULT_PALETTE: [u32; 2] = [0x000000, 0xFFFFFF];
+
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    roms: HashMap<String, RomEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RomEntry {
+    #[serde(default)]
+    platforms: Vec<String>,
+    tickrate: Option<u32>,
+    #[serde(default)]
+    quirky_platforms: HashMap<String, QuirkOverrides>,
+    colors: Option<Colors>,
+    #[serde(default)]
+    keys: HashMap<String, u8>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuirkOverrides {
+    shift: Option<bool>,
+    memory_increment_by_x: Option<bool>,
+    memory_leave_i_unchanged: Option<bool>,
+    wrap: Option<bool>,
+    jump: Option<bool>,
+    vblank: Option<bool>,
+    logic: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct Colors {
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct RomSettings {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub platform: Option<Platform>,
+    pub quirks: Quirks,
+    pub tick_rate: u32,
+    pub palette: [u32; 2],
+    pub keys: Vec<(String, u8)>,
+    pub memory_size: usize,
+    pub program_start: u16,
+    pub font_address: u16,
+}
+
+impl Default for RomSettings {
+    fn default() -> RomSettings {
+        RomSettings {
+            title: None,
+            authors: Vec::new(),
+            platform: None,
+            quirks: Quirks::default(),
+            tick_rate: Platform::ModernChip8.tick_rate(),
+            palette: DEFAULT_PALETTE,
+            keys: Vec::new(),
+            memory_size: chip8::MEMORY_SIZE,
+            program_start: chip8::PROGRAM_START,
+            font_address: chip8::FONT_ADDRESS,
+        }
+    }
+}
+
+impl RomSettings {
+    pub fn apply(&self, cpu: &mut Chip8) {
+        cpu.set_memory_layout(self.memory_size, self.program_start, self.font_address);
+        cpu.quirks = self.quirks;
+        cpu.tick_rate = self.tick_rate;
+    }
+}
+
+// Settings by lowercase SHA-1, built once from each database file.
+type Index = HashMap<String, RomSettings>;
+type Parsed = Arc<Result<Index, String>>;
+
+static BUNDLED: OnceLock<Result<Index, String>> = OnceLock::new();
+static OVERRIDES: OnceLock<Mutex<HashMap<PathBuf, Parsed>>> = OnceLock::new();
+
+pub fn lookup(hash: &str) -> Result<Option<RomSettings>, Box<dyn Error>> {
+    let hash = hash.to_lowercase();
+    if let Some(file) = config::config_file(OVERRIDE_FILE) {
+        if file.exists() {
+            let overrides = overrides(file);
+            let overrides = overrides.as_ref().as_ref().map_err(String::as_str)?;
+            if let Some(settings) = overrides.get(&hash) {
+                return Ok(Some(settings.clone()));
+            }
+        }
+    }
+    let bundled = BUNDLED.get_or_init(|| index(BUNDLED_DATABASE));
+    let bundled = bundled.as_ref().map_err(String::as_str)?;
+    Ok(bundled.get(&hash).cloned())
+}
+
+// The override file is read the first time it is needed and kept, like the
+// bundled database, so browsing a directory of ROMs parses it once.
+fn overrides(file: PathBuf) -> Parsed {
+    let cache = OVERRIDES.get_or_init(Default::default);
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .entry(file)
+        .or_insert_with_key(|file| {
+            let index = fs::read_to_string(file)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| index(&contents));
+            Arc::new(index.map_err(|e| format!("{}: {}", file.display(), e)))
+        })
+        .clone()
+}
+
+fn index(json: &str) -> Result<Index, String> {
+    let programs: Vec<Program> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let mut index = Index::new();
+    for program in programs.iter() {
+        for (hash, rom) in program.roms.iter() {
+            // The first entry for a hash wins.
+            index
+                .entry(hash.to_lowercase())
+                .or_insert_with(|| settings_for(program, rom));
+        }
+    }
+    Ok(index)
+}
+
+fn settings_for(program: &Program, rom: &RomEntry) -> RomSettings {
+    let platform_id = rom
+        .platforms
+        .iter()
+        .find(|id| Platform::from_id(id).is_some());
+    let platform = platform_id.and_then(|id| Platform::from_id(id));
+    let mut quirks = platform.map_or_else(Quirks::default, Platform::quirks);
+    if let Some(overrides) = platform_id.and_then(|id| rom.quirky_platforms.get(id)) {
+        overrides.apply(&mut quirks);
+    }
+    let tick_rate = rom
+        .tickrate
+        .or_else(|| platform.map(Platform::tick_rate))
+        .unwrap_or_else(|| RomSettings::default().tick_rate);
+    let mut palette = DEFAULT_PALETTE;
+    if let Some(colors) = &rom.colors {
+        for (slot, color) in palette.iter_mut().zip(colors.pixels.iter()) {
+            if let Some(rgb) = parse_color(color) {
+                *slot = rgb;
+            }
+        }
+    }
+    let mut keys: Vec<(String, u8)> = rom
+        .keys
+        .iter()
+        .map(|(name, &key)| (name.clone(), key & 0xF))
+        .collect();
+    keys.sort();
+    RomSettings {
+        title: Some(program.title.clone()),
+        authors: program.authors.clone(),
+        platform,
+        quirks,
+        tick_rate,
+        palette,
+        keys,
+        memory_size: platform.map_or(chip8::MEMORY_SIZE, Platform::memory_size),
+        ..RomSettings::default()
+    }
+}
+
+impl QuirkOverrides {
+    fn apply(&self, quirks: &mut Quirks) {
+        let overrides = [
+            (self.shift, &mut quirks.shift),
+            (
+                self.memory_increment_by_x,
+                &mut quirks.memory_increment_by_x,
+            ),
+            (
+                self.memory_leave_i_unchanged,
+                &mut quirks.memory_leave_i_unchanged,
+            ),
+            (self.wrap, &mut quirks.wrap),
+            (self.jump, &mut quirks.jump),
+            (self.vblank, &mut quirks.vblank),
+            (self.logic, &mut quirks.logic),
+        ];
+        for (value, quirk) in overrides {
+            if let Some(value) = value {
+                *quirk = value;
+            }
+        }
+    }
+}
+
+fn parse_color(color: &str) -> Option<u32> {
+    let hex = color.trim_start_matches('#');
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    match hex.len() {
+        6 => Some(rgb),
+        3 => Some(
+            ((rgb >> 8) & 0xF) * 0x110000 + ((rgb >> 4) & 0xF) * 0x001100 + (rgb & 0xF) * 0x000011,
+        ),
+        _ => None,
+    }
+}
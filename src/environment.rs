@@ -0,0 +1,327 @@
+use crate::chip8::Chip8;
+use crate::config;
+use crate::fontset::Font;
+use crate::rom::Rom;
+use rand::rngs::StdRng;
+use rand::{random, Rng, SeedableRng};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+const BUNDLED_ENVIRONMENTS: &str = include_str!("../chip8-database/environments.json");
+const OVERRIDE_FILE: &str = "environments.json";
+
+// How to score and end an episode of one ROM, keyed by ROM hash in
+// environments.json.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentSpec {
+    #[serde(default)]
+    pub title: Option<String>,
+    // The reward for a step is the scaled change of each of these values.
+    #[serde(default)]
+    pub reward: Vec<RewardTerm>,
+    // The episode ends as soon as any of these holds.
+    #[serde(default)]
+    pub done: Vec<DoneCondition>,
+    // Key masks, one per action. Defaults to no key plus each single key.
+    #[serde(default)]
+    pub actions: Option<Vec<u16>>,
+    #[serde(default)]
+    pub max_frames: Option<u64>,
+}
+
+// A register, or a big-endian number of one or more bytes in memory. BCD
+// values are stored one decimal digit per byte, as FX33 writes them.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Value {
+    pub register: Option<usize>,
+    pub memory: Option<usize>,
+    #[serde(default = "one_byte")]
+    pub bytes: usize,
+    #[serde(default)]
+    pub bcd: bool,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct RewardTerm {
+    #[serde(flatten)]
+    pub value: Value,
+    #[serde(default = "unit_scale")]
+    pub scale: f64,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct DoneCondition {
+    #[serde(flatten)]
+    pub value: Value,
+    pub equals: Option<u64>,
+    pub below: Option<u64>,
+    pub above: Option<u64>,
+}
+
+#[derive(Clone, Copy)]
+pub struct EnvironmentOptions {
+    // Emulated frames per step, all with the same action.
+    pub frame_skip: u32,
+    // The chance that a frame keeps the previous action instead of the new
+    // one, as in the Arcade Learning Environment.
+    pub sticky_action_probability: f64,
+    // Seeds both CXNN and the sticky actions; random when missing.
+    pub seed: Option<u64>,
+}
+
+impl Default for EnvironmentOptions {
+    fn default() -> EnvironmentOptions {
+        EnvironmentOptions {
+            frame_skip: 4,
+            sticky_action_probability: 0.0,
+            seed: None,
+        }
+    }
+}
+
+pub struct StepResult {
+    pub reward: f64,
+    pub done: bool,
+    // Set when the episode ended because it reached max_frames.
+    pub truncated: bool,
+}
+
+// A reinforcement learning environment in the style of OpenAI Gym: reset
+// starts an episode, step applies an action and reports the reward.
+pub struct Environment {
+    rom: Rom,
+    spec: EnvironmentSpec,
+    options: EnvironmentOptions,
+    actions: Vec<u16>,
+    seeds: StdRng,
+    sticky: StdRng,
+    pub cpu: Chip8,
+    previous_action: u16,
+    previous_rewards: Vec<u64>,
+    pub frame: u64,
+    pub episode_seed: u64,
+}
+
+fn one_byte() -> usize {
+    1
+}
+
+fn unit_scale() -> f64 {
+    1.0
+}
+
+impl Value {
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        match (self.register, self.memory) {
+            (Some(register), None) if register < 16 => Ok(()),
+            (Some(register), None) => Err(format!("there is no register V{}", register).into()),
+            (None, Some(_)) if (1..=8).contains(&self.bytes) => Ok(()),
+            (None, Some(_)) => Err("a memory value is 1 to 8 bytes long".into()),
+            _ => Err("a value needs either a register or a memory address".into()),
+        }
+    }
+
+    pub fn read(&self, cpu: &Chip8) -> u64 {
+        if let Some(register) = self.register {
+            return cpu.registers[register] as u64;
+        }
+        let address = self.memory.unwrap_or(0);
+        (address..address + self.bytes)
+            .map(|address| cpu.memory[cpu.wrap_address(address)] as u64)
+            .fold(0, |value, byte| {
+                if self.bcd {
+                    value * 10 + byte % 10
+                } else {
+                    value << 8 | byte
+                }
+            })
+    }
+}
+
+impl DoneCondition {
+    fn holds(&self, cpu: &Chip8) -> bool {
+        let value = self.value.read(cpu);
+        self.equals == Some(value)
+            || self.below.is_some_and(|below| value < below)
+            || self.above.is_some_and(|above| value > above)
+    }
+}
+
+impl EnvironmentSpec {
+    pub fn from_json(json: &str) -> Result<EnvironmentSpec, Box<dyn Error>> {
+        let spec: EnvironmentSpec = serde_json::from_str(json)?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        for term in self.reward.iter() {
+            term.value.validate()?;
+        }
+        for condition in self.done.iter() {
+            condition.value.validate()?;
+        }
+        if self.actions.as_ref().is_some_and(Vec::is_empty) {
+            return Err("the action list is empty".into());
+        }
+        Ok(())
+    }
+}
+
+pub fn lookup(hash: &str) -> Result<Option<EnvironmentSpec>, Box<dyn Error>> {
+    let hash = hash.to_lowercase();
+    if let Some(file) = config::config_file(OVERRIDE_FILE) {
+        if file.exists() {
+            let contents = fs::read_to_string(&file)?;
+            if let Some(spec) =
+                find(&contents, &hash).map_err(|e| format!("{}: {}", file.display(), e))?
+            {
+                return Ok(Some(spec));
+            }
+        }
+    }
+    find(BUNDLED_ENVIRONMENTS, &hash)
+}
+
+fn find(json: &str, hash: &str) -> Result<Option<EnvironmentSpec>, Box<dyn Error>> {
+    let specs: HashMap<String, EnvironmentSpec> = serde_json::from_str(json)?;
+    let spec = specs
+        .into_iter()
+        .find(|(rom_hash, _)| rom_hash.to_lowercase() == hash)
+        .map(|(_, spec)| spec);
+    if let Some(spec) = &spec {
+        spec.validate()?;
+    }
+    Ok(spec)
+}
+
+impl Environment {
+    // Without a spec the one from environments.json is used, and a ROM
+    // without one gets no reward and only ends at max_frames.
+    pub fn new(
+        rom: Rom,
+        spec: Option<EnvironmentSpec>,
+        options: EnvironmentOptions,
+    ) -> Result<Environment, Box<dyn Error>> {
+        let spec = match spec {
+            Some(spec) => {
+                spec.validate()?;
+                spec
+            }
+            None => lookup(&rom.hash())?.unwrap_or_default(),
+        };
+        if options.frame_skip == 0 {
+            return Err("the frame skip must be at least 1".into());
+        }
+        if !(0.0..1.0).contains(&options.sticky_action_probability) {
+            return Err("the sticky action probability must be in [0, 1)".into());
+        }
+        let actions = spec.actions.clone().unwrap_or_else(|| {
+            (0..=16)
+                .map(|action| ((1u32 << action) >> 1) as u16)
+                .collect()
+        });
+        let seed = options.seed.unwrap_or_else(random);
+        let mut environment = Environment {
+            rom,
+            spec,
+            options,
+            actions,
+            seeds: StdRng::seed_from_u64(seed),
+            sticky: StdRng::seed_from_u64(seed),
+            cpu: Chip8::with_seed(seed),
+            previous_action: 0,
+            previous_rewards: Vec::new(),
+            frame: 0,
+            episode_seed: seed,
+        };
+        environment.reset(Some(seed))?;
+        Ok(environment)
+    }
+
+    pub fn actions(&self) -> &[u16] {
+        &self.actions
+    }
+
+    pub fn spec(&self) -> &EnvironmentSpec {
+        &self.spec
+    }
+
+    // Starts a new episode. Without a seed the next one comes from the seed
+    // the environment was created with, so a run of episodes is repeatable.
+    pub fn reset(&mut self, seed: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let seed = seed.unwrap_or_else(|| self.seeds.gen());
+        let mut cpu = Chip8::with_seed(seed);
+        self.rom.settings.apply(&mut cpu);
+        self.rom
+            .load_into_memory(&mut cpu.memory, cpu.program_start)?;
+        cpu.load_font_set(&Font::for_platform(self.rom.settings.platform));
+        self.cpu = cpu;
+        // Sticky actions get their own stream so they do not shift CXNN.
+        self.sticky = StdRng::seed_from_u64(!seed);
+        self.previous_action = 0;
+        self.previous_rewards = self.reward_values();
+        self.frame = 0;
+        self.episode_seed = seed;
+        Ok(())
+    }
+
+    pub fn step(&mut self, action: usize) -> Result<StepResult, Box<dyn Error>> {
+        let keys = *self.actions.get(action).ok_or_else(|| {
+            format!(
+                "action {} is out of range, there are {}",
+                action,
+                self.actions.len()
+            )
+        })?;
+        let mut result = StepResult {
+            reward: 0.0,
+            done: false,
+            truncated: false,
+        };
+        for _ in 0..self.options.frame_skip {
+            if self.sticky.gen::<f64>() >= self.options.sticky_action_probability {
+                self.previous_action = keys;
+            }
+            self.cpu.set_keys_mask(self.previous_action);
+            self.cpu.run_frame();
+            self.frame += 1;
+            let values = self.reward_values();
+            for ((term, &value), &previous) in self
+                .spec
+                .reward
+                .iter()
+                .zip(values.iter())
+                .zip(self.previous_rewards.iter())
+            {
+                result.reward += term.scale * (value as f64 - previous as f64);
+            }
+            self.previous_rewards = values;
+            // A program that faulted cannot go on, so the episode ends.
+            result.done =
+                self.cpu.fault.is_some() || self.spec.done.iter().any(|done| done.holds(&self.cpu));
+            result.truncated = !result.done
+                && self
+                    .spec
+                    .max_frames
+                    .is_some_and(|max_frames| self.frame >= max_frames);
+            if result.done || result.truncated {
+                result.done = true;
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    fn reward_values(&self) -> Vec<u64> {
+        self.spec
+            .reward
+            .iter()
+            .map(|term| term.value.read(&self.cpu))
+            .collect()
+    }
+}
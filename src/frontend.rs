@@ -0,0 +1,37 @@
+use chip_8_emulator::chip8::Chip8;
+
+#[derive(Clone)]
+pub enum Hotkey {
+    Quit,
+    Pause,
+    Screenshot,
+    ToggleRecording,
+    // Chosen in the ROM browser of the SDL frontend.
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    Open(String),
+}
+
+// What the main loop needs from a display: keypad input, hotkeys and a
+// place to draw the screen. Everything else runs the same for all of them.
+pub trait Frontend {
+    // Updates the CPU keypad and returns the hotkeys pressed since the last call.
+    fn capture(&mut self, cpu: &mut Chip8) -> Vec<Hotkey>;
+
+    fn is_rewind_held(&self) -> bool;
+
+    // The machine waits while a menu covers the screen.
+    fn is_menu_open(&self) -> bool {
+        false
+    }
+
+    // Frontends that are not watched by anyone run faster than 60 Hz.
+    fn is_realtime(&self) -> bool {
+        true
+    }
+
+    fn present(&mut self, cpu: &Chip8);
+
+    // Called after every emulated frame; returns true when the frontend needs
+    // to present even though the CPU did not draw.
+    fn end_frame(&mut self, cpu: &Chip8) -> bool;
+}
@@ -0,0 +1,469 @@
+use chip_8_emulator::chip8::Chip8;
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+// Register numbers as seen by the debugger: V0-VF, then I, PC, SP and the
+// two timers. I and PC are 16 bits wide and sent little-endian.
+const INDEX_REGISTER: usize = 16;
+const PC_REGISTER: usize = 17;
+const SP_REGISTER: usize = 18;
+const DELAY_TIMER_REGISTER: usize = 19;
+const SOUND_TIMER_REGISTER: usize = 20;
+const REGISTER_COUNT: usize = 21;
+const PACKET_SIZE: usize = 0x1000;
+const INTERRUPT: u8 = 0x03;
+const SIGINT: &str = "S02";
+const SIGTRAP: &str = "S05";
+const SIGSEGV: &str = "S0B";
+
+pub enum GdbEvent {
+    Halted,
+    Running,
+    Kill,
+}
+
+pub struct GdbStub {
+    listener: TcpListener,
+    connection: Option<Connection>,
+    breakpoints: HashSet<u16>,
+    running: bool,
+    step_over: bool,
+}
+
+struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    acknowledge: bool,
+}
+
+impl GdbStub {
+    // Blocks until a debugger connects, so it can set breakpoints before the
+    // first instruction runs.
+    pub fn listen(port: u16) -> Result<GdbStub, Box<dyn Error>> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        println!("waiting for gdb on 127.0.0.1:{}", port);
+        let mut stub = GdbStub {
+            listener,
+            connection: None,
+            breakpoints: HashSet::new(),
+            running: false,
+            step_over: false,
+        };
+        stub.accept()?;
+        stub.listener.set_nonblocking(true)?;
+        Ok(stub)
+    }
+
+    fn accept(&mut self) -> Result<(), Box<dyn Error>> {
+        let (stream, address) = self.listener.accept()?;
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        println!("gdb connected from {}", address);
+        self.connection = Some(Connection {
+            stream,
+            buffer: Vec::new(),
+            acknowledge: true,
+        });
+        self.running = false;
+        Ok(())
+    }
+
+    pub fn poll(&mut self, cpu: &mut Chip8) -> Result<GdbEvent, Box<dyn Error>> {
+        if self.connection.is_none() {
+            match self.accept() {
+                Ok(()) => {}
+                Err(error) if is_would_block(error.as_ref()) => return Ok(GdbEvent::Running),
+                Err(error) => return Err(error),
+            }
+        }
+        let closed = match &mut self.connection {
+            Some(connection) => connection.receive()?,
+            None => false,
+        };
+        while let Some(packet) = self.connection.as_mut().and_then(Connection::next_packet) {
+            match packet {
+                Packet::Interrupt => {
+                    if self.running {
+                        self.running = false;
+                        self.send(SIGINT)?;
+                    }
+                }
+                Packet::Command(command) => {
+                    if let Some(event) = self.execute(&command, cpu)? {
+                        return Ok(event);
+                    }
+                }
+            }
+        }
+        if closed {
+            self.detach();
+        }
+        Ok(if self.running {
+            GdbEvent::Running
+        } else {
+            GdbEvent::Halted
+        })
+    }
+
+    // Runs one frame, stopping at the first breakpoint and reporting it.
+    // observe sees every instruction that is about to run.
+    pub fn run_frame(
+        &mut self,
+        cpu: &mut Chip8,
+        mut observe: impl FnMut(&Chip8),
+    ) -> Result<(), Box<dyn Error>> {
+        let breakpoints = &self.breakpoints;
+        let step_over = &mut self.step_over;
+        let hit = cpu.run_frame_until(|cpu| {
+            let skip = *step_over;
+            *step_over = false;
+            if !skip && breakpoints.contains(&cpu.pc) {
+                return true;
+            }
+            observe(cpu);
+            false
+        });
+        if hit {
+            self.running = false;
+            self.send(SIGTRAP)?;
+        } else if cpu.fault.is_some() {
+            self.running = false;
+            self.send(SIGSEGV)?;
+        }
+        Ok(())
+    }
+
+    fn detach(&mut self) {
+        self.connection = None;
+        self.running = true;
+        println!("gdb detached");
+    }
+
+    fn send(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        if let Some(connection) = &mut self.connection {
+            connection.send(data)?;
+        }
+        Ok(())
+    }
+
+    fn execute(
+        &mut self,
+        command: &str,
+        cpu: &mut Chip8,
+    ) -> Result<Option<GdbEvent>, Box<dyn Error>> {
+        let kind = command.get(..1).unwrap_or_default();
+        let arguments = command.get(1..).unwrap_or_default();
+        let reply = match kind {
+            "?" => stop_reason(cpu).to_string(),
+            "g" => (0..REGISTER_COUNT)
+                .map(|register| read_register(cpu, register))
+                .collect(),
+            "G" => write_registers(cpu, arguments),
+            "p" => match usize::from_str_radix(arguments, 16) {
+                Ok(register) if register < REGISTER_COUNT => read_register(cpu, register),
+                _ => "E01".to_string(),
+            },
+            "P" => write_register_command(cpu, arguments),
+            "m" => read_memory(cpu, arguments),
+            "M" => write_memory(cpu, arguments),
+            "c" | "s" => {
+                if let Ok(address) = u16::from_str_radix(arguments, 16) {
+                    cpu.pc = address;
+                }
+                if kind == "s" {
+                    cpu.run();
+                    stop_reason(cpu).to_string()
+                } else {
+                    self.running = true;
+                    self.step_over = true;
+                    return Ok(Some(GdbEvent::Running));
+                }
+            }
+            "Z" | "z" => self.update_breakpoint(kind == "Z", arguments),
+            "k" => {
+                self.connection = None;
+                return Ok(Some(GdbEvent::Kill));
+            }
+            "D" => {
+                self.send("OK")?;
+                self.detach();
+                return Ok(Some(GdbEvent::Running));
+            }
+            "H" | "T" => "OK".to_string(),
+            "q" | "Q" => self.query(command),
+            _ => String::new(),
+        };
+        self.send(&reply)?;
+        Ok(None)
+    }
+
+    fn query(&mut self, command: &str) -> String {
+        if command.starts_with("qSupported") {
+            format!(
+                "PacketSize={:X};QStartNoAckMode+;qXfer:features:read+",
+                PACKET_SIZE
+            )
+        } else if command == "QStartNoAckMode" {
+            if let Some(connection) = &mut self.connection {
+                connection.acknowledge = false;
+            }
+            "OK".to_string()
+        } else if let Some(range) = command.strip_prefix("qXfer:features:read:target.xml:") {
+            transfer(&target_description(), range)
+        } else {
+            match command {
+                "qAttached" => "1".to_string(),
+                "qC" => "QC1".to_string(),
+                "qfThreadInfo" => "m1".to_string(),
+                "qsThreadInfo" => "l".to_string(),
+                _ => String::new(),
+            }
+        }
+    }
+
+    fn update_breakpoint(&mut self, insert: bool, arguments: &str) -> String {
+        let mut fields = arguments.split(',');
+        let kind = fields.next();
+        let address = fields.next().and_then(|a| u16::from_str_radix(a, 16).ok());
+        match (kind, address) {
+            (Some("0"), Some(address)) | (Some("1"), Some(address)) => {
+                if insert {
+                    self.breakpoints.insert(address);
+                } else {
+                    self.breakpoints.remove(&address);
+                }
+                "OK".to_string()
+            }
+            (Some("0"), None) | (Some("1"), None) => "E01".to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+enum Packet {
+    Interrupt,
+    Command(String),
+}
+
+impl Connection {
+    // Reads whatever is available without blocking; returns true once the
+    // debugger has closed the socket.
+    fn receive(&mut self) -> Result<bool, Box<dyn Error>> {
+        let mut chunk = [0; 1024];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Ok(true),
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(false),
+                Err(error) if error.kind() == ErrorKind::ConnectionReset => return Ok(true),
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    fn next_packet(&mut self) -> Option<Packet> {
+        loop {
+            match *self.buffer.first()? {
+                INTERRUPT => {
+                    self.buffer.remove(0);
+                    return Some(Packet::Interrupt);
+                }
+                b'$' => break,
+                // Acknowledgements and line noise between packets.
+                _ => {
+                    self.buffer.remove(0);
+                }
+            }
+        }
+        let end = self.buffer.iter().position(|&byte| byte == b'#')?;
+        if self.buffer.len() < end + 3 {
+            return None;
+        }
+        let packet: Vec<u8> = self.buffer.drain(..end + 3).collect();
+        let data = &packet[1..end];
+        let valid = std::str::from_utf8(&packet[end + 1..])
+            .ok()
+            .and_then(|checksum| u8::from_str_radix(checksum, 16).ok())
+            == Some(checksum(data));
+        if self.acknowledge {
+            let ack: &[u8] = if valid { b"+" } else { b"-" };
+            let _ = self.stream.write_all(ack);
+        }
+        if !valid {
+            return self.next_packet();
+        }
+        Some(Packet::Command(String::from_utf8_lossy(data).into_owned()))
+    }
+
+    fn send(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        let packet = format!("${}#{:02x}", data, checksum(data.as_bytes()));
+        self.stream.set_nonblocking(false)?;
+        let written = self.stream.write_all(packet.as_bytes());
+        self.stream.set_nonblocking(true)?;
+        written?;
+        Ok(())
+    }
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum: u8, &byte| sum.wrapping_add(byte))
+}
+
+// A machine that faulted reports it as a segmentation fault.
+fn stop_reason(cpu: &Chip8) -> &'static str {
+    if cpu.fault.is_some() {
+        SIGSEGV
+    } else {
+        SIGTRAP
+    }
+}
+
+fn is_would_block(error: &(dyn Error + 'static)) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|error| error.kind() == ErrorKind::WouldBlock)
+}
+
+fn register_width(register: usize) -> usize {
+    match register {
+        INDEX_REGISTER | PC_REGISTER => 2,
+        _ => 1,
+    }
+}
+
+fn read_register(cpu: &Chip8, register: usize) -> String {
+    match register {
+        INDEX_REGISTER => to_hex(&cpu.index.to_le_bytes()),
+        PC_REGISTER => to_hex(&cpu.pc.to_le_bytes()),
+        SP_REGISTER => to_hex(&[cpu.sp]),
+        DELAY_TIMER_REGISTER => to_hex(&[cpu.delay_timer]),
+        SOUND_TIMER_REGISTER => to_hex(&[cpu.sound_timer]),
+        _ => to_hex(&[cpu.registers[register]]),
+    }
+}
+
+fn write_register(cpu: &mut Chip8, register: usize, bytes: &[u8]) {
+    match register {
+        INDEX_REGISTER => cpu.index = u16::from_le_bytes([bytes[0], bytes[1]]),
+        PC_REGISTER => cpu.pc = u16::from_le_bytes([bytes[0], bytes[1]]),
+        SP_REGISTER => cpu.sp = bytes[0].min(cpu.stack.len() as u8),
+        DELAY_TIMER_REGISTER => cpu.delay_timer = bytes[0],
+        SOUND_TIMER_REGISTER => cpu.sound_timer = bytes[0],
+        _ => cpu.registers[register] = bytes[0],
+    }
+}
+
+fn write_registers(cpu: &mut Chip8, arguments: &str) -> String {
+    let bytes = match from_hex(arguments) {
+        Some(bytes) => bytes,
+        None => return "E01".to_string(),
+    };
+    let mut offset = 0;
+    for register in 0..REGISTER_COUNT {
+        let width = register_width(register);
+        if offset + width > bytes.len() {
+            break;
+        }
+        write_register(cpu, register, &bytes[offset..offset + width]);
+        offset += width;
+    }
+    "OK".to_string()
+}
+
+fn write_register_command(cpu: &mut Chip8, arguments: &str) -> String {
+    let (register, value) = match arguments.split_once('=') {
+        Some(parts) => parts,
+        None => return "E01".to_string(),
+    };
+    match (usize::from_str_radix(register, 16), from_hex(value)) {
+        (Ok(register), Some(bytes))
+            if register < REGISTER_COUNT && bytes.len() == register_width(register) =>
+        {
+            write_register(cpu, register, &bytes);
+            "OK".to_string()
+        }
+        _ => "E01".to_string(),
+    }
+}
+
+fn memory_range(cpu: &Chip8, range: &str) -> Option<(usize, usize)> {
+    let (address, length) = range.split_once(',')?;
+    let address = usize::from_str_radix(address, 16).ok()?;
+    let length = usize::from_str_radix(length, 16).ok()?;
+    if address.checked_add(length)? > cpu.memory.len() {
+        return None;
+    }
+    Some((address, length))
+}
+
+fn read_memory(cpu: &Chip8, arguments: &str) -> String {
+    match memory_range(cpu, arguments) {
+        Some((address, length)) => to_hex(&cpu.memory[address..address + length]),
+        None => "E01".to_string(),
+    }
+}
+
+fn write_memory(cpu: &mut Chip8, arguments: &str) -> String {
+    let (range, data) = match arguments.split_once(':') {
+        Some(parts) => parts,
+        None => return "E01".to_string(),
+    };
+    match (memory_range(cpu, range), from_hex(data)) {
+        (Some((address, length)), Some(bytes)) if bytes.len() == length => {
+            cpu.memory[address..address + length].copy_from_slice(&bytes);
+            cpu.update_display = true;
+            "OK".to_string()
+        }
+        _ => "E01".to_string(),
+    }
+}
+
+fn target_description() -> String {
+    let mut registers: Vec<String> = (0..16)
+        .map(|v| format!("<reg name=\"v{:x}\" bitsize=\"8\" type=\"uint8\"/>", v))
+        .collect();
+    registers.push("<reg name=\"i\" bitsize=\"16\" type=\"data_ptr\"/>".to_string());
+    registers.push("<reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\"/>".to_string());
+    registers.push("<reg name=\"sp\" bitsize=\"8\" type=\"uint8\"/>".to_string());
+    registers.push("<reg name=\"dt\" bitsize=\"8\" type=\"uint8\"/>".to_string());
+    registers.push("<reg name=\"st\" bitsize=\"8\" type=\"uint8\"/>".to_string());
+    format!(
+        "<?xml version=\"1.0\"?><!DOCTYPE target SYSTEM \"gdb-target.dtd\">\
+         <target version=\"1.0\"><feature name=\"org.chip8.core\">{}</feature></target>",
+        registers.concat()
+    )
+}
+
+fn transfer(document: &str, range: &str) -> String {
+    match range.split_once(',').and_then(|(offset, length)| {
+        Some((
+            usize::from_str_radix(offset, 16).ok()?,
+            usize::from_str_radix(length, 16).ok()?,
+        ))
+    }) {
+        // Reading at or past the end is how a debugger learns it has it all.
+        Some((offset, _)) if offset >= document.len() => "l".to_string(),
+        Some((offset, length)) => {
+            let end = offset.saturating_add(length).min(document.len());
+            let more = if end < document.len() { "m" } else { "l" };
+            format!("{}{}", more, &document[offset..end])
+        }
+        _ => "E01".to_string(),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -1,11 +1,11 @@
+use crate::text;
+use chip_8_emulator::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::WindowCanvas;
 use sdl2::Sdl;
 use std::error::Error;
 
-pub const SCREEN_WIDTH: usize = 64;
-pub const SCREEN_HEIGHT: usize = 32;
 const SCALE_FACTOR: u32 = 10;
 
 pub fn create_window(title: &str, context: &Sdl) -> Result<WindowCanvas, Box<dyn Error>> {
@@ -21,26 +21,49 @@ pub fn create_window(title: &str, context: &Sdl) -> Result<WindowCanvas, Box<dyn
     Ok(window.into_canvas().build()?)
 }
 
-pub fn create_default_screen(canvas: &mut WindowCanvas) {
-    canvas.set_draw_color(Color::BLACK);
+fn palette_color(rgb: u32) -> Color {
+    Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+pub fn create_default_screen(canvas: &mut WindowCanvas, palette: [u32; 2]) {
+    canvas.set_draw_color(palette_color(palette[0]));
     canvas.clear();
     canvas.present();
 }
 
-pub fn update_screen(canvas: &mut WindowCanvas, video_buffer: [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT]) {
+pub fn draw_video(
+    canvas: &mut WindowCanvas,
+    video_buffer: [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT],
+    palette: [u32; 2],
+) {
     canvas.clear();
     for (y, pixels) in video_buffer.iter().enumerate() {
         for (x, &pixel) in pixels.iter().enumerate() {
             let x = (x as u32) * SCALE_FACTOR;
             let y = (y as u32) * SCALE_FACTOR;
 
-            if pixel == 1 {
-                canvas.set_draw_color(Color::WHITE);
-            } else {
-                canvas.set_draw_color(Color::BLACK);
-            }
+            canvas.set_draw_color(palette_color(palette[pixel as usize & 1]));
             let _ = canvas.fill_rect(Rect::new(x as i32, y as i32, SCALE_FACTOR, SCALE_FACTOR));
         }
     }
-    canvas.present();
+}
+
+pub fn draw_text(canvas: &mut WindowCanvas, line: &str, x: i32, y: i32, scale: u32, color: Color) {
+    canvas.set_draw_color(color);
+    let advance = ((text::GLYPH_WIDTH + 1) * scale) as i32;
+    for (column, character) in line.chars().enumerate() {
+        let glyph_x = x + column as i32 * advance;
+        for (row, bits) in text::glyph(character).iter().enumerate() {
+            for bit in 0..text::GLYPH_WIDTH {
+                if bits >> (text::GLYPH_WIDTH - 1 - bit) & 1 == 1 {
+                    let _ = canvas.fill_rect(Rect::new(
+                        glyph_x + (bit * scale) as i32,
+                        y + (row as u32 * scale) as i32,
+                        scale,
+                        scale,
+                    ));
+                }
+            }
+        }
+    }
 }
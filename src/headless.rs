@@ -0,0 +1,40 @@
+use crate::frontend::{Frontend, Hotkey};
+use chip_8_emulator::chip8::Chip8;
+
+// Runs without a display or input as fast as the host allows, for rendering
+// captures and replays. Keys only come from a replayed movie.
+pub struct HeadlessFrontend {
+    frame: usize,
+    frames: usize,
+}
+
+impl HeadlessFrontend {
+    pub fn new(frames: usize) -> HeadlessFrontend {
+        HeadlessFrontend { frame: 0, frames }
+    }
+}
+
+impl Frontend for HeadlessFrontend {
+    fn capture(&mut self, _cpu: &mut Chip8) -> Vec<Hotkey> {
+        if self.frame >= self.frames {
+            vec![Hotkey::Quit]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn is_rewind_held(&self) -> bool {
+        false
+    }
+
+    fn is_realtime(&self) -> bool {
+        false
+    }
+
+    fn present(&mut self, _cpu: &Chip8) {}
+
+    fn end_frame(&mut self, _cpu: &Chip8) -> bool {
+        self.frame += 1;
+        false
+    }
+}
@@ -0,0 +1,108 @@
+use crate::graphics;
+use crate::text;
+use chip_8_emulator::chip8::Chip8;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, WindowCanvas};
+use std::time::{Duration, Instant};
+
+const SCALE: u32 = 2;
+const LINE_HEIGHT: i32 = ((text::GLYPH_HEIGHT + 2) * SCALE) as i32;
+const MARGIN: i32 = 4;
+const TEXT_COLOR: Color = Color::RGB(0x40, 0xFF, 0x40);
+const BACKGROUND: Color = Color::RGBA(0, 0, 0, 0xB0);
+
+pub struct Hud {
+    pub visible: bool,
+    sample_start: Instant,
+    sample_frames: u32,
+    sample_cycles: u64,
+    fps: u32,
+    ips: u64,
+}
+
+impl Hud {
+    pub fn new(visible: bool) -> Hud {
+        Hud {
+            visible,
+            sample_start: Instant::now(),
+            sample_frames: 0,
+            sample_cycles: 0,
+            fps: 0,
+            ips: 0,
+        }
+    }
+
+    pub fn end_frame(&mut self, cpu: &Chip8) {
+        self.sample_frames += 1;
+        let elapsed = self.sample_start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            let seconds = elapsed.as_secs_f64();
+            self.fps = (self.sample_frames as f64 / seconds).round() as u32;
+            self.ips = ((cpu.cycles - self.sample_cycles) as f64 / seconds).round() as u64;
+            self.sample_start = Instant::now();
+            self.sample_frames = 0;
+            self.sample_cycles = cpu.cycles;
+        }
+    }
+
+    pub fn draw(&self, canvas: &mut WindowCanvas, cpu: &Chip8) {
+        let mut lines = Vec::new();
+        for row in cpu.registers.chunks(4).enumerate() {
+            let (offset, values) = row;
+            let line: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(column, value)| format!("V{:X} {:02X}", offset * 4 + column, value))
+                .collect();
+            lines.push(line.join("  "));
+        }
+        lines.push(format!(
+            "I {:03X}  PC {:03X}  SP {:X}",
+            cpu.index, cpu.pc, cpu.sp
+        ));
+        lines.push(format!(
+            "DT {:02X}  ST {:02X}",
+            cpu.delay_timer, cpu.sound_timer
+        ));
+        let stack: Vec<String> = cpu.stack[..cpu.sp as usize]
+            .iter()
+            .map(|address| format!("{:03X}", address))
+            .collect();
+        for (row, chunk) in stack.chunks(8).enumerate() {
+            let label = if row == 0 { "STACK" } else { "     " };
+            lines.push(format!("{} {}", label, chunk.join(" ")));
+        }
+        if stack.is_empty() {
+            lines.push("STACK -".to_string());
+        }
+        let keys: Vec<String> = cpu
+            .keys
+            .iter()
+            .enumerate()
+            .filter(|(_, &pressed)| pressed)
+            .map(|(key, _)| format!("{:X}", key))
+            .collect();
+        lines.push(format!(
+            "KEYS {}",
+            if keys.is_empty() {
+                "-".to_string()
+            } else {
+                keys.join(" ")
+            }
+        ));
+        lines.push(format!("FPS {}  IPS {}", self.fps, self.ips));
+
+        let longest = lines.iter().map(|line| line.len()).max().unwrap_or(0) as u32;
+        let width = longest * (text::GLYPH_WIDTH + 1) * SCALE + 2 * MARGIN as u32;
+        let height = lines.len() as u32 * LINE_HEIGHT as u32 + 2 * MARGIN as u32;
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(BACKGROUND);
+        let _ = canvas.fill_rect(Rect::new(0, 0, width, height));
+        canvas.set_blend_mode(BlendMode::None);
+        for (row, line) in lines.iter().enumerate() {
+            let y = MARGIN + row as i32 * LINE_HEIGHT;
+            graphics::draw_text(canvas, line, MARGIN, y, SCALE, TEXT_COLOR);
+        }
+    }
+}
@@ -1,212 +1,151 @@
-use crate::chip8::Chip8;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use chip_8_emulator::chip8::Chip8;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Scancode};
 use sdl2::EventPump;
+use std::collections::HashMap;
 
-pub fn capture(events: &mut EventPump, cpu: &mut Chip8) -> bool {
-    let polled_event: Option<Event> = events.poll_event();
-    match polled_event {
-        Some(event) => match event {
+pub type Keymap = HashMap<Keycode, usize>;
+
+#[derive(Clone, Copy)]
+pub enum Shortcut {
+    Quit,
+    Pause,
+    ToggleHud,
+    Screenshot,
+    ToggleRecording,
+    OpenBrowser,
+    // A key pressed while the ROM browser is open, kept from the CPU.
+    BrowserKey(Keycode),
+    CloseWindow(u32),
+}
+
+const DEFAULT_LAYOUT: [(Keycode, usize); 16] = [
+    (Keycode::Num1, 0x1),
+    (Keycode::Num2, 0x2),
+    (Keycode::Num3, 0x3),
+    (Keycode::Num4, 0xC),
+    (Keycode::Q, 0x4),
+    (Keycode::W, 0x5),
+    (Keycode::E, 0x6),
+    (Keycode::R, 0xD),
+    (Keycode::A, 0x7),
+    (Keycode::S, 0x8),
+    (Keycode::D, 0x9),
+    (Keycode::F, 0xE),
+    (Keycode::Z, 0xA),
+    (Keycode::X, 0x0),
+    (Keycode::C, 0xB),
+    (Keycode::V, 0xF),
+];
+
+pub fn create_keymap(rom_keys: &[(String, u8)]) -> Keymap {
+    let mut keymap: Keymap = DEFAULT_LAYOUT.iter().copied().collect();
+    for (button, key) in rom_keys {
+        let keycode = match button.as_str() {
+            "up" => Keycode::Up,
+            "down" => Keycode::Down,
+            "left" => Keycode::Left,
+            "right" => Keycode::Right,
+            "a" => Keycode::Space,
+            "b" => Keycode::LShift,
+            "player2Up" => Keycode::Kp8,
+            "player2Down" => Keycode::Kp2,
+            "player2Left" => Keycode::Kp4,
+            "player2Right" => Keycode::Kp6,
+            "player2A" => Keycode::Kp0,
+            "player2B" => Keycode::KpEnter,
+            _ => continue,
+        };
+        keymap.insert(keycode, *key as usize);
+    }
+    keymap
+}
+
+pub fn capture(
+    events: &mut EventPump,
+    cpu: &mut Chip8,
+    keymap: &Keymap,
+    browsing: bool,
+) -> Vec<Shortcut> {
+    let mut shortcuts = Vec::new();
+    for event in events.poll_iter() {
+        match event {
             Event::Quit { .. } => {
-                return true;
-            }
-            Event::KeyDown {
-                keycode: Some(Keycode::Num1),
-                ..
-            } => {
-                cpu.keys[0x1] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::Num1),
-                ..
-            } => {
-                cpu.keys[0x1] = false;
+                shortcuts.push(Shortcut::Quit);
             }
-            Event::KeyDown {
-                keycode: Some(Keycode::Num2),
-                ..
-            } => {
-                cpu.keys[0x2] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::Num2),
+            Event::Window {
+                window_id,
+                win_event: WindowEvent::Close,
                 ..
             } => {
-                cpu.keys[0x2] = false;
+                shortcuts.push(Shortcut::CloseWindow(window_id));
             }
             Event::KeyDown {
-                keycode: Some(Keycode::Num3),
+                keycode: Some(keycode),
                 ..
-            } => {
-                cpu.keys[0x3] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::Num3),
-                ..
-            } => {
-                cpu.keys[0x3] = false;
+            } if browsing => {
+                shortcuts.push(Shortcut::BrowserKey(keycode));
             }
+            Event::KeyUp { .. } if browsing => {}
             Event::KeyDown {
-                keycode: Some(Keycode::Num4),
-                ..
-            } => {
-                cpu.keys[0xC] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::Num4),
+                keycode: Some(Keycode::Escape),
+                repeat: false,
                 ..
             } => {
-                cpu.keys[0xC] = false;
+                shortcuts.push(Shortcut::OpenBrowser);
             }
             Event::KeyDown {
-                keycode: Some(Keycode::Q),
-                ..
-            } => {
-                cpu.keys[0x4] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::Q),
+                keycode: Some(Keycode::P),
+                repeat: false,
                 ..
             } => {
-                cpu.keys[0x4] = false;
+                shortcuts.push(Shortcut::Pause);
             }
             Event::KeyDown {
-                keycode: Some(Keycode::W),
+                keycode: Some(Keycode::F1),
+                repeat: false,
                 ..
             } => {
-                cpu.keys[0x5] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::W),
-                ..
-            } => {
-                cpu.keys[0x5] = false;
+                shortcuts.push(Shortcut::ToggleHud);
             }
             Event::KeyDown {
-                keycode: Some(Keycode::E),
-                ..
-            } => {
-                cpu.keys[0x6] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::E),
+                keycode: Some(Keycode::F12),
+                repeat: false,
                 ..
             } => {
-                cpu.keys[0x6] = false;
+                shortcuts.push(Shortcut::Screenshot);
             }
             Event::KeyDown {
-                keycode: Some(Keycode::R),
+                keycode: Some(Keycode::F10),
+                repeat: false,
                 ..
             } => {
-                cpu.keys[0xD] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::R),
-                ..
-            } => {
-                cpu.keys[0xD] = false;
+                shortcuts.push(Shortcut::ToggleRecording);
             }
             Event::KeyDown {
-                keycode: Some(Keycode::A),
+                keycode: Some(keycode),
                 ..
             } => {
-                cpu.keys[0x7] = true;
+                if let Some(&key) = keymap.get(&keycode) {
+                    cpu.keys[key] = true;
+                }
             }
             Event::KeyUp {
-                keycode: Some(Keycode::A),
+                keycode: Some(keycode),
                 ..
             } => {
-                cpu.keys[0x7] = false;
-            }
-            Event::KeyDown {
-                keycode: Some(Keycode::S),
-                ..
-            } => {
-                cpu.keys[0x8] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::S),
-                ..
-            } => {
-                cpu.keys[0x8] = false;
-            }
-            Event::KeyDown {
-                keycode: Some(Keycode::D),
-                ..
-            } => {
-                cpu.keys[0x9] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::D),
-                ..
-            } => {
-                cpu.keys[0x9] = false;
-            }
-            Event::KeyDown {
-                keycode: Some(Keycode::F),
-                ..
-            } => {
-                cpu.keys[0xE] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::F),
-                ..
-            } => {
-                cpu.keys[0xE] = false;
-            }
-            Event::KeyDown {
-                keycode: Some(Keycode::Z),
-                ..
-            } => {
-                cpu.keys[0xA] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::Z),
-                ..
-            } => {
-                cpu.keys[0xA] = false;
-            }
-            Event::KeyDown {
-                keycode: Some(Keycode::X),
-                ..
-            } => {
-                cpu.keys[0x0] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::X),
-                ..
-            } => {
-                cpu.keys[0x0] = false;
-            }
-            Event::KeyDown {
-                keycode: Some(Keycode::C),
-                ..
-            } => {
-                cpu.keys[0xB] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::C),
-                ..
-            } => {
-                cpu.keys[0xB] = false;
-            }
-            Event::KeyDown {
-                keycode: Some(Keycode::V),
-                ..
-            } => {
-                cpu.keys[0xF] = true;
-            }
-            Event::KeyUp {
-                keycode: Some(Keycode::V),
-                ..
-            } => {
-                cpu.keys[0xF] = false;
+                if let Some(&key) = keymap.get(&keycode) {
+                    cpu.keys[key] = false;
+                }
             }
             _ => {}
-        },
-        None => {
-            return false;
         }
     }
-    false
+    shortcuts
+}
+
+pub fn is_rewind_held(events: &EventPump) -> bool {
+    events
+        .keyboard_state()
+        .is_scancode_pressed(Scancode::Backspace)
 }
@@ -0,0 +1,116 @@
+use crate::graphics;
+use chip_8_emulator::chip8::Chip8;
+use chip_8_emulator::sprites::{self, SpriteReference};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+use sdl2::Sdl;
+use std::error::Error;
+
+const COLUMNS: u32 = 8;
+const MAX_ROWS: u32 = 8;
+const PIXEL_SCALE: u32 = 4;
+const CELL_WIDTH: u32 = 80;
+const CELL_HEIGHT: u32 = 80;
+const LABEL_SCALE: u32 = 2;
+const LOG_LINES: usize = 8;
+const LOG_LINE_HEIGHT: u32 = 14;
+const WINDOW_WIDTH: u32 = COLUMNS * CELL_WIDTH;
+const BACKGROUND: Color = Color::RGB(0x20, 0x20, 0x20);
+const LABEL_COLOR: Color = Color::RGB(0xA0, 0xA0, 0xA0);
+const DRAWN_COLOR: Color = Color::RGB(0x40, 0xFF, 0x40);
+const COLLISION_COLOR: Color = Color::RGB(0xFF, 0x40, 0x40);
+
+pub struct SpriteInspector {
+    canvas: WindowCanvas,
+    references: Vec<SpriteReference>,
+}
+
+impl SpriteInspector {
+    pub fn new(context: &Sdl, cpu: &Chip8) -> Result<SpriteInspector, Box<dyn Error>> {
+        let references = sprites::find_sprite_references(
+            &cpu.memory,
+            cpu.program_start as usize,
+            cpu.memory.len(),
+        );
+        let rows = (references.len() as u32)
+            .div_ceil(COLUMNS)
+            .clamp(1, MAX_ROWS);
+        let height = rows * CELL_HEIGHT + LOG_LINES as u32 * LOG_LINE_HEIGHT + 8;
+        let window = context
+            .video()?
+            .window("sprite inspector", WINDOW_WIDTH, height)
+            .build()?;
+        Ok(SpriteInspector {
+            canvas: window.into_canvas().build()?,
+            references,
+        })
+    }
+
+    pub fn window_id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    pub fn draw(&mut self, cpu: &Chip8) {
+        self.canvas.set_draw_color(BACKGROUND);
+        self.canvas.clear();
+        let visible = (COLUMNS * MAX_ROWS) as usize;
+        for (slot, reference) in self.references.iter().take(visible).enumerate() {
+            let cell_x = (slot as u32 % COLUMNS * CELL_WIDTH) as i32;
+            let cell_y = (slot as u32 / COLUMNS * CELL_HEIGHT) as i32;
+            let drawn = cpu
+                .draw_log
+                .iter()
+                .filter(|draw| draw.address == reference.address)
+                .map(|draw| draw.collision)
+                .reduce(|a, b| a || b);
+            let label_color = match drawn {
+                Some(true) => COLLISION_COLOR,
+                Some(false) => DRAWN_COLOR,
+                None => LABEL_COLOR,
+            };
+            let label = format!("{:03X}", reference.address);
+            graphics::draw_text(
+                &mut self.canvas,
+                &label,
+                cell_x + 4,
+                cell_y + 4,
+                LABEL_SCALE,
+                label_color,
+            );
+            self.canvas.set_draw_color(Color::WHITE);
+            let rows = sprites::sprite_rows(&cpu.memory, reference.address, reference.height);
+            for (row, bits) in rows.iter().enumerate() {
+                for bit in 0..8 {
+                    if bits >> (7 - bit) & 1 == 1 {
+                        let _ = self.canvas.fill_rect(Rect::new(
+                            cell_x + 4 + (bit * PIXEL_SCALE) as i32,
+                            cell_y + 18 + (row as u32 * PIXEL_SCALE) as i32,
+                            PIXEL_SCALE,
+                            PIXEL_SCALE,
+                        ));
+                    }
+                }
+            }
+        }
+        let log_top = self.canvas.window().size().1 - LOG_LINES as u32 * LOG_LINE_HEIGHT - 4;
+        for (line, draw) in cpu.draw_log.iter().rev().take(LOG_LINES).enumerate() {
+            let text = format!(
+                "DRAW {:03X} X {:02} Y {:02} H {:02} {}",
+                draw.address,
+                draw.x,
+                draw.y,
+                draw.height,
+                if draw.collision { "COLLISION" } else { "" }
+            );
+            let color = if draw.collision {
+                COLLISION_COLOR
+            } else {
+                LABEL_COLOR
+            };
+            let y = (log_top + line as u32 * LOG_LINE_HEIGHT) as i32;
+            graphics::draw_text(&mut self.canvas, &text, 4, y, LABEL_SCALE, color);
+        }
+        self.canvas.present();
+    }
+}
@@ -1,5 +1,5 @@
-use crate::chip8::{Chip8, OpCode};
-use crate::graphics;
+use crate::chip8::{self, Chip8, Fault, OpCode};
+use crate::sprites::DrawRecord;
 use rand::prelude::*;
 use std::collections::HashMap;
 
@@ -17,51 +17,100 @@ fn get_vx_and_number(opcode: &OpCode) -> (usize, u8) {
     (vx as usize, number as u8)
 }
 
+fn reset_vf_if_logic_quirk(cpu: &mut Chip8) {
+    if cpu.quirks.logic {
+        cpu.registers[VF] = 0;
+    }
+}
+
+fn shift_source(cpu: &Chip8, vx: usize, vy: usize) -> u8 {
+    if cpu.quirks.shift {
+        cpu.registers[vx]
+    } else {
+        cpu.registers[vy]
+    }
+}
+
+fn increment_index_after_memory_access(cpu: &mut Chip8, vx: usize) {
+    if cpu.quirks.memory_leave_i_unchanged {
+        return;
+    }
+    if cpu.quirks.memory_increment_by_x {
+        cpu.index = cpu.index.wrapping_add(vx as u16);
+    } else {
+        cpu.index = cpu.index.wrapping_add(vx as u16 + 1);
+    }
+}
+
+fn skip_next_instruction(cpu: &mut Chip8) {
+    cpu.pc = cpu.wrap_address(cpu.pc as usize + 2) as u16;
+}
+
+fn memory_at_index(cpu: &Chip8, offset: usize) -> usize {
+    cpu.wrap_address(cpu.index as usize + offset)
+}
+
+// sp counts the return addresses on the stack. A call with the stack full
+// or a return with it empty faults instead of running.
 fn call_subroutine(cpu: &mut Chip8, opcode: OpCode) {
+    if cpu.sp as usize == cpu.stack.len() {
+        halt(cpu, Fault::StackOverflow);
+        return;
+    }
     cpu.stack[cpu.sp as usize] = cpu.pc;
     cpu.sp += 1;
-    cpu.pc = opcode.data;
+    cpu.pc = cpu.wrap_address(opcode.data as usize) as u16;
+}
+
+// Halts with pc back on the instruction that faulted.
+fn halt(cpu: &mut Chip8, fault: Fault) {
+    cpu.pc = cpu.wrap_address(cpu.pc as usize + cpu.memory.len() - 2) as u16;
+    cpu.fault = Some(fault);
 }
 
 fn clear_screen(cpu: &mut Chip8, _opcode: OpCode) {
-    cpu.video = [[0; graphics::SCREEN_WIDTH]; graphics::SCREEN_HEIGHT];
+    cpu.video = [[0; chip8::SCREEN_WIDTH]; chip8::SCREEN_HEIGHT];
     cpu.update_display = true;
 }
 
 fn return_from_subroutine(cpu: &mut Chip8, _opcode: OpCode) {
+    if cpu.sp == 0 {
+        halt(cpu, Fault::StackUnderflow);
+        return;
+    }
     cpu.sp -= 1;
     cpu.pc = cpu.stack[cpu.sp as usize];
 }
 
 fn jump_to_address(cpu: &mut Chip8, opcode: OpCode) {
-    cpu.pc = opcode.data;
+    cpu.pc = cpu.wrap_address(opcode.data as usize) as u16;
 }
 
 fn jump_if_reg_values_are_equal(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, vy) = get_vx_and_vy(&opcode);
     if cpu.registers[vx] == cpu.registers[vy] {
-        cpu.pc += 2;
+        skip_next_instruction(cpu);
     }
 }
 
 fn jump_if_reg_value_is_equal_to_number(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, number) = get_vx_and_number(&opcode);
     if cpu.registers[vx] == number {
-        cpu.pc += 2;
+        skip_next_instruction(cpu);
     }
 }
 
 fn jump_if_reg_value_is_not_equal_to_number(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, number) = get_vx_and_number(&opcode);
     if cpu.registers[vx] != number {
-        cpu.pc += 2;
+        skip_next_instruction(cpu);
     }
 }
 
 fn jump_if_reg_values_are_not_equal(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, vy) = get_vx_and_vy(&opcode);
     if cpu.registers[vx] != cpu.registers[vy] {
-        cpu.pc += 2;
+        skip_next_instruction(cpu);
     }
 }
 
@@ -83,61 +132,55 @@ fn add_to_register_and_ignore_carry_flag(cpu: &mut Chip8, opcode: OpCode) {
 fn bitwise_or_and_store(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, vy) = get_vx_and_vy(&opcode);
     cpu.registers[vx] |= cpu.registers[vy];
+    reset_vf_if_logic_quirk(cpu);
 }
 
 fn bitwise_and_and_store(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, vy) = get_vx_and_vy(&opcode);
     cpu.registers[vx] &= cpu.registers[vy];
+    reset_vf_if_logic_quirk(cpu);
 }
 
 fn bitwise_xor_and_store(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, vy) = get_vx_and_vy(&opcode);
     cpu.registers[vx] ^= cpu.registers[vy];
+    reset_vf_if_logic_quirk(cpu);
 }
 
 fn bitwise_shif_right_and_store(cpu: &mut Chip8, opcode: OpCode) {
-    let (vx, _) = get_vx_and_vy(&opcode);
-    cpu.registers[VF] = cpu.registers[vx] & 0x1;
-    cpu.registers[vx] >>= 1;
+    let (vx, vy) = get_vx_and_vy(&opcode);
+    let number = shift_source(cpu, vx, vy);
+    cpu.registers[vx] = number >> 1;
+    cpu.registers[VF] = number & 0x1;
 }
 
 fn add_and_store(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, vy) = get_vx_and_vy(&opcode);
     let sum = cpu.registers[vx] as u16 + cpu.registers[vy] as u16;
-
-    if sum > std::u8::MAX as u16 {
-        cpu.registers[VF] = 1;
-    } else {
-        cpu.registers[VF] = 0;
-    }
+    // The flag is written after the result, so it wins when VX is VF.
     cpu.registers[vx] = (sum & 0xFF) as u8;
+    cpu.registers[VF] = (sum > u8::MAX as u16) as u8;
 }
 
 fn subtract_and_store(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, vy) = get_vx_and_vy(&opcode);
-    if cpu.registers[vx] > cpu.registers[vy] {
-        cpu.registers[VF] = 1;
-    } else {
-        cpu.registers[VF] = 0;
-    }
+    let no_borrow = cpu.registers[vx] >= cpu.registers[vy];
     cpu.registers[vx] = cpu.registers[vx].wrapping_sub(cpu.registers[vy]);
+    cpu.registers[VF] = no_borrow as u8;
 }
 
 fn subtract_and_store_and_set_vf(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, vy) = get_vx_and_vy(&opcode);
-    if cpu.registers[vy] > cpu.registers[vx] {
-        cpu.registers[VF] = 1;
-    } else {
-        cpu.registers[VF] = 0;
-    }
+    let no_borrow = cpu.registers[vy] >= cpu.registers[vx];
     cpu.registers[vx] = cpu.registers[vy].wrapping_sub(cpu.registers[vx]);
+    cpu.registers[VF] = no_borrow as u8;
 }
 
 fn store_msb_and_left_shift(cpu: &mut Chip8, opcode: OpCode) {
-    let (vx, _) = get_vx_and_vy(&opcode);
-    let number = cpu.registers[vx];
+    let (vx, vy) = get_vx_and_vy(&opcode);
+    let number = shift_source(cpu, vx, vy);
+    cpu.registers[vx] = number << 1;
     cpu.registers[VF] = number >> 7;
-    cpu.registers[vx] <<= 1;
 }
 
 fn set_index_to_mem_address(cpu: &mut Chip8, opcode: OpCode) {
@@ -145,11 +188,17 @@ fn set_index_to_mem_address(cpu: &mut Chip8, opcode: OpCode) {
 }
 
 fn jump_to_address_plus_v0(cpu: &mut Chip8, opcode: OpCode) {
-    cpu.pc = cpu.registers[0] as u16 + (opcode.code & 0x0FFF);
+    let register = if cpu.quirks.jump {
+        get_vx_and_vy(&opcode).0
+    } else {
+        0
+    };
+    let address = cpu.registers[register] as usize + (opcode.code & 0x0FFF) as usize;
+    cpu.pc = cpu.wrap_address(address) as u16;
 }
 
 fn bitwise_on_a_random_number_and_store(cpu: &mut Chip8, opcode: OpCode) {
-    let random_number: u8 = random();
+    let random_number: u8 = cpu.rng.gen();
     let (vx, number) = get_vx_and_number(&opcode);
     cpu.registers[vx] = random_number & number;
 }
@@ -157,31 +206,56 @@ fn bitwise_on_a_random_number_and_store(cpu: &mut Chip8, opcode: OpCode) {
 fn draw_sprite(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, vy) = get_vx_and_vy(&opcode);
     let height = opcode.code & 0x000F;
+    // VF may be one of the coordinates, so it is only cleared once they are read.
+    let start_x = cpu.registers[vx] as u16 % chip8::SCREEN_WIDTH as u16;
+    let start_y = cpu.registers[vy] as u16 % chip8::SCREEN_HEIGHT as u16;
     cpu.registers[VF] = 0;
 
     for byte in 0..height {
-        let y = (cpu.registers[vy] as u16 + byte).rem_euclid(graphics::SCREEN_HEIGHT as u16);
+        let y = start_y + byte;
+        if y >= chip8::SCREEN_HEIGHT as u16 && !cpu.quirks.wrap {
+            break;
+        }
+        let y = y.rem_euclid(chip8::SCREEN_HEIGHT as u16);
         for bit in 0..8 {
-            let x = (cpu.registers[vx] as u16 + bit).rem_euclid(graphics::SCREEN_WIDTH as u16);
-            let color = (cpu.memory[(cpu.index + byte) as usize] >> (7 - bit)) & 1;
+            let x = start_x + bit;
+            if x >= chip8::SCREEN_WIDTH as u16 && !cpu.quirks.wrap {
+                break;
+            }
+            let x = x.rem_euclid(chip8::SCREEN_WIDTH as u16);
+            let color = (cpu.memory[memory_at_index(cpu, byte as usize)] >> (7 - bit)) & 1;
             cpu.registers[VF] |= color & cpu.video[y as usize][x as usize];
             cpu.video[y as usize][x as usize] ^= color;
         }
     }
+    if cpu.record_draws {
+        cpu.draw_log.push(DrawRecord {
+            x: start_x as u8,
+            y: start_y as u8,
+            height: height as u8,
+            address: cpu.index,
+            collision: cpu.registers[VF] == 1,
+        });
+    }
     cpu.update_display = true;
+    cpu.vblank_wait = cpu.quirks.vblank;
+}
+
+// The key is the value of VX; only its low nibble names a key.
+fn key_in_vx(cpu: &Chip8, opcode: &OpCode) -> bool {
+    let (vx, _) = get_vx_and_vy(opcode);
+    cpu.keys[(cpu.registers[vx] & 0xF) as usize]
 }
 
 fn jump_if_key_is_pressed(cpu: &mut Chip8, opcode: OpCode) {
-    let (vx, _) = get_vx_and_vy(&opcode);
-    if cpu.keys[vx] {
-        cpu.pc += 2;
+    if key_in_vx(cpu, &opcode) {
+        skip_next_instruction(cpu);
     }
 }
 
 fn jump_if_key_is_not_pressed(cpu: &mut Chip8, opcode: OpCode) {
-    let (vx, _) = get_vx_and_vy(&opcode);
-    if !cpu.keys[vx] {
-        cpu.pc += 2;
+    if !key_in_vx(cpu, &opcode) {
+        skip_next_instruction(cpu);
     }
 }
 
@@ -192,12 +266,12 @@ fn get_delay_timer_and_set_to_vx(cpu: &mut Chip8, opcode: OpCode) {
 
 fn set_delay_timer_to_vx(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, _) = get_vx_and_vy(&opcode);
-    cpu.delay_timer = vx as u8;
+    cpu.delay_timer = cpu.registers[vx];
 }
 
 fn set_sound_timer_to_vx(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, _) = get_vx_and_vy(&opcode);
-    cpu.sound_timer = vx as u8;
+    cpu.sound_timer = cpu.registers[vx];
 }
 
 fn get_key_press_and_store(cpu: &mut Chip8, opcode: OpCode) {
@@ -208,12 +282,12 @@ fn get_key_press_and_store(cpu: &mut Chip8, opcode: OpCode) {
             return;
         }
     }
-    cpu.pc -= 2;
+    cpu.pc = cpu.wrap_address(cpu.pc as usize + cpu.memory.len() - 2) as u16;
 }
 
 fn add_vx_to_i_and_set_overflow(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, _) = get_vx_and_vy(&opcode);
-    let sum = vx as u16 + cpu.index;
+    let sum = cpu.index.wrapping_add(cpu.registers[vx] as u16);
     if sum > 0xFFF {
         cpu.registers[VF] = 1;
     } else {
@@ -224,31 +298,48 @@ fn add_vx_to_i_and_set_overflow(cpu: &mut Chip8, opcode: OpCode) {
 
 fn set_i_to_location_of_sprite_in_vx(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, _) = get_vx_and_vy(&opcode);
-    let number = cpu.registers[vx] as u16;
-    cpu.index = 0x50 + (5 * number);
+    let number = (cpu.registers[vx] & 0xF) as u16;
+    cpu.index = cpu.font_address.wrapping_add(5 * number);
+}
+
+fn set_i_to_location_of_large_sprite_in_vx(cpu: &mut Chip8, opcode: OpCode) {
+    let (vx, _) = get_vx_and_vy(&opcode);
+    let number = (cpu.registers[vx] & 0xF) as u16;
+    cpu.index = cpu.large_font_address.wrapping_add(10 * number);
 }
 
 fn store_binary_coded_decimal_representaion_of_vx(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, _) = get_vx_and_vy(&opcode);
     let mut value = cpu.registers[vx];
-    cpu.memory[cpu.index as usize + 2] = value.rem_euclid(10);
-    value = value.div_euclid(10);
-    cpu.memory[cpu.index as usize + 1] = value.rem_euclid(10);
-    value = value.div_euclid(10);
-    cpu.memory[cpu.index as usize] = value.rem_euclid(10);
+    for offset in (0..3).rev() {
+        let address = memory_at_index(cpu, offset);
+        cpu.memory[address] = value.rem_euclid(10);
+        value = value.div_euclid(10);
+    }
 }
 
 fn store_v0_to_vx_starting_at_address_i(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, _) = get_vx_and_vy(&opcode);
     for v_index in 0..=vx {
-        cpu.memory[cpu.index as usize + v_index] = cpu.registers[v_index];
+        let address = memory_at_index(cpu, v_index);
+        cpu.memory[address] = cpu.registers[v_index];
     }
+    increment_index_after_memory_access(cpu, vx);
 }
 
 fn fill_v0_to_vx_starting_at_address_i(cpu: &mut Chip8, opcode: OpCode) {
     let (vx, _) = get_vx_and_vy(&opcode);
     for v_index in 0..=vx {
-        cpu.registers[v_index] = cpu.memory[cpu.index as usize + v_index];
+        cpu.registers[v_index] = cpu.memory[memory_at_index(cpu, v_index)];
+    }
+    increment_index_after_memory_access(cpu, vx);
+}
+
+pub fn handler_key(code: u16) -> u16 {
+    match code & 0xF000 {
+        0x0000 | 0xE000 | 0xF000 => code & 0xF0FF,
+        0x8000 => code & 0xF00F,
+        decoded => decoded,
     }
 }
 
@@ -285,6 +376,7 @@ pub fn create_opcode_instructions_map() -> HashMap<u16, fn(&mut Chip8, OpCode)>
     map.insert(0xF018, set_sound_timer_to_vx);
     map.insert(0xF01E, add_vx_to_i_and_set_overflow);
     map.insert(0xF029, set_i_to_location_of_sprite_in_vx);
+    map.insert(0xF030, set_i_to_location_of_large_sprite_in_vx);
     map.insert(0xF033, store_binary_coded_decimal_representaion_of_vx);
     map.insert(0xF055, store_v0_to_vx_starting_at_address_i);
     map.insert(0xF065, fill_v0_to_vx_starting_at_address_i);
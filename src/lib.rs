@@ -0,0 +1,25 @@
+pub mod analysis;
+pub mod capture;
+pub mod cheats;
+pub mod chip8;
+pub mod config;
+pub mod database;
+pub mod disassembler;
+pub mod environment;
+pub mod fontset;
+pub mod instructions;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+pub mod movie;
+pub mod patch;
+pub mod profiler;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod quirks;
+pub mod recent;
+pub mod rom;
+pub mod sprites;
+pub mod state;
+pub mod watch;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
@@ -0,0 +1,568 @@
+// The libretro core, built into the cdylib with --features libretro.
+// Frontends call these functions from one thread at a time, so the core and
+// its callbacks live in globals behind a mutex.
+#![allow(clippy::missing_safety_doc)]
+
+use crate::capture::{Beeper, SAMPLES_PER_FRAME, SAMPLE_RATE};
+use crate::cheats::{Cheat, Cheats};
+use crate::chip8::{Chip8, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::database::RomSettings;
+use crate::fontset::Font;
+use crate::quirks::Platform;
+use crate::rom::Rom;
+use crate::state;
+use std::error::Error;
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_char;
+use std::path::Path;
+use std::slice;
+use std::sync::Mutex;
+
+const API_VERSION: u32 = 1;
+
+const ENVIRONMENT_SET_PIXEL_FORMAT: u32 = 10;
+const ENVIRONMENT_GET_VARIABLE: u32 = 15;
+const ENVIRONMENT_SET_VARIABLES: u32 = 16;
+const ENVIRONMENT_GET_VARIABLE_UPDATE: u32 = 17;
+const PIXEL_FORMAT_XRGB8888: u32 = 1;
+
+const DEVICE_JOYPAD: u32 = 1;
+const DEVICE_KEYBOARD: u32 = 3;
+const DEVICE_ID_JOYPAD_B: u32 = 0;
+const DEVICE_ID_JOYPAD_UP: u32 = 4;
+const DEVICE_ID_JOYPAD_DOWN: u32 = 5;
+const DEVICE_ID_JOYPAD_LEFT: u32 = 6;
+const DEVICE_ID_JOYPAD_RIGHT: u32 = 7;
+const DEVICE_ID_JOYPAD_A: u32 = 8;
+const MEMORY_SYSTEM_RAM: u32 = 2;
+const REGION_NTSC: u32 = 0;
+
+const FRAMES_PER_SECOND: f64 = 60.0;
+const PLATFORM_VARIABLE: &[u8] = b"chip8_platform\0";
+const IPS_VARIABLE: &[u8] = b"chip8_ips\0";
+const VARIABLES: [(&[u8], &[u8]); 2] = [
+    (
+        PLATFORM_VARIABLE,
+        b"Quirks profile; auto|originalChip8|hybridVIP|modernChip8|chip48|superchip1|superchip|xochip\0",
+    ),
+    (
+        IPS_VARIABLE,
+        b"Instructions per second; auto|600|720|900|1200|1800|3000|6000|12000|30000|60000\0",
+    ),
+];
+
+// Keyboard keycodes match ASCII for digits and lowercase letters.
+const KEYBOARD_LAYOUT: [(u8, usize); 16] = [
+    (b'1', 0x1),
+    (b'2', 0x2),
+    (b'3', 0x3),
+    (b'4', 0xC),
+    (b'q', 0x4),
+    (b'w', 0x5),
+    (b'e', 0x6),
+    (b'r', 0xD),
+    (b'a', 0x7),
+    (b's', 0x8),
+    (b'd', 0x9),
+    (b'f', 0xE),
+    (b'z', 0xA),
+    (b'x', 0x0),
+    (b'c', 0xB),
+    (b'v', 0xF),
+];
+
+pub type EnvironmentFn = extern "C" fn(cmd: u32, data: *mut c_void) -> bool;
+pub type VideoRefreshFn = extern "C" fn(data: *const c_void, width: u32, height: u32, pitch: usize);
+pub type AudioSampleFn = extern "C" fn(left: i16, right: i16);
+pub type AudioSampleBatchFn = extern "C" fn(data: *const i16, frames: usize) -> usize;
+pub type InputPollFn = extern "C" fn();
+pub type InputStateFn = extern "C" fn(port: u32, device: u32, index: u32, id: u32) -> i16;
+
+#[repr(C)]
+pub struct SystemInfo {
+    pub library_name: *const c_char,
+    pub library_version: *const c_char,
+    pub valid_extensions: *const c_char,
+    pub need_fullpath: bool,
+    pub block_extract: bool,
+}
+
+#[repr(C)]
+pub struct GameGeometry {
+    pub base_width: u32,
+    pub base_height: u32,
+    pub max_width: u32,
+    pub max_height: u32,
+    pub aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct SystemTiming {
+    pub fps: f64,
+    pub sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct SystemAvInfo {
+    pub geometry: GameGeometry,
+    pub timing: SystemTiming,
+}
+
+#[repr(C)]
+pub struct GameInfo {
+    pub path: *const c_char,
+    pub data: *const c_void,
+    pub size: usize,
+    pub meta: *const c_char,
+}
+
+#[repr(C)]
+pub struct Variable {
+    pub key: *const c_char,
+    pub value: *const c_char,
+}
+
+#[derive(Clone, Copy)]
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+struct Core {
+    name: String,
+    bytes: Vec<u8>,
+    settings: RomSettings,
+    cpu: Chip8,
+    // (port, joypad button, CHIP-8 key) from the database key names.
+    joypad: Vec<(u32, u32, usize)>,
+    framebuffer: Vec<u32>,
+    beeper: Beeper,
+    cheats: Cheats,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+fn callbacks() -> Callbacks {
+    *CALLBACKS.lock().unwrap()
+}
+
+fn environment(cmd: u32, data: *mut c_void) -> bool {
+    match callbacks().environment {
+        Some(environment) => environment(cmd, data),
+        None => false,
+    }
+}
+
+fn variable(key: &[u8]) -> Option<String> {
+    let mut variable = Variable {
+        key: key.as_ptr() as *const c_char,
+        value: std::ptr::null(),
+    };
+    if !environment(
+        ENVIRONMENT_GET_VARIABLE,
+        &mut variable as *mut Variable as *mut c_void,
+    ) || variable.value.is_null()
+    {
+        return None;
+    }
+    let value = unsafe { CStr::from_ptr(variable.value) };
+    value
+        .to_str()
+        .ok()
+        .filter(|value| *value != "auto")
+        .map(str::to_string)
+}
+
+fn platform_option() -> Option<Platform> {
+    variable(PLATFORM_VARIABLE).and_then(|id| Platform::from_id(&id))
+}
+
+fn apply_options(cpu: &mut Chip8, settings: &RomSettings) {
+    let platform = platform_option();
+    cpu.quirks = platform.map_or(settings.quirks, Platform::quirks);
+    cpu.tick_rate = platform.map_or(settings.tick_rate, Platform::tick_rate);
+    if let Some(ips) = variable(IPS_VARIABLE).and_then(|ips| ips.parse::<u32>().ok()) {
+        cpu.tick_rate = (ips / FRAMES_PER_SECOND as u32).max(1);
+    }
+}
+
+fn joypad_button(name: &str) -> Option<(u32, u32)> {
+    let (port, button) = match name.strip_prefix("player2") {
+        Some(button) => (1, button.to_lowercase()),
+        None => (0, name.to_string()),
+    };
+    let id = match button.as_str() {
+        "up" => DEVICE_ID_JOYPAD_UP,
+        "down" => DEVICE_ID_JOYPAD_DOWN,
+        "left" => DEVICE_ID_JOYPAD_LEFT,
+        "right" => DEVICE_ID_JOYPAD_RIGHT,
+        "a" => DEVICE_ID_JOYPAD_A,
+        "b" => DEVICE_ID_JOYPAD_B,
+        _ => return None,
+    };
+    Some((port, id))
+}
+
+impl Core {
+    // The quirks profile option also decides the memory size, which can only
+    // change when the game is (re)loaded.
+    fn boot(name: &str, bytes: Vec<u8>) -> Result<Core, Box<dyn Error>> {
+        let mut rom = Rom::from_bytes(name, bytes.clone())?;
+        let mut settings = std::mem::take(&mut rom.settings);
+        if let Some(platform) = platform_option() {
+            settings.platform = Some(platform);
+            settings.memory_size = platform.memory_size();
+        }
+        let mut cpu = Chip8::new();
+        settings.apply(&mut cpu);
+        apply_options(&mut cpu, &settings);
+        rom.load_into_memory(&mut cpu.memory, cpu.program_start)?;
+        cpu.load_font_set(&Font::for_platform(settings.platform));
+        let joypad = settings
+            .keys
+            .iter()
+            .filter_map(|(name, key)| {
+                joypad_button(name).map(|(port, id)| (port, id, *key as usize))
+            })
+            .collect();
+        Ok(Core {
+            name: name.to_string(),
+            bytes,
+            settings,
+            cpu,
+            joypad,
+            framebuffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            beeper: Beeper::new(),
+            cheats: Cheats::empty(),
+        })
+    }
+
+    fn poll_keys(&mut self, input_state: InputStateFn) {
+        let mut keys = [false; 16];
+        for &(port, id, key) in self.joypad.iter() {
+            keys[key] |= input_state(port, DEVICE_JOYPAD, 0, id) != 0;
+        }
+        for &(keycode, key) in KEYBOARD_LAYOUT.iter() {
+            keys[key] |= input_state(0, DEVICE_KEYBOARD, 0, keycode as u32) != 0;
+        }
+        self.cpu.keys = keys;
+    }
+
+    fn render(&mut self) {
+        let palette = self.settings.palette;
+        for (pixel, &lit) in self
+            .framebuffer
+            .iter_mut()
+            .zip(self.cpu.video.iter().flatten())
+        {
+            *pixel = palette[(lit & 1) as usize];
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> u32 {
+    API_VERSION
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    *info = SystemInfo {
+        library_name: b"chip-8-emulator\0".as_ptr() as *const c_char,
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: b"ch8|c8|sc8|xo8|rom\0".as_ptr() as *const c_char,
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    *info = SystemAvInfo {
+        geometry: GameGeometry {
+            base_width: SCREEN_WIDTH as u32,
+            base_height: SCREEN_HEIGHT as u32,
+            max_width: SCREEN_WIDTH as u32,
+            max_height: SCREEN_HEIGHT as u32,
+            aspect_ratio: SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32,
+        },
+        timing: SystemTiming {
+            fps: FRAMES_PER_SECOND,
+            sample_rate: SAMPLE_RATE as f64,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    CALLBACKS.lock().unwrap().environment = Some(callback);
+    let mut variables: Vec<Variable> = VARIABLES
+        .iter()
+        .map(|(key, value)| Variable {
+            key: key.as_ptr() as *const c_char,
+            value: value.as_ptr() as *const c_char,
+        })
+        .collect();
+    variables.push(Variable {
+        key: std::ptr::null(),
+        value: std::ptr::null(),
+    });
+    callback(
+        ENVIRONMENT_SET_VARIABLES,
+        variables.as_mut_ptr() as *mut c_void,
+    );
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    CALLBACKS.lock().unwrap().video_refresh = Some(callback);
+}
+
+// Audio goes out a frame at a time through the batch callback.
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    CALLBACKS.lock().unwrap().audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    CALLBACKS.lock().unwrap().input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    CALLBACKS.lock().unwrap().input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: u32, _device: u32) {}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap() = None;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    if game.is_null() || (*game).data.is_null() {
+        return false;
+    }
+    let game = &*game;
+    let mut format = PIXEL_FORMAT_XRGB8888;
+    if !environment(
+        ENVIRONMENT_SET_PIXEL_FORMAT,
+        &mut format as *mut u32 as *mut c_void,
+    ) {
+        return false;
+    }
+    let bytes = slice::from_raw_parts(game.data as *const u8, game.size).to_vec();
+    let name = if game.path.is_null() {
+        "rom".to_string()
+    } else {
+        let path = CStr::from_ptr(game.path).to_string_lossy();
+        Path::new(path.as_ref())
+            .file_name()
+            .map_or_else(|| path.to_string(), |name| name.to_string_lossy().into())
+    };
+    match Core::boot(&name, bytes) {
+        Ok(core) => {
+            *CORE.lock().unwrap() = Some(core);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: u32,
+    _info: *const GameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *CORE.lock().unwrap() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> u32 {
+    REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    let mut core = CORE.lock().unwrap();
+    if let Some(current) = core.take() {
+        *core = match Core::boot(&current.name, current.bytes.clone()) {
+            Ok(mut reset) => {
+                reset.cheats = current.cheats;
+                reset.cheats.apply_all(&mut reset.cpu);
+                Some(reset)
+            }
+            Err(_) => Some(current),
+        };
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let callbacks = callbacks();
+    let mut updated = false;
+    if environment(
+        ENVIRONMENT_GET_VARIABLE_UPDATE,
+        &mut updated as *mut bool as *mut c_void,
+    ) && updated
+    {
+        if let Some(core) = CORE.lock().unwrap().as_mut() {
+            apply_options(&mut core.cpu, &core.settings);
+        }
+    }
+    if let Some(input_poll) = callbacks.input_poll {
+        input_poll();
+    }
+    let mut core = CORE.lock().unwrap();
+    let core = match core.as_mut() {
+        Some(core) => core,
+        None => return,
+    };
+    if let Some(input_state) = callbacks.input_state {
+        core.poll_keys(input_state);
+    }
+    core.cheats.apply_frozen(&mut core.cpu);
+    core.cpu.run_frame();
+    core.render();
+    if let Some(video_refresh) = callbacks.video_refresh {
+        video_refresh(
+            core.framebuffer.as_ptr() as *const c_void,
+            SCREEN_WIDTH as u32,
+            SCREEN_HEIGHT as u32,
+            SCREEN_WIDTH * 4,
+        );
+    }
+    let samples = core.beeper.frame(core.cpu.sound_timer > 0);
+    let stereo: Vec<i16> = samples.iter().flat_map(|&s| [s, s]).collect();
+    if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+        let mut written = 0;
+        while written < SAMPLES_PER_FRAME {
+            let accepted =
+                audio_sample_batch(stereo[written * 2..].as_ptr(), SAMPLES_PER_FRAME - written);
+            if accepted == 0 {
+                break;
+            }
+            written += accepted;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    CORE.lock()
+        .unwrap()
+        .as_ref()
+        .map_or(0, |core| state::state_size(&core.cpu))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let mut core = CORE.lock().unwrap();
+    let core = match core.as_mut() {
+        Some(core) => core,
+        None => return false,
+    };
+    let bytes = state::save_state(&core.cpu);
+    if data.is_null() || size < bytes.len() {
+        return false;
+    }
+    slice::from_raw_parts_mut(data as *mut u8, bytes.len()).copy_from_slice(&bytes);
+    true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut core = CORE.lock().unwrap();
+    let core = match core.as_mut() {
+        Some(core) => core,
+        None => return false,
+    };
+    if data.is_null() {
+        return false;
+    }
+    let bytes = slice::from_raw_parts(data as *const u8, size);
+    let size = state::state_size(&core.cpu).min(size);
+    state::load_state(&mut core.cpu, &bytes[..size]).is_ok()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {
+    if let Some(core) = CORE.lock().unwrap().as_mut() {
+        core.cheats.clear();
+    }
+}
+
+// Codes are hexadecimal ADDRESS:VALUE pairs joined with '+', and are frozen
+// while enabled.
+#[no_mangle]
+pub unsafe extern "C" fn retro_cheat_set(_index: u32, enabled: bool, code: *const c_char) {
+    let mut core = CORE.lock().unwrap();
+    let core = match core.as_mut() {
+        Some(core) if !code.is_null() => core,
+        _ => return,
+    };
+    let code = CStr::from_ptr(code).to_string_lossy();
+    for pair in code.split('+') {
+        let (address, value) = match pair.trim().split_once(':') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let address = u16::from_str_radix(address.trim_start_matches("0x"), 16);
+        let value = u8::from_str_radix(value.trim_start_matches("0x"), 16);
+        if let (Ok(address), Ok(value)) = (address, value) {
+            if enabled {
+                let cheat = Cheat {
+                    address,
+                    value,
+                    freeze: true,
+                    description: String::new(),
+                };
+                core.cheats.add(&mut core.cpu, cheat);
+            } else {
+                core.cheats.remove(address);
+            }
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: u32) -> *mut c_void {
+    match (id, CORE.lock().unwrap().as_mut()) {
+        (MEMORY_SYSTEM_RAM, Some(core)) => core.cpu.memory.as_mut_ptr() as *mut c_void,
+        _ => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: u32) -> usize {
+    match (id, CORE.lock().unwrap().as_ref()) {
+        (MEMORY_SYSTEM_RAM, Some(core)) => core.cpu.memory.len(),
+        _ => 0,
+    }
+}
@@ -1,35 +1,497 @@
-mod chip8;
-mod fontset;
+// Without a frontend the binary can only report that it was built without one.
+#![cfg_attr(not(any(feature = "sdl", feature = "tui")), allow(dead_code))]
+#[cfg(feature = "sdl")]
+mod browser;
+mod cli;
+mod frontend;
+mod gdb;
+#[cfg(feature = "sdl")]
 mod graphics;
+mod headless;
+#[cfg(feature = "sdl")]
+mod hud;
+#[cfg(feature = "sdl")]
 mod input;
-mod instructions;
-mod rom;
-use chip8::Chip8;
-use rom::Rom;
+#[cfg(feature = "sdl")]
+mod inspector;
+mod memview;
+#[cfg(feature = "tui")]
+mod terminal;
+#[cfg(feature = "sdl")]
+mod text;
+#[cfg(feature = "sdl")]
+mod window;
+use chip_8_emulator::capture::{self, GifRecorder, WavWriter, Y4mWriter};
+use chip_8_emulator::cheats::Cheats;
+use chip_8_emulator::chip8::Chip8;
+use chip_8_emulator::fontset::{self, Font};
+use chip_8_emulator::movie::Movie;
+use chip_8_emulator::patch::PatchSource;
+use chip_8_emulator::profiler::Profiler;
+use chip_8_emulator::rom::Rom;
+use chip_8_emulator::state::Rewind;
+use chip_8_emulator::watch::{self, RomWatcher, WatchMode};
+use chip_8_emulator::{analysis, patch, recent, sprites};
+use cli::Options;
+use frontend::{Frontend, Hotkey};
+use gdb::{GdbEvent, GdbStub};
+use headless::HeadlessFrontend;
+use memview::MemoryViewer;
+use std::env;
 use std::error::Error;
+use std::fs;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+#[cfg(feature = "tui")]
+use terminal::TerminalFrontend;
+#[cfg(feature = "sdl")]
+use window::SdlFrontend;
+
+const FRAME_DURATION: Duration = Duration::from_micros(1_000_000 / 60);
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut emulator = Chip8::new();
-    let rom = Rom::new("chip8-roms/TICTAC")?;
-    rom.load_into_memory(&mut emulator.memory);
-    emulator.load_font_set(fontset::FONT_SET);
-    let context = sdl2::init()?;
-    let mut canvas = graphics::create_window("chip8 emulator", &context)?;
-    graphics::create_default_screen(&mut canvas);
-    let mut events = context.event_pump()?;
+    let mut options = cli::parse(env::args())?;
+    if let Some((modified, output)) = &options.create_patch {
+        let patch = patch::create(&fs::read(&options.rom)?, &fs::read(modified)?, output)?;
+        fs::write(output, patch)?;
+        return Ok(());
+    }
+    let mut rom = Rom::with_patch(&options.rom, &options.patch)?;
+    if let Some(patch) = &rom.patch {
+        println!("applied {}", patch.display());
+    }
+    for warning in rom.warnings() {
+        eprintln!("warning: {}: {}", options.rom, warning);
+    }
+    let replay = match &options.replay {
+        Some(file) => Some(Movie::load_for(file, &rom)?),
+        None => None,
+    };
+    let mut emulator = match (&replay, options.seed) {
+        (Some(movie), _) => movie.machine(),
+        (None, Some(seed)) => Chip8::with_seed(seed),
+        (None, None) => Chip8::new(),
+    };
+    let mut recording = options
+        .record
+        .as_ref()
+        .map(|_| Movie::new(rom.hash(), emulator.seed));
+    boot(&mut rom, &options, &mut emulator)?;
+    if options.analyze || options.disassemble || options.dot.is_some() {
+        let analysis = analysis::analyze(
+            &emulator.memory,
+            emulator.program_start,
+            emulator.program_start as usize + rom.size(),
+        );
+        if options.disassemble {
+            print!("{}", analysis.listing(&emulator.memory));
+        }
+        if let Some(file) = &options.dot {
+            fs::write(file, analysis.to_dot(&emulator.memory))?;
+        }
+        print!("{}", analysis.report());
+        return Ok(());
+    }
+    let mut palette = rom.settings.palette;
+    let mut title = window_title(&rom);
+    if let Some(platform) = rom.settings.platform {
+        println!(
+            "{}: {} at {} instructions per frame",
+            title,
+            platform.id(),
+            rom.settings.tick_rate
+        );
+    }
+    if let Some(file) = &options.dump_sprites {
+        let references = sprites::find_sprite_references(
+            &emulator.memory,
+            emulator.program_start as usize,
+            emulator.memory.len(),
+        );
+        sprites::dump(&emulator.memory, &references, file)?;
+    }
+    // Cheats would make movies play back differently, so they are left out
+    // while recording or replaying one.
+    let mut cheats = if recording.is_none() && replay.is_none() {
+        Cheats::load(&rom.hash())?
+    } else {
+        Cheats::empty()
+    };
+    if !cheats.cheats.is_empty() {
+        println!("applying {} saved cheats", cheats.cheats.len());
+        cheats.apply_all(&mut emulator);
+    }
+    let mut profiler = options
+        .profile
+        .as_ref()
+        .map(|file| Profiler::new(file, &emulator, rom.size()));
+    let mut gdb = match options.gdb_port {
+        Some(port) => Some(GdbStub::listen(port)?),
+        None => None,
+    };
+    if !options.headless {
+        add_recent(&options.rom);
+    }
+    let mut frontend = create_frontend(
+        &options,
+        &title,
+        palette,
+        &rom.settings.keys,
+        replay.as_ref().map(|movie| movie.frames.len()),
+        &mut emulator,
+    )?;
+    let frame_duration = if frontend.is_realtime() {
+        FRAME_DURATION
+    } else {
+        Duration::ZERO
+    };
+    let mut gif = match &options.gif {
+        Some(file) => Some(GifRecorder::new(file, palette, options.scale)?),
+        None => None,
+    };
+    let mut y4m = match &options.y4m {
+        Some(file) => Some(Y4mWriter::new(file, palette, options.scale)?),
+        None => None,
+    };
+    let mut wav = match &options.wav {
+        Some(file) => Some(WavWriter::new(file)?),
+        None => None,
+    };
+    let mut frame = 0;
+    let rewind_enabled = options.rewind_memory > 0 && recording.is_none() && replay.is_none();
+    let mut rewind = Rewind::new(options.rewind_memory);
+    let mut memory_viewer = if options.memory_viewer {
+        Some(MemoryViewer::new(&emulator))
+    } else {
+        None
+    };
+    let mut watcher = options
+        .watch
+        .map(|mode| (RomWatcher::new(&options.rom, &rom), mode));
+    let mut paused = false;
     'emulator_loop: loop {
-        let quit = input::capture(&mut events, &mut emulator);
-        if quit {
-            break 'emulator_loop;
+        let frame_start = Instant::now();
+        // The ROM to load in place of the current one, and how.
+        let mut load = None;
+        for hotkey in frontend.capture(&mut emulator) {
+            match hotkey {
+                Hotkey::Quit => break 'emulator_loop,
+                Hotkey::Pause => paused = !paused,
+                Hotkey::Screenshot => {
+                    let file = timestamped_file("screenshot", "png");
+                    capture::write_png(&file, &emulator.video, palette, options.scale)?;
+                    println!("saved {}", file);
+                }
+                Hotkey::ToggleRecording => match gif.take() {
+                    Some(recorder) => {
+                        recorder.finish()?;
+                        println!("recording stopped");
+                    }
+                    None => {
+                        let file = timestamped_file("recording", "gif");
+                        gif = Some(GifRecorder::new(&file, palette, options.scale)?);
+                        println!("recording to {}", file);
+                    }
+                },
+                // Movies and profiles only make sense for a single ROM.
+                Hotkey::Open(_)
+                    if recording.is_some() || replay.is_some() || profiler.is_some() =>
+                {
+                    eprintln!(
+                        "warning: cannot open another ROM while recording, replaying or profiling"
+                    );
+                }
+                Hotkey::Open(file) => load = Some((file, WatchMode::Reset)),
+            }
+        }
+        if let Some((watcher, mode)) = &mut watcher {
+            if watcher.poll() && load.is_none() {
+                load = Some((options.rom.clone(), *mode));
+            }
+        }
+        if let Some((file, mode)) = load {
+            let opened = file != options.rom;
+            // A patch given on the command line belongs to the ROM it was given for.
+            let patch = match &options.patch {
+                PatchSource::File(_) if opened => PatchSource::Auto,
+                patch => patch.clone(),
+            };
+            match reload(&file, &patch, &options, emulator.seed) {
+                Ok((new_rom, fresh)) => {
+                    let data = match mode {
+                        WatchMode::KeepPc => watch::changed_data(&rom, &new_rom, &fresh),
+                        _ => None,
+                    };
+                    match data {
+                        Some(changed) => {
+                            for &address in changed.iter() {
+                                emulator.memory[address] = fresh.memory[address];
+                            }
+                            println!(
+                                "reloaded {}, patched {} data bytes in place",
+                                file,
+                                changed.len()
+                            );
+                        }
+                        None => {
+                            emulator = fresh;
+                            if opened {
+                                cheats = Cheats::load(&new_rom.hash()).unwrap_or_else(|error| {
+                                    eprintln!("warning: not loading cheats: {}", error);
+                                    Cheats::empty()
+                                });
+                            }
+                            cheats.apply_all(&mut emulator);
+                            rewind = Rewind::new(options.rewind_memory);
+                            if let Some(viewer) = &mut memory_viewer {
+                                viewer.reset(&emulator);
+                            }
+                            if mode == WatchMode::Reset {
+                                palette = new_rom.settings.palette;
+                                title = window_title(&new_rom);
+                                // SDL only allows one window at a time.
+                                drop(frontend);
+                                frontend = create_frontend(
+                                    &options,
+                                    &title,
+                                    palette,
+                                    &new_rom.settings.keys,
+                                    None,
+                                    &mut emulator,
+                                )?;
+                            }
+                            println!("{} {}", if opened { "opened" } else { "reloaded" }, file);
+                        }
+                    }
+                    if opened {
+                        add_recent(&file);
+                    }
+                    if let Some((watcher, _)) = &mut watcher {
+                        watcher.watch(&file, &new_rom);
+                    }
+                    options.rom = file;
+                    options.patch = patch;
+                    rom = new_rom;
+                }
+                Err(error) => eprintln!("warning: not loading {}: {}", file, error),
+            }
+        }
+        if let Some(viewer) = &mut memory_viewer {
+            viewer.process_commands(&mut emulator, &mut cheats, paused);
+        }
+        let mut halted = false;
+        if let Some(stub) = &mut gdb {
+            match stub.poll(&mut emulator)? {
+                GdbEvent::Halted => halted = true,
+                GdbEvent::Running => {}
+                GdbEvent::Kill => break 'emulator_loop,
+            }
         }
-        emulator.run();
-        if emulator.update_display {
+        if paused || halted || frontend.is_menu_open() {
+            frontend.present(&emulator);
+            if let Some(viewer) = &memory_viewer {
+                viewer.render(&emulator, &cheats, paused);
+            }
+            wait_for_next_frame(frame_start, frame_duration);
+            continue;
+        }
+        if rewind_enabled && frontend.is_rewind_held() {
+            if rewind.rewind(&mut emulator) {
+                emulator.update_display = false;
+                frontend.present(&emulator);
+            }
+            wait_for_next_frame(frame_start, frame_duration);
+            continue;
+        }
+        if rewind_enabled {
+            rewind.push(&emulator);
+        }
+        if let Some(movie) = &replay {
+            if !movie.play_frame(&mut emulator, frame) && frame == movie.frames.len() {
+                println!("replay finished");
+            }
+        }
+        if let Some(movie) = &mut recording {
+            movie.record_frame(&emulator);
+        }
+        cheats.apply_frozen(&mut emulator);
+        let faulted = emulator.fault.is_some();
+        match (&mut gdb, &mut profiler) {
+            (Some(stub), Some(profiler)) => {
+                stub.run_frame(&mut emulator, |cpu| profiler.record(cpu))?
+            }
+            (Some(stub), None) => stub.run_frame(&mut emulator, |_| {})?,
+            (None, Some(profiler)) => {
+                emulator.run_frame_until(|cpu| {
+                    profiler.record(cpu);
+                    false
+                });
+            }
+            (None, None) => emulator.run_frame(),
+        }
+        if let (Some(fault), false) = (emulator.fault, faulted) {
+            // A headless run cannot be looked at, so it stops with an error
+            // once the captures are written.
+            if options.headless {
+                break 'emulator_loop;
+            }
+            eprintln!(
+                "error: {} at 0x{:03X}, the machine is halted",
+                fault, emulator.pc
+            );
+        }
+        if let Some(recorder) = &mut gif {
+            recorder.push_frame(&emulator.video)?;
+        }
+        if let Some(writer) = &mut y4m {
+            writer.push_frame(&emulator.video)?;
+        }
+        if let Some(writer) = &mut wav {
+            writer.push_frame(emulator.sound_timer > 0)?;
+        }
+        let redraw = frontend.end_frame(&emulator);
+        if emulator.update_display || redraw {
             emulator.update_display = false;
-            graphics::update_screen(&mut canvas, emulator.video);
+            frontend.present(&emulator);
+        }
+        if let Some(viewer) = &mut memory_viewer {
+            viewer.end_frame(&emulator);
+            viewer.render(&emulator, &cheats, paused);
         }
-        thread::sleep(Duration::from_millis(2));
+        frame += 1;
+        wait_for_next_frame(frame_start, frame_duration);
+    }
+    if let Some(profiler) = &mut profiler {
+        profiler.finish(&emulator)?;
+    }
+    if let Some(recorder) = gif {
+        recorder.finish()?;
+    }
+    if let Some(writer) = y4m {
+        writer.finish()?;
     }
+    if let Some(writer) = wav {
+        writer.finish()?;
+    }
+    if let Some(file) = &options.screenshot {
+        capture::write_png(file, &emulator.video, palette, options.scale)?;
+    }
+    if let (Some(movie), Some(file)) = (&recording, &options.record) {
+        movie.save(file)?;
+    }
+    if let (Some(fault), true) = (emulator.fault, options.headless) {
+        return Err(format!("{} at 0x{:03X}", fault, emulator.pc).into());
+    }
+    Ok(())
+}
+
+// Sets a fresh machine up for the ROM, with the overrides from the command
+// line.
+fn boot(rom: &mut Rom, options: &Options, emulator: &mut Chip8) -> Result<(), Box<dyn Error>> {
+    if let Some(address) = options.load_address {
+        rom.settings.program_start = address;
+    }
+    if let Some(address) = options.font_address {
+        rom.settings.font_address = address;
+    }
+    let font = match (&options.font, &options.font_file) {
+        (Some(name), _) => Font::builtin(name).ok_or_else(|| {
+            format!(
+                "unknown font {}, expected one of {}",
+                name,
+                fontset::FONT_NAMES.join(", ")
+            )
+        })?,
+        (None, Some(file)) => Font::load(file)?,
+        (None, None) => Font::for_platform(rom.settings.platform),
+    };
+    rom.check_font(&font)?;
+    rom.settings.apply(emulator);
+    rom.load_into_memory(&mut emulator.memory, emulator.program_start)?;
+    emulator.load_font_set(&font);
     Ok(())
 }
+
+// Reads a ROM that changed on disk or was opened from the browser and boots
+// it on a machine with the same seed, so CXNN behaves as it did before.
+fn reload(
+    file: &str,
+    patch: &PatchSource,
+    options: &Options,
+    seed: u64,
+) -> Result<(Rom, Chip8), Box<dyn Error>> {
+    let mut rom = Rom::with_patch(file, patch)?;
+    for warning in rom.warnings() {
+        eprintln!("warning: {}: {}", file, warning);
+    }
+    let mut emulator = Chip8::with_seed(seed);
+    boot(&mut rom, options, &mut emulator)?;
+    Ok((rom, emulator))
+}
+
+fn add_recent(file: &str) {
+    if let Err(error) = recent::add(file) {
+        eprintln!(
+            "warning: not adding {} to the recent files: {}",
+            file, error
+        );
+    }
+}
+
+fn window_title(rom: &Rom) -> String {
+    match &rom.settings.title {
+        Some(title) => format!("chip8 emulator - {}", title),
+        None => "chip8 emulator".to_string(),
+    }
+}
+
+fn create_frontend(
+    options: &Options,
+    title: &str,
+    palette: [u32; 2],
+    rom_keys: &[(String, u8)],
+    replay_frames: Option<usize>,
+    emulator: &mut Chip8,
+) -> Result<Box<dyn Frontend>, Box<dyn Error>> {
+    if options.headless {
+        let frames = options.frames.or(replay_frames).unwrap_or(0);
+        return Ok(Box::new(HeadlessFrontend::new(frames)));
+    }
+    #[cfg(feature = "tui")]
+    if options.tui {
+        return Ok(Box::new(TerminalFrontend::new(title, palette, rom_keys)?));
+    }
+    #[cfg(feature = "sdl")]
+    if !options.tui {
+        return Ok(Box::new(SdlFrontend::new(
+            title,
+            palette,
+            rom_keys,
+            options.hud,
+            options.sprite_inspector,
+            &options.rom_dir,
+            emulator,
+        )?));
+    }
+    let _ = (title, palette, rom_keys, emulator);
+    let missing = if options.tui { "tui" } else { "sdl" };
+    Err(format!(
+        "this build has no {0} frontend, rebuild with --features {0}",
+        missing
+    )
+    .into())
+}
+
+fn wait_for_next_frame(frame_start: Instant, frame_duration: Duration) {
+    if let Some(remaining) = frame_duration.checked_sub(frame_start.elapsed()) {
+        thread::sleep(remaining);
+    }
+}
+
+fn timestamped_file(prefix: &str, extension: &str) -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+    format!("{}-{}.{}", prefix, millis, extension)
+}
@@ -0,0 +1,254 @@
+use chip_8_emulator::cheats::{Cheat, Cheats, Comparison, MemorySearch};
+use chip_8_emulator::chip8::Chip8;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+const BYTES_PER_ROW: usize = 16;
+const VISIBLE_ROWS: usize = 16;
+const RESET: &str = "\x1b[0m";
+const PC_STYLE: &str = "\x1b[7m";
+const INDEX_STYLE: &str = "\x1b[4;36m";
+const CHANGED_STYLE: &str = "\x1b[31m";
+const FROZEN_STYLE: &str = "\x1b[34m";
+const LISTED_CANDIDATES: usize = 8;
+
+pub struct MemoryViewer {
+    previous: Vec<u8>,
+    changed: Vec<bool>,
+    pinned: Option<usize>,
+    commands: Receiver<String>,
+    message: String,
+    search: Option<MemorySearch>,
+}
+
+impl MemoryViewer {
+    pub fn new(cpu: &Chip8) -> MemoryViewer {
+        let (sender, commands) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            return;
+                        }
+                    }
+                    Err(_) => return,
+                }
+            }
+        });
+        MemoryViewer {
+            previous: cpu.memory.clone(),
+            changed: vec![false; cpu.memory.len()],
+            pinned: None,
+            commands,
+            message: "commands: set ADDR BYTE.. | view ADDR | follow | search | changed | unchanged | increased | decreased | equals BYTE | freeze ADDR [BYTE] | patch ADDR BYTE | unfreeze ADDR | cheats".to_string(),
+            search: None,
+        }
+    }
+
+    // Called when the ROM is reloaded, which may change the memory size.
+    pub fn reset(&mut self, cpu: &Chip8) {
+        self.previous = cpu.memory.clone();
+        self.changed = vec![false; cpu.memory.len()];
+        if self.search.take().is_some() {
+            self.message = "the ROM was reloaded, the search was cleared".to_string();
+        }
+    }
+
+    pub fn end_frame(&mut self, cpu: &Chip8) {
+        for (address, (&byte, previous)) in
+            cpu.memory.iter().zip(self.previous.iter_mut()).enumerate()
+        {
+            self.changed[address] = byte != *previous;
+            *previous = byte;
+        }
+    }
+
+    pub fn process_commands(&mut self, cpu: &mut Chip8, cheats: &mut Cheats, paused: bool) {
+        while let Ok(command) = self.commands.try_recv() {
+            self.message = match self.execute(&command, cpu, cheats, paused) {
+                Ok(message) => message,
+                Err(error) => format!("error: {}", error),
+            };
+        }
+    }
+
+    fn execute(
+        &mut self,
+        command: &str,
+        cpu: &mut Chip8,
+        cheats: &mut Cheats,
+        paused: bool,
+    ) -> Result<String, String> {
+        let mut words = command.split_whitespace();
+        match words.next() {
+            Some("set") => {
+                if !paused {
+                    return Err("pause the emulator with P before editing memory".to_string());
+                }
+                let address = parse_number(words.next().ok_or("set expects an address")?)?;
+                // Nothing is written unless every byte is valid and fits.
+                let values = words
+                    .map(|word| match parse_number(word)? {
+                        value if value > 0xFF => Err(format!("{} is not a byte", word)),
+                        value => Ok(value as u8),
+                    })
+                    .collect::<Result<Vec<u8>, String>>()?;
+                let end = address.saturating_add(values.len());
+                if end > cpu.memory.len() {
+                    return Err(format!(
+                        "0x{:03X} is outside of memory",
+                        address.max(cpu.memory.len())
+                    ));
+                }
+                let written = values.len();
+                for (target, value) in (address..end).zip(values) {
+                    cpu.memory[target] = value;
+                    self.changed[target] = true;
+                    self.previous[target] = value;
+                }
+                Ok(format!("wrote {} bytes at 0x{:03X}", written, address))
+            }
+            Some("view") => {
+                let address = parse_number(words.next().ok_or("view expects an address")?)?;
+                if address >= cpu.memory.len() {
+                    return Err(format!("0x{:03X} is outside of memory", address));
+                }
+                self.pinned = Some(address);
+                Ok(format!("viewing 0x{:03X}", address))
+            }
+            Some("follow") => {
+                self.pinned = None;
+                Ok("following the program counter".to_string())
+            }
+            Some("search") => {
+                let search = MemorySearch::new(cpu);
+                let count = search.candidates().len();
+                self.search = Some(search);
+                Ok(format!("searching {} addresses", count))
+            }
+            Some(name @ ("changed" | "unchanged" | "increased" | "decreased" | "equals")) => {
+                let comparison =
+                    Comparison::parse(name, words.next()).ok_or("equals expects a byte")?;
+                let search = self
+                    .search
+                    .as_mut()
+                    .ok_or("start a search with the search command")?;
+                let count = search.filter(cpu, comparison);
+                let listed: Vec<String> = search
+                    .candidates()
+                    .iter()
+                    .take(LISTED_CANDIDATES)
+                    .map(|&address| format!("0x{:03X}={:02X}", address, cpu.memory[address]))
+                    .collect();
+                Ok(format!("{} candidates: {}", count, listed.join(" ")))
+            }
+            Some(command @ ("freeze" | "patch")) => {
+                let address = parse_number(words.next().ok_or("expected an address")?)?;
+                if address >= cpu.memory.len() {
+                    return Err(format!("0x{:03X} is outside of memory", address));
+                }
+                let value = match words.next() {
+                    Some(word) => parse_number(word)?,
+                    None if command == "freeze" => cpu.memory[address] as usize,
+                    None => return Err("patch expects a byte".to_string()),
+                };
+                if value > 0xFF {
+                    return Err(format!("0x{:X} is not a byte", value));
+                }
+                let cheat = Cheat {
+                    address: address as u16,
+                    value: value as u8,
+                    freeze: command == "freeze",
+                    description: String::new(),
+                };
+                cheats.add(cpu, cheat);
+                cheats.save().map_err(|e| e.to_string())?;
+                Ok(format!("{} 0x{:03X} at {:02X}", command, address, value))
+            }
+            Some("unfreeze") => {
+                let address = parse_number(words.next().ok_or("unfreeze expects an address")?)?;
+                if !cheats.remove(address as u16) {
+                    return Err(format!("there is no cheat at 0x{:03X}", address));
+                }
+                cheats.save().map_err(|e| e.to_string())?;
+                Ok(format!("removed the cheat at 0x{:03X}", address))
+            }
+            Some("cheats") => {
+                let listed: Vec<String> = cheats
+                    .cheats
+                    .iter()
+                    .map(|cheat| {
+                        let kind = if cheat.freeze { "frozen" } else { "patched" };
+                        format!("0x{:03X}={:02X} {}", cheat.address, cheat.value, kind)
+                    })
+                    .collect();
+                if listed.is_empty() {
+                    Ok("no cheats".to_string())
+                } else {
+                    Ok(listed.join(", "))
+                }
+            }
+            Some(other) => Err(format!("unknown command {}", other)),
+            None => Ok(String::new()),
+        }
+    }
+
+    pub fn render(&self, cpu: &Chip8, cheats: &Cheats, paused: bool) {
+        let pc = cpu.pc as usize;
+        let index = cpu.index as usize;
+        let last_row =
+            cpu.memory.len() / BYTES_PER_ROW - VISIBLE_ROWS.min(cpu.memory.len() / BYTES_PER_ROW);
+        let first_row = (self.pinned.unwrap_or(pc) / BYTES_PER_ROW)
+            .saturating_sub(VISIBLE_ROWS / 2)
+            .min(last_row);
+        let mut out = String::from("\x1b[H\x1b[J");
+        out.push_str(&format!(
+            "PC 0x{:03X}  I 0x{:03X}  {}\n",
+            pc,
+            index,
+            if paused { "PAUSED" } else { "running" }
+        ));
+        for row in first_row..first_row + VISIBLE_ROWS {
+            let start = row * BYTES_PER_ROW;
+            out.push_str(&format!("{:04X}:", start));
+            for address in start..start + BYTES_PER_ROW {
+                let style = if address == pc || address == pc + 1 {
+                    PC_STYLE
+                } else if address == index {
+                    INDEX_STYLE
+                } else if cheats
+                    .cheats
+                    .iter()
+                    .any(|cheat| cheat.freeze && cheat.address as usize == address)
+                {
+                    FROZEN_STYLE
+                } else if self.changed[address] {
+                    CHANGED_STYLE
+                } else {
+                    ""
+                };
+                out.push_str(&format!(" {}{:02X}{}", style, cpu.memory[address], RESET));
+            }
+            out.push('\n');
+        }
+        out.push_str(&self.message);
+        out.push('\n');
+        let stdout = io::stdout();
+        let mut handle = stdout.lock();
+        let _ = handle.write_all(out.as_bytes());
+        let _ = handle.flush();
+    }
+}
+
+fn parse_number(value: &str) -> Result<usize, String> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => usize::from_str_radix(value, 16),
+    };
+    parsed.map_err(|_| format!("{} is not a hex number", value))
+}
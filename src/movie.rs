@@ -0,0 +1,112 @@
+use crate::chip8::Chip8;
+use crate::rom::Rom;
+use std::error::Error;
+use std::fs;
+
+const MOVIE_HEADER: &str = "CHIP8MOVIE 1";
+
+pub struct Movie {
+    pub rom_hash: String,
+    pub seed: u64,
+    pub frames: Vec<u16>,
+}
+
+impl Movie {
+    pub fn new(rom_hash: String, seed: u64) -> Movie {
+        Movie {
+            rom_hash,
+            seed,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn load(file: &str) -> Result<Movie, Box<dyn Error>> {
+        let contents = fs::read_to_string(file)?;
+        let mut lines = contents.lines();
+        if lines.next() != Some(MOVIE_HEADER) {
+            return Err(format!("{} is not a chip8 movie file", file).into());
+        }
+        let rom_hash = read_header_field(&mut lines, "rom_sha1")?.to_string();
+        let seed = read_header_field(&mut lines, "seed")?.parse::<u64>()?;
+        let frame_count = read_header_field(&mut lines, "frames")?.parse::<usize>()?;
+        let mut frames = Vec::with_capacity(frame_count);
+        for line in lines {
+            frames.push(u16::from_str_radix(line.trim(), 16)?);
+        }
+        if frames.len() != frame_count {
+            return Err(format!(
+                "{} is truncated: expected {} frames, found {}",
+                file,
+                frame_count,
+                frames.len()
+            )
+            .into());
+        }
+        Ok(Movie {
+            rom_hash,
+            seed,
+            frames,
+        })
+    }
+
+    // A movie only replays the same way on the ROM it was recorded with.
+    pub fn load_for(file: &str, rom: &Rom) -> Result<Movie, Box<dyn Error>> {
+        let movie = Movie::load(file)?;
+        if movie.rom_hash != rom.hash() {
+            return Err(format!(
+                "{} was recorded with another ROM, whose SHA-1 is {}",
+                file, movie.rom_hash
+            )
+            .into());
+        }
+        Ok(movie)
+    }
+
+    // A machine seeded like the one the movie was recorded on. The ROM still
+    // has to be loaded into it.
+    pub fn machine(&self) -> Chip8 {
+        Chip8::with_seed(self.seed)
+    }
+
+    pub fn save(&self, file: &str) -> Result<(), Box<dyn Error>> {
+        let mut contents = format!(
+            "{}\nrom_sha1 {}\nseed {}\nframes {}\n",
+            MOVIE_HEADER,
+            self.rom_hash,
+            self.seed,
+            self.frames.len()
+        );
+        for keys in &self.frames {
+            contents.push_str(&format!("{:04X}\n", keys));
+        }
+        fs::write(file, contents)?;
+        Ok(())
+    }
+
+    // Called before every frame with the keys about to be used for it.
+    pub fn record_frame(&mut self, cpu: &Chip8) {
+        self.frames.push(cpu.keys_mask());
+    }
+
+    // Called before every frame instead of reading the keypad. Returns false
+    // once the movie has no more frames, leaving the keys as they were.
+    pub fn play_frame(&self, cpu: &mut Chip8, frame: usize) -> bool {
+        match self.frames.get(frame) {
+            Some(&keys) => {
+                cpu.set_keys_mask(keys);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn read_header_field<'a>(
+    lines: &mut dyn Iterator<Item = &'a str>,
+    name: &str,
+) -> Result<&'a str, Box<dyn Error>> {
+    match lines.next().and_then(|line| line.split_once(' ')) {
+        Some((field, value)) if field == name => Ok(value.trim()),
+        _ => Err(format!("movie header is missing the {} field", name).into()),
+    }
+}
@@ -0,0 +1,348 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+// "EOF" read as an offset, which IPS records therefore cannot start at.
+const IPS_EOF_OFFSET: usize = 0x454F46;
+const IPS_MAX_OFFSET: usize = 0xFFFFFF;
+const IPS_MAX_RECORD: usize = 0xFFFF;
+const BPS_MAGIC: &[u8] = b"BPS1";
+const BPS_FOOTER_SIZE: usize = 12;
+// The largest memory of any platform, so no ROM worth patching is larger.
+const BPS_MAX_TARGET: usize = 0x10000;
+const EXTENSIONS: [&str; 2] = ["ips", "bps"];
+
+const SOURCE_READ: u64 = 0;
+const TARGET_READ: u64 = 1;
+const SOURCE_COPY: u64 = 2;
+
+#[derive(Clone)]
+pub enum PatchSource {
+    // Uses a patch next to the ROM, as ROM.ips, ROM.bps or with the ROM's
+    // extension replaced, when there is one.
+    Auto,
+    File(String),
+    None,
+}
+
+impl PatchSource {
+    pub fn resolve(&self, rom_file: &str) -> Option<PathBuf> {
+        match self {
+            PatchSource::Auto => find_next_to(rom_file),
+            PatchSource::File(file) => Some(PathBuf::from(file)),
+            PatchSource::None => None,
+        }
+    }
+}
+
+fn find_next_to(rom_file: &str) -> Option<PathBuf> {
+    let rom = Path::new(rom_file);
+    let appended = EXTENSIONS
+        .iter()
+        .map(|extension| PathBuf::from(format!("{}.{}", rom_file, extension)));
+    let replaced = EXTENSIONS
+        .iter()
+        .map(|extension| rom.with_extension(extension));
+    appended
+        .chain(replaced)
+        .find(|candidate| candidate != rom && candidate.is_file())
+}
+
+// Picks the format from the patch header.
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if patch.starts_with(IPS_MAGIC) {
+        apply_ips(rom, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(rom, patch)
+    } else {
+        Err("not an IPS or BPS patch".into())
+    }
+}
+
+// Picks the format from the extension of the patch file name.
+pub fn create(original: &[u8], modified: &[u8], file: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let extension = Path::new(file)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("ips") => create_ips(original, modified),
+        Some("bps") => Ok(create_bps(original, modified)),
+        _ => Err(format!("{} should end in .ips or .bps", file).into()),
+    }
+}
+
+pub fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut reader = Reader::new(patch);
+    if reader.bytes(IPS_MAGIC.len())? != IPS_MAGIC {
+        return Err("missing the IPS header".into());
+    }
+    let mut output = rom.to_vec();
+    loop {
+        let record = reader.bytes(3)?;
+        if record == IPS_EOF {
+            break;
+        }
+        let offset = be(record);
+        let size = be(reader.bytes(2)?);
+        let data = if size == 0 {
+            let run = be(reader.bytes(2)?);
+            vec![reader.byte()?; run]
+        } else {
+            reader.bytes(size)?.to_vec()
+        };
+        if output.len() < offset + data.len() {
+            output.resize(offset + data.len(), 0);
+        }
+        output[offset..offset + data.len()].copy_from_slice(&data);
+    }
+    // An optional extension after EOF truncates the output.
+    if let Ok(size) = reader.bytes(3) {
+        output.truncate(be(size));
+    }
+    Ok(output)
+}
+
+pub fn create_ips(original: &[u8], modified: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if modified.len() > IPS_MAX_OFFSET {
+        return Err("IPS patches cannot address ROMs over 16 MB".into());
+    }
+    let mut patch = IPS_MAGIC.to_vec();
+    let differs = |offset: usize| original.get(offset) != Some(&modified[offset]);
+    let mut offset = 0;
+    while offset < modified.len() {
+        if !differs(offset) {
+            offset += 1;
+            continue;
+        }
+        // A record cannot start at the offset that reads as "EOF".
+        let start = if offset == IPS_EOF_OFFSET {
+            offset - 1
+        } else {
+            offset
+        };
+        let mut end = offset;
+        while end < modified.len() && end - start < IPS_MAX_RECORD && differs(end) {
+            end += 1;
+        }
+        patch.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+        patch.extend_from_slice(&((end - start) as u16).to_be_bytes());
+        patch.extend_from_slice(&modified[start..end]);
+        offset = end;
+    }
+    patch.extend_from_slice(IPS_EOF);
+    if modified.len() < original.len() {
+        patch.extend_from_slice(&(modified.len() as u32).to_be_bytes()[1..]);
+    }
+    Ok(patch)
+}
+
+pub fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if patch.len() < BPS_MAGIC.len() + BPS_FOOTER_SIZE || !patch.starts_with(BPS_MAGIC) {
+        return Err("missing the BPS header".into());
+    }
+    let (body, footer) = patch.split_at(patch.len() - BPS_FOOTER_SIZE);
+    let checksum = |index: usize| le(&footer[index * 4..index * 4 + 4]);
+    if crc32(&patch[..patch.len() - 4]) != checksum(2) {
+        return Err("the patch is corrupt, its checksum does not match".into());
+    }
+    if crc32(rom) != checksum(0) {
+        return Err("the patch was made for a different ROM".into());
+    }
+    let mut reader = Reader::new(&body[BPS_MAGIC.len()..]);
+    let source_size = reader.number()? as usize;
+    let target_size = reader.number()? as usize;
+    if source_size != rom.len() {
+        return Err("the patch was made for a ROM of a different size".into());
+    }
+    if target_size > BPS_MAX_TARGET {
+        return Err(format!("the patched ROM would be {} bytes", target_size).into());
+    }
+    let metadata_size = reader.number()? as usize;
+    reader.bytes(metadata_size)?;
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset: i64 = 0;
+    let mut target_offset: i64 = 0;
+    while !reader.is_empty() {
+        let action = reader.number()?;
+        let length = (action >> 2) as usize + 1;
+        if length > target_size - target.len() {
+            return Err("the patch writes past the end of the patched ROM".into());
+        }
+        match action & 3 {
+            SOURCE_READ => {
+                let start = target.len();
+                let bytes = rom
+                    .get(start..start + length)
+                    .ok_or("a source read is out of range")?;
+                target.extend_from_slice(bytes);
+            }
+            TARGET_READ => target.extend_from_slice(reader.bytes(length)?),
+            SOURCE_COPY => {
+                source_offset = move_offset(source_offset, reader.signed_number()?)?;
+                let start = usize::try_from(source_offset)?;
+                let bytes = rom
+                    .get(start..start.saturating_add(length))
+                    .ok_or("a source copy is out of range")?;
+                target.extend_from_slice(bytes);
+                source_offset += length as i64;
+            }
+            // Target copy.
+            _ => {
+                target_offset = move_offset(target_offset, reader.signed_number()?)?;
+                // The copy may overlap the bytes it writes, so it goes one
+                // byte at a time.
+                for _ in 0..length {
+                    let byte = *target
+                        .get(usize::try_from(target_offset)?)
+                        .ok_or("a target copy is out of range")?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+    if target.len() != target_size {
+        return Err("the patched ROM has the wrong size".into());
+    }
+    if crc32(&target) != checksum(1) {
+        return Err("the patched ROM does not match the patch checksum".into());
+    }
+    Ok(target)
+}
+
+fn move_offset(offset: i64, relative: i64) -> Result<i64, Box<dyn Error>> {
+    offset
+        .checked_add(relative)
+        .ok_or_else(|| "a copy is out of range".into())
+}
+
+// Only uses source and target reads, which is plenty for CHIP-8 sized ROMs.
+pub fn create_bps(original: &[u8], modified: &[u8]) -> Vec<u8> {
+    let mut patch = BPS_MAGIC.to_vec();
+    write_number(&mut patch, original.len() as u64);
+    write_number(&mut patch, modified.len() as u64);
+    write_number(&mut patch, 0);
+    let same = |offset: usize| original.get(offset) == Some(&modified[offset]);
+    let mut offset = 0;
+    while offset < modified.len() {
+        let matching = same(offset);
+        let start = offset;
+        while offset < modified.len() && same(offset) == matching {
+            offset += 1;
+        }
+        let length = (offset - start) as u64;
+        if matching {
+            write_number(&mut patch, (length - 1) << 2 | SOURCE_READ);
+        } else {
+            write_number(&mut patch, (length - 1) << 2 | TARGET_READ);
+            patch.extend_from_slice(&modified[start..offset]);
+        }
+    }
+    patch.extend_from_slice(&crc32(original).to_le_bytes());
+    patch.extend_from_slice(&crc32(modified).to_le_bytes());
+    let checksum = crc32(&patch);
+    patch.extend_from_slice(&checksum.to_le_bytes());
+    patch
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, offset: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset.saturating_add(count))
+            .ok_or("the patch is truncated")?;
+        self.offset += count;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, Box<dyn Error>> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    // BPS numbers: seven bits per byte, the last byte marked by its high
+    // bit, with each continuation adding one so encodings are unique.
+    fn number(&mut self) -> Result<u64, Box<dyn Error>> {
+        let mut number: u64 = 0;
+        let mut shift: u64 = 1;
+        loop {
+            let byte = self.byte()?;
+            number = ((byte & 0x7F) as u64)
+                .checked_mul(shift)
+                .and_then(|value| number.checked_add(value))
+                .ok_or("a number in the patch is too large")?;
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+            shift = shift
+                .checked_mul(0x80)
+                .ok_or("a number in the patch is too large")?;
+            number = number
+                .checked_add(shift)
+                .ok_or("a number in the patch is too large")?;
+        }
+    }
+
+    fn signed_number(&mut self) -> Result<i64, Box<dyn Error>> {
+        let number = self.number()?;
+        let magnitude = (number >> 1) as i64;
+        Ok(if number & 1 != 0 {
+            -magnitude
+        } else {
+            magnitude
+        })
+    }
+}
+
+fn write_number(patch: &mut Vec<u8>, mut number: u64) {
+    loop {
+        let byte = (number & 0x7F) as u8;
+        number >>= 7;
+        if number == 0 {
+            patch.push(byte | 0x80);
+            return;
+        }
+        patch.push(byte);
+        number -= 1;
+    }
+}
+
+fn be(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| value << 8 | byte as usize)
+}
+
+fn le(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+// CRC-32 as used by zip and BPS.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,236 @@
+use crate::chip8::Chip8;
+use crate::disassembler;
+use crate::instructions;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+
+const HOTTEST_ADDRESSES: usize = 20;
+
+struct Call {
+    target: u16,
+    entered_at: u64,
+}
+
+#[derive(Default)]
+struct Subroutine {
+    calls: u64,
+    inclusive: u64,
+}
+
+pub struct Profiler {
+    file: String,
+    rom_start: usize,
+    rom_end: usize,
+    executions: Vec<u64>,
+    classes: HashMap<u16, u64>,
+    subroutines: HashMap<u16, Subroutine>,
+    call_stack: Vec<Call>,
+    total: u64,
+    written: bool,
+}
+
+impl Profiler {
+    pub fn new(file: &str, cpu: &Chip8, rom_size: usize) -> Profiler {
+        Profiler {
+            file: file.to_string(),
+            rom_start: cpu.program_start as usize,
+            rom_end: (cpu.program_start as usize + rom_size).min(cpu.memory.len()),
+            executions: vec![0; cpu.memory.len()],
+            classes: HashMap::new(),
+            subroutines: HashMap::new(),
+            call_stack: Vec::new(),
+            total: 0,
+            written: false,
+        }
+    }
+
+    // Called before every instruction with the CPU about to execute it.
+    pub fn record(&mut self, cpu: &Chip8) {
+        let pc = cpu.pc as usize;
+        if pc + 1 >= cpu.memory.len() {
+            return;
+        }
+        let opcode = (cpu.memory[pc] as u16) << 8 | cpu.memory[pc + 1] as u16;
+        self.executions[pc] += 1;
+        self.total += 1;
+        *self
+            .classes
+            .entry(instructions::handler_key(opcode))
+            .or_insert(0) += 1;
+        match opcode & 0xF000 {
+            0x2000 => {
+                let target = opcode & 0x0FFF;
+                self.subroutines.entry(target).or_default().calls += 1;
+                self.call_stack.push(Call {
+                    target,
+                    entered_at: self.total,
+                });
+            }
+            0x0000 if opcode == 0x00EE => {
+                if let Some(call) = self.call_stack.pop() {
+                    // Recursive calls are already covered by the outermost one.
+                    if !self.call_stack.iter().any(|c| c.target == call.target) {
+                        self.subroutines.entry(call.target).or_default().inclusive +=
+                            self.total - call.entered_at;
+                    }
+                }
+            }
+            _ => {}
+        }
+        if opcode & 0xF000 == 0x1000 && opcode & 0x0FFF == cpu.pc && !self.written {
+            println!("the ROM halted at 0x{:03X}, writing {}", cpu.pc, self.file);
+            if let Err(error) = self.write(&cpu.memory) {
+                eprintln!("error: could not write {}: {}", self.file, error);
+            }
+        }
+    }
+
+    pub fn finish(&mut self, cpu: &Chip8) -> Result<(), Box<dyn Error>> {
+        if self.written {
+            return Ok(());
+        }
+        self.write(&cpu.memory)
+    }
+
+    fn write(&mut self, memory: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.written = true;
+        fs::write(&self.file, self.report(memory))?;
+        Ok(())
+    }
+
+    pub fn report(&self, memory: &[u8]) -> String {
+        let mut report = format!("{} instructions executed\n", self.total);
+
+        report.push_str("\nhottest addresses\n");
+        let mut addresses: Vec<(usize, u64)> = self
+            .executions
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        addresses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for &(address, count) in addresses.iter().take(HOTTEST_ADDRESSES) {
+            let opcode = (memory[address] as u16) << 8 | memory[address + 1] as u16;
+            report.push_str(&format!(
+                "  0x{:03X}  {:>10}  {:>5.1}%  {}\n",
+                address,
+                count,
+                self.percent(count),
+                disassembler::disassemble(opcode)
+            ));
+        }
+
+        report.push_str("\nopcode classes\n");
+        let mut classes: Vec<(&u16, &u64)> = self.classes.iter().collect();
+        classes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (&key, &count) in classes {
+            report.push_str(&format!(
+                "  {}  {:>10}  {:>5.1}%\n",
+                class_name(key),
+                count,
+                self.percent(count)
+            ));
+        }
+
+        report.push_str("\nsubroutines by inclusive instructions\n");
+        let mut subroutines: Vec<(u16, u64, u64)> = self
+            .subroutines
+            .iter()
+            .map(|(&target, subroutine)| {
+                (target, self.inclusive(target, subroutine), subroutine.calls)
+            })
+            .collect();
+        subroutines.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for (target, inclusive, calls) in subroutines {
+            report.push_str(&format!(
+                "  0x{:03X}  {:>10}  {:>5.1}%  {} calls\n",
+                target,
+                inclusive,
+                self.percent(inclusive),
+                calls
+            ));
+        }
+
+        report.push_str("\nnever executed\n");
+        let mut never_executed = 0;
+        for (start, end) in self.unexecuted_ranges() {
+            never_executed += end - start;
+            report.push_str(&format!(
+                "  0x{:03X}-0x{:03X}  {} bytes\n",
+                start,
+                end - 1,
+                end - start
+            ));
+        }
+        report.push_str(&format!(
+            "  {} of {} ROM bytes\n",
+            never_executed,
+            self.rom_end - self.rom_start
+        ));
+        report
+    }
+
+    // Counts calls that have not returned yet, such as a main loop that was
+    // entered with 2NNN, up to the last recorded instruction.
+    fn inclusive(&self, target: u16, subroutine: &Subroutine) -> u64 {
+        let outermost = self.call_stack.iter().find(|call| call.target == target);
+        subroutine.inclusive + outermost.map_or(0, |call| self.total - call.entered_at)
+    }
+
+    // Ranges of ROM bytes that were never part of an executed instruction.
+    fn unexecuted_ranges(&self) -> Vec<(usize, usize)> {
+        let mut executed = vec![false; self.executions.len() + 1];
+        for (address, &count) in self.executions.iter().enumerate() {
+            if count > 0 {
+                executed[address] = true;
+                executed[address + 1] = true;
+            }
+        }
+        let mut ranges = Vec::new();
+        let mut start = None;
+        for (address, &executed) in executed
+            .iter()
+            .enumerate()
+            .take(self.rom_end)
+            .skip(self.rom_start)
+        {
+            match (executed, start) {
+                (false, None) => start = Some(address),
+                (true, Some(first)) => {
+                    ranges.push((first, address));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(first) = start {
+            ranges.push((first, self.rom_end));
+        }
+        ranges
+    }
+
+    fn percent(&self, count: u64) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            count as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+fn class_name(key: u16) -> String {
+    match key & 0xF000 {
+        0x0000 => match key {
+            0x00E0 | 0x00EE => format!("{:04X}", key),
+            _ => "0NNN".to_string(),
+        },
+        0x1000 | 0x2000 | 0xA000 | 0xB000 => format!("{:X}NNN", key >> 12),
+        0x5000 | 0x9000 => format!("{:X}XY0", key >> 12),
+        0x8000 => format!("8XY{:X}", key & 0x000F),
+        0xD000 => "DXYN".to_string(),
+        0xE000 | 0xF000 => format!("{:X}X{:02X}", key >> 12, key & 0x00FF),
+        _ => format!("{:X}XNN", key >> 12),
+    }
+}
@@ -0,0 +1,385 @@
+// Python bindings, built into the cdylib with --features python and packaged
+// as a wheel by maturin (see pyproject.toml).
+
+use crate::chip8::{Chip8, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::database::RomSettings;
+use crate::environment::{Environment, EnvironmentOptions, EnvironmentSpec};
+use crate::fontset::Font;
+use crate::quirks::Platform;
+use crate::rom::Rom;
+use crate::state;
+use numpy::ndarray::{Array2, Array3};
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyArray3};
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyString};
+use std::error::Error;
+
+#[pyclass(name = "Chip8", module = "chip8")]
+pub struct Emulator {
+    cpu: Chip8,
+    title: Option<String>,
+    palette: [u32; 2],
+}
+
+#[pymethods]
+impl Emulator {
+    // A seed makes CXNN reproducible; without one it is random.
+    #[new]
+    #[pyo3(signature = (seed=None))]
+    fn new(seed: Option<u64>) -> Emulator {
+        let cpu = match seed {
+            Some(seed) => Chip8::with_seed(seed),
+            None => Chip8::new(),
+        };
+        Emulator {
+            cpu,
+            title: None,
+            palette: RomSettings::default().palette,
+        }
+    }
+
+    // Resets the machine to the settings from the bundled database, or to
+    // those of the given platform id, and loads the ROM. The seed is kept.
+    #[pyo3(signature = (rom, platform=None))]
+    fn load_rom(&mut self, rom: &[u8], platform: Option<&str>) -> PyResult<()> {
+        let mut rom = Rom::from_bytes("rom", rom.to_vec()).map_err(to_py_error)?;
+        if let Some(id) = platform {
+            let platform = Platform::from_id(id)
+                .ok_or_else(|| PyValueError::new_err(format!("unknown platform {}", id)))?;
+            rom.settings.platform = Some(platform);
+            rom.settings.quirks = platform.quirks();
+            rom.settings.tick_rate = platform.tick_rate();
+            rom.settings.memory_size = platform.memory_size();
+        }
+        let mut cpu = Chip8::with_seed(self.cpu.seed);
+        rom.settings.apply(&mut cpu);
+        rom.load_into_memory(&mut cpu.memory, cpu.program_start)
+            .map_err(to_py_error)?;
+        cpu.load_font_set(&Font::for_platform(rom.settings.platform));
+        self.cpu = cpu;
+        self.title = rom.settings.title.clone();
+        self.palette = rom.settings.palette;
+        Ok(())
+    }
+
+    #[pyo3(signature = (file, platform=None))]
+    fn load_rom_file(&mut self, file: &str, platform: Option<&str>) -> PyResult<()> {
+        let bytes = std::fs::read(file)?;
+        self.load_rom(&bytes, platform)
+    }
+
+    // Executes a single instruction.
+    fn step(&mut self) {
+        self.cpu.run();
+    }
+
+    // Runs whole 60 Hz frames: timers first, then tick_rate instructions.
+    #[pyo3(signature = (frames=1))]
+    fn run_frame(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.cpu.run_frame();
+        }
+    }
+
+    // The screen as a (32, 64) uint8 array, 1 for lit pixels.
+    fn framebuffer<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<u8>> {
+        Array2::from_shape_fn((SCREEN_HEIGHT, SCREEN_WIDTH), |(y, x)| self.cpu.video[y][x])
+            .into_pyarray(py)
+    }
+
+    // The screen as a (32, 64, 3) uint8 RGB array in the ROM's palette.
+    fn rgb_framebuffer<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray3<u8>> {
+        Array3::from_shape_fn((SCREEN_HEIGHT, SCREEN_WIDTH, 3), |(y, x, channel)| {
+            let color = self.palette[(self.cpu.video[y][x] & 1) as usize];
+            (color >> (16 - 8 * channel)) as u8
+        })
+        .into_pyarray(py)
+    }
+
+    fn set_key(&mut self, key: usize, pressed: bool) -> PyResult<()> {
+        let slot = self
+            .cpu
+            .keys
+            .get_mut(key)
+            .ok_or_else(|| PyIndexError::new_err(format!("no key {}", key)))?;
+        *slot = pressed;
+        Ok(())
+    }
+
+    // Bit N is set while key N is held.
+    #[getter]
+    fn keys(&self) -> u16 {
+        self.cpu.keys_mask()
+    }
+
+    #[setter]
+    fn set_keys(&mut self, mask: u16) {
+        self.cpu.set_keys_mask(mask);
+    }
+
+    // Addresses wrap around the end of memory, as they do for the CPU.
+    fn read_memory<'py>(
+        &self,
+        py: Python<'py>,
+        address: usize,
+        length: usize,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        Ok(PyBytes::new(py, &memory_bytes(&self.cpu, address, length)?))
+    }
+
+    fn write_memory(&mut self, address: usize, data: &[u8]) -> PyResult<()> {
+        check_memory_range(&self.cpu, address, data.len())?;
+        for (offset, &byte) in data.iter().enumerate() {
+            let address = self.cpu.wrap_address(address + offset);
+            self.cpu.memory[address] = byte;
+        }
+        Ok(())
+    }
+
+    #[getter]
+    fn memory<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u8>> {
+        PyArray1::from_slice(py, &self.cpu.memory)
+    }
+
+    #[getter]
+    fn v(&self) -> [u8; 16] {
+        self.cpu.registers
+    }
+
+    fn set_register(&mut self, register: usize, value: u8) -> PyResult<()> {
+        let slot = self
+            .cpu
+            .registers
+            .get_mut(register)
+            .ok_or_else(|| PyIndexError::new_err(format!("no register V{:X}", register)))?;
+        *slot = value;
+        Ok(())
+    }
+
+    #[getter]
+    fn i(&self) -> u16 {
+        self.cpu.index
+    }
+
+    #[setter]
+    fn set_i(&mut self, value: u16) {
+        self.cpu.index = value;
+    }
+
+    #[getter]
+    fn pc(&self) -> u16 {
+        self.cpu.pc
+    }
+
+    #[setter]
+    fn set_pc(&mut self, value: u16) {
+        self.cpu.pc = value;
+    }
+
+    #[getter]
+    fn sp(&self) -> u8 {
+        self.cpu.sp
+    }
+
+    #[getter]
+    fn stack(&self) -> [u16; 16] {
+        self.cpu.stack
+    }
+
+    // Why the machine halted, or None while it runs.
+    #[getter]
+    fn fault(&self) -> Option<String> {
+        self.cpu.fault.map(|fault| fault.to_string())
+    }
+
+    #[getter]
+    fn delay_timer(&self) -> u8 {
+        self.cpu.delay_timer
+    }
+
+    #[setter]
+    fn set_delay_timer(&mut self, value: u8) {
+        self.cpu.delay_timer = value;
+    }
+
+    #[getter]
+    fn sound_timer(&self) -> u8 {
+        self.cpu.sound_timer
+    }
+
+    #[setter]
+    fn set_sound_timer(&mut self, value: u8) {
+        self.cpu.sound_timer = value;
+    }
+
+    #[getter]
+    fn cycles(&self) -> u64 {
+        self.cpu.cycles
+    }
+
+    #[getter]
+    fn title(&self) -> Option<String> {
+        self.title.clone()
+    }
+
+    // Background and foreground as 0xRRGGBB.
+    #[getter]
+    fn palette(&self) -> [u32; 2] {
+        self.palette
+    }
+
+    fn save_state<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &state::save_state(&self.cpu))
+    }
+
+    fn load_state(&mut self, data: &[u8]) -> PyResult<()> {
+        state::load_state(&mut self.cpu, data).map_err(to_py_error)
+    }
+}
+
+type StepOutput<'py> = (Bound<'py, PyArray2<u8>>, f64, bool, Bound<'py, PyDict>);
+
+// The Gym-style environment: reset() and step(action) with rewards and the
+// end of an episode read from memory, as described in environments.json.
+#[pyclass(name = "Env", module = "chip8")]
+pub struct PyEnvironment {
+    environment: Environment,
+}
+
+#[pymethods]
+impl PyEnvironment {
+    // The spec is a dict or a JSON string in the environments.json format.
+    #[new]
+    #[pyo3(signature = (rom, spec=None, frame_skip=4, sticky_action_probability=0.0, seed=None, max_frames=None))]
+    fn new(
+        rom: &[u8],
+        spec: Option<&Bound<'_, PyAny>>,
+        frame_skip: u32,
+        sticky_action_probability: f64,
+        seed: Option<u64>,
+        max_frames: Option<u64>,
+    ) -> PyResult<PyEnvironment> {
+        let rom = Rom::from_bytes("rom", rom.to_vec()).map_err(to_py_error)?;
+        let mut spec = match spec {
+            Some(spec) => {
+                let json = if spec.is_instance_of::<PyString>() {
+                    spec.extract::<String>()?
+                } else {
+                    let json = spec.py().import("json")?;
+                    json.call_method1("dumps", (spec,))?.extract::<String>()?
+                };
+                Some(EnvironmentSpec::from_json(&json).map_err(to_py_error)?)
+            }
+            None => crate::environment::lookup(&rom.hash()).map_err(to_py_error)?,
+        };
+        if let Some(max_frames) = max_frames {
+            spec.get_or_insert_with(EnvironmentSpec::default).max_frames = Some(max_frames);
+        }
+        let options = EnvironmentOptions {
+            frame_skip,
+            sticky_action_probability,
+            seed,
+        };
+        let environment = Environment::new(rom, spec, options).map_err(to_py_error)?;
+        Ok(PyEnvironment { environment })
+    }
+
+    // Returns the first observation of the new episode.
+    #[pyo3(signature = (seed=None))]
+    fn reset<'py>(
+        &mut self,
+        py: Python<'py>,
+        seed: Option<u64>,
+    ) -> PyResult<Bound<'py, PyArray2<u8>>> {
+        self.environment.reset(seed).map_err(to_py_error)?;
+        Ok(self.observation(py))
+    }
+
+    // Returns (observation, reward, done, info).
+    fn step<'py>(&mut self, py: Python<'py>, action: usize) -> PyResult<StepOutput<'py>> {
+        let result = self.environment.step(action).map_err(to_py_error)?;
+        let info = PyDict::new(py);
+        info.set_item("frame", self.environment.frame)?;
+        info.set_item("truncated", result.truncated)?;
+        info.set_item("seed", self.environment.episode_seed)?;
+        Ok((self.observation(py), result.reward, result.done, info))
+    }
+
+    // The screen as a (32, 64) uint8 array, 1 for lit pixels.
+    fn observation<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<u8>> {
+        let video = &self.environment.cpu.video;
+        Array2::from_shape_fn((SCREEN_HEIGHT, SCREEN_WIDTH), |(y, x)| video[y][x]).into_pyarray(py)
+    }
+
+    #[getter]
+    fn n_actions(&self) -> usize {
+        self.environment.actions().len()
+    }
+
+    // The key mask each action holds down.
+    #[getter]
+    fn actions(&self) -> Vec<u16> {
+        self.environment.actions().to_vec()
+    }
+
+    #[getter]
+    fn observation_shape(&self) -> (usize, usize) {
+        (SCREEN_HEIGHT, SCREEN_WIDTH)
+    }
+
+    #[getter]
+    fn frame(&self) -> u64 {
+        self.environment.frame
+    }
+
+    fn read_memory<'py>(
+        &self,
+        py: Python<'py>,
+        address: usize,
+        length: usize,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        Ok(PyBytes::new(
+            py,
+            &memory_bytes(&self.environment.cpu, address, length)?,
+        ))
+    }
+
+    #[getter]
+    fn v(&self) -> [u8; 16] {
+        self.environment.cpu.registers
+    }
+}
+
+// A range may wrap around the end of memory but not cover it more than once.
+fn check_memory_range(cpu: &Chip8, address: usize, length: usize) -> PyResult<()> {
+    match address.checked_add(length) {
+        Some(_) if length <= cpu.memory.len() => Ok(()),
+        _ => Err(PyValueError::new_err(format!(
+            "cannot access {} bytes at {}, memory is {} bytes",
+            length,
+            address,
+            cpu.memory.len()
+        ))),
+    }
+}
+
+fn memory_bytes(cpu: &Chip8, address: usize, length: usize) -> PyResult<Vec<u8>> {
+    check_memory_range(cpu, address, length)?;
+    Ok((address..address + length)
+        .map(|address| cpu.memory[cpu.wrap_address(address)])
+        .collect())
+}
+
+fn to_py_error(error: Box<dyn Error>) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+#[pymodule]
+pub fn chip8(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Emulator>()?;
+    module.add_class::<PyEnvironment>()?;
+    module.add("SCREEN_WIDTH", SCREEN_WIDTH)?;
+    module.add("SCREEN_HEIGHT", SCREEN_HEIGHT)?;
+    Ok(())
+}
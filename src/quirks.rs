@@ -0,0 +1,132 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Platform {
+    OriginalChip8,
+    HybridVip,
+    ModernChip8,
+    Chip48,
+    SuperChip1,
+    SuperChip,
+    XoChip,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quirks {
+    pub shift: bool,
+    pub memory_increment_by_x: bool,
+    pub memory_leave_i_unchanged: bool,
+    pub wrap: bool,
+    pub jump: bool,
+    pub vblank: bool,
+    pub logic: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks {
+            shift: true,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: true,
+            wrap: true,
+            jump: false,
+            vblank: false,
+            logic: false,
+        }
+    }
+}
+
+impl Platform {
+    pub const ALL: [Platform; 7] = [
+        Platform::OriginalChip8,
+        Platform::HybridVip,
+        Platform::ModernChip8,
+        Platform::Chip48,
+        Platform::SuperChip1,
+        Platform::SuperChip,
+        Platform::XoChip,
+    ];
+
+    pub fn from_id(id: &str) -> Option<Platform> {
+        match id {
+            "originalChip8" => Some(Platform::OriginalChip8),
+            "hybridVIP" => Some(Platform::HybridVip),
+            "modernChip8" => Some(Platform::ModernChip8),
+            "chip48" => Some(Platform::Chip48),
+            "superchip1" => Some(Platform::SuperChip1),
+            "superchip" => Some(Platform::SuperChip),
+            "xochip" => Some(Platform::XoChip),
+            _ => None,
+        }
+    }
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Platform::OriginalChip8 => "originalChip8",
+            Platform::HybridVip => "hybridVIP",
+            Platform::ModernChip8 => "modernChip8",
+            Platform::Chip48 => "chip48",
+            Platform::SuperChip1 => "superchip1",
+            Platform::SuperChip => "superchip",
+            Platform::XoChip => "xochip",
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        let original = Quirks {
+            shift: false,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: false,
+            wrap: false,
+            jump: false,
+            vblank: true,
+            logic: true,
+        };
+        match self {
+            Platform::OriginalChip8 | Platform::HybridVip => original,
+            Platform::ModernChip8 => Quirks {
+                vblank: false,
+                logic: false,
+                ..original
+            },
+            // SUPER-CHIP 1.0 still moves I by X in FX55 and FX65, like
+            // CHIP-48; 1.1 leaves it alone.
+            Platform::Chip48 | Platform::SuperChip1 => Quirks {
+                shift: true,
+                memory_increment_by_x: true,
+                jump: true,
+                vblank: false,
+                logic: false,
+                ..original
+            },
+            Platform::SuperChip => Quirks {
+                shift: true,
+                memory_leave_i_unchanged: true,
+                jump: true,
+                vblank: false,
+                logic: false,
+                ..original
+            },
+            Platform::XoChip => Quirks {
+                wrap: true,
+                vblank: false,
+                logic: false,
+                ..original
+            },
+        }
+    }
+
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::XoChip => 0x10000,
+            _ => 0x1000,
+        }
+    }
+
+    pub fn tick_rate(self) -> u32 {
+        match self {
+            Platform::OriginalChip8 | Platform::HybridVip => 15,
+            Platform::ModernChip8 => 12,
+            Platform::Chip48 | Platform::SuperChip1 | Platform::SuperChip => 30,
+            Platform::XoChip => 100,
+        }
+    }
+}
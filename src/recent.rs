@@ -0,0 +1,38 @@
+use crate::config;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+const RECENT_FILE: &str = "recent.json";
+const MAX_RECENT: usize = 10;
+
+// The ROMs opened most recently, newest first, as absolute paths.
+pub fn load() -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let file = match config::config_file(RECENT_FILE) {
+        Some(file) if file.exists() => file,
+        _ => return Ok(Vec::new()),
+    };
+    let contents = fs::read_to_string(&file)?;
+    let files =
+        serde_json::from_str(&contents).map_err(|e| format!("{}: {}", file.display(), e))?;
+    Ok(files)
+}
+
+// Moves the ROM to the front of the list, dropping the oldest beyond
+// MAX_RECENT. A list that cannot be read is started over.
+pub fn add(rom_file: &str) -> Result<(), Box<dyn Error>> {
+    let file = match config::config_file(RECENT_FILE) {
+        Some(file) => file,
+        None => return Ok(()),
+    };
+    let rom = fs::canonicalize(rom_file)?;
+    let mut files = load().unwrap_or_default();
+    files.retain(|recent| *recent != rom);
+    files.insert(0, rom);
+    files.truncate(MAX_RECENT);
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(file, serde_json::to_string_pretty(&files)?)?;
+    Ok(())
+}
@@ -1,24 +1,141 @@
+use crate::database::{self, RomSettings};
+use crate::fontset::Font;
+use crate::instructions;
+use crate::patch::{self, PatchSource};
+use crate::quirks::Platform;
+use sha1::Sha1;
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::prelude::*;
-
-const ROM_MEMORY: usize = 0xFFF - 0x200;
+use std::path::PathBuf;
 
 pub struct Rom {
+    name: String,
     bytes: Vec<u8>,
+    pub settings: RomSettings,
+    // The patch applied on top of the file, if any.
+    pub patch: Option<PathBuf>,
 }
 
 impl Rom {
     pub fn new(file: &str) -> Result<Rom, Box<dyn Error>> {
+        Rom::with_patch(file, &PatchSource::Auto)
+    }
+
+    // A patched ROM uses the database settings of the original when the
+    // database does not know the patched one, as is usual for translations
+    // and hacks.
+    pub fn with_patch(file: &str, patch: &PatchSource) -> Result<Rom, Box<dyn Error>> {
         let mut f = File::open(file)?;
-        let mut buffer: Vec<u8> = Vec::with_capacity(ROM_MEMORY);
+        let mut buffer: Vec<u8> = Vec::new();
         f.read_to_end(&mut buffer)?;
-        Ok(Rom { bytes: buffer })
+        let patch_file = match patch.resolve(file) {
+            Some(patch_file) => patch_file,
+            None => return Rom::from_bytes(file, buffer),
+        };
+        let patched = patch::apply(&buffer, &fs::read(&patch_file)?)
+            .map_err(|e| format!("{}: {}", patch_file.display(), e))?;
+        let original_hash = Sha1::from(&buffer).digest().to_string();
+        let mut rom = Rom::from_bytes(file, patched)?;
+        if database::lookup(&rom.hash())?.is_none() {
+            if let Some(settings) = database::lookup(&original_hash)? {
+                rom.check_fits(settings.memory_size, settings.program_start)?;
+                rom.settings = settings;
+            }
+        }
+        rom.patch = Some(patch_file);
+        Ok(rom)
+    }
+
+    pub fn from_bytes(name: &str, bytes: Vec<u8>) -> Result<Rom, Box<dyn Error>> {
+        let hash = Sha1::from(&bytes).digest().to_string();
+        let settings = database::lookup(&hash)?.unwrap_or_default();
+        let rom = Rom {
+            name: name.to_string(),
+            bytes,
+            settings,
+            patch: None,
+        };
+        rom.check_fits(rom.settings.memory_size, rom.settings.program_start)?;
+        Ok(rom)
+    }
+
+    pub fn hash(&self) -> String {
+        Sha1::from(&self.bytes).digest().to_string()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn size(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        match self.bytes.len() {
+            0 => warnings.push("the ROM is empty".to_string()),
+            1 => warnings.push("the ROM is a single byte, too short for an opcode".to_string()),
+            _ => {
+                let opcode = (self.bytes[0] as u16) << 8 | self.bytes[1] as u16;
+                let known = instructions::create_opcode_instructions_map()
+                    .contains_key(&instructions::handler_key(opcode));
+                if !known {
+                    warnings.push(format!(
+                        "the first opcode 0x{:04X} is not a chip-8 instruction",
+                        opcode
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+
+    pub fn load_into_memory(&self, memory: &mut [u8], address: u16) -> Result<(), Box<dyn Error>> {
+        self.check_fits(memory.len(), address)?;
+        let start = address as usize;
+        memory[start..start + self.bytes.len()].copy_from_slice(&self.bytes);
+        Ok(())
+    }
+
+    // The font is loaded after the ROM, so it must fit in memory and stay
+    // clear of the program to not overwrite it.
+    pub fn check_font(&self, font: &Font) -> Result<(), Box<dyn Error>> {
+        let font_start = self.settings.font_address as usize;
+        let font_end = font_start + font.size();
+        if font_end > self.settings.memory_size {
+            return Err(format!("the font does not fit in memory at 0x{:03X}", font_start).into());
+        }
+        let program_start = self.settings.program_start as usize;
+        let program_end = program_start + self.bytes.len();
+        if font_start < program_end && program_start < font_end {
+            return Err(format!(
+                "the font at 0x{:03X}-0x{:03X} overlaps the program at 0x{:03X}-0x{:03X}",
+                font_start,
+                font_end - 1,
+                program_start,
+                program_end - 1
+            )
+            .into());
+        }
+        Ok(())
     }
 
-    pub fn load_into_memory(&self, memory: &mut [u8; 0xFFF]) {
-        for (index, &byte) in self.bytes.iter().enumerate() {
-            memory[0x200 + index] = byte;
+    fn check_fits(&self, memory_size: usize, address: u16) -> Result<(), Box<dyn Error>> {
+        let capacity = memory_size.saturating_sub(address as usize);
+        if self.bytes.len() > capacity {
+            return Err(format!(
+                "{} is {} bytes, but only {} bytes fit between 0x{:03X} and the end of {} bytes of {} memory",
+                self.name,
+                self.bytes.len(),
+                capacity,
+                address,
+                memory_size,
+                self.settings.platform.map_or("chip-8", Platform::id)
+            )
+            .into());
         }
+        Ok(())
     }
 }
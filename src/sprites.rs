@@ -0,0 +1,110 @@
+use std::error::Error;
+use std::fs;
+
+const DRAW_SEARCH_WINDOW: usize = 16;
+
+#[derive(Clone, Copy)]
+pub struct DrawRecord {
+    pub x: u8,
+    pub y: u8,
+    pub height: u8,
+    pub address: u16,
+    pub collision: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct SpriteReference {
+    pub address: u16,
+    pub height: u8,
+    pub loaded_at: u16,
+    pub drawn_at: u16,
+}
+
+// Pairs every ANNN with the first DXYN that follows it, stopping at anything
+// that could change I or leave the straight-line code in between.
+pub fn find_sprite_references(memory: &[u8], start: usize, end: usize) -> Vec<SpriteReference> {
+    let end = end.min(memory.len());
+    let mut references: Vec<SpriteReference> = Vec::new();
+    let mut address = start;
+    while address + 1 < end {
+        let opcode = read_opcode(memory, address);
+        let target = (opcode & 0x0FFF) as usize;
+        if opcode & 0xF000 == 0xA000 && target >= start && target < end {
+            if let Some(reference) = find_draw_after(memory, address, end, opcode & 0x0FFF) {
+                let known = references
+                    .iter()
+                    .any(|r| r.address == reference.address && r.height == reference.height);
+                if !known {
+                    references.push(reference);
+                }
+            }
+        }
+        address += 1;
+    }
+    references.sort_by_key(|reference| reference.address);
+    references
+}
+
+fn find_draw_after(
+    memory: &[u8],
+    loaded_at: usize,
+    end: usize,
+    sprite: u16,
+) -> Option<SpriteReference> {
+    let mut address = loaded_at + 2;
+    for _ in 0..DRAW_SEARCH_WINDOW {
+        if address + 1 >= end {
+            return None;
+        }
+        let opcode = read_opcode(memory, address);
+        match opcode & 0xF000 {
+            0xD000 if opcode & 0x000F != 0 => {
+                return Some(SpriteReference {
+                    address: sprite,
+                    height: (opcode & 0x000F) as u8,
+                    loaded_at: loaded_at as u16,
+                    drawn_at: address as u16,
+                })
+            }
+            0x1000 | 0x2000 | 0xA000 | 0xB000 => return None,
+            0x0000 if opcode == 0x00EE => return None,
+            0xF000 if matches!(opcode & 0x00FF, 0x1E | 0x29 | 0x30 | 0x55 | 0x65) => return None,
+            _ => {}
+        }
+        address += 2;
+    }
+    None
+}
+
+pub fn sprite_rows(memory: &[u8], address: u16, height: u8) -> Vec<u8> {
+    (0..height as usize)
+        .map(|row| memory.get(address as usize + row).copied().unwrap_or(0))
+        .collect()
+}
+
+pub fn dump(
+    memory: &[u8],
+    references: &[SpriteReference],
+    file: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut contents = String::new();
+    for reference in references {
+        contents.push_str(&format!(
+            "sprite 0x{:03X} height {} loaded at 0x{:03X} drawn at 0x{:03X}\n",
+            reference.address, reference.height, reference.loaded_at, reference.drawn_at
+        ));
+        for row in sprite_rows(memory, reference.address, reference.height) {
+            let line: String = (0..8)
+                .map(|bit| if row >> (7 - bit) & 1 == 1 { '#' } else { '.' })
+                .collect();
+            contents.push_str(&format!("  {}  {:02X}\n", line, row));
+        }
+        contents.push('\n');
+    }
+    fs::write(file, contents)?;
+    Ok(())
+}
+
+fn read_opcode(memory: &[u8], address: usize) -> u16 {
+    (memory[address] as u16) << 8 | memory[address + 1] as u16
+}
@@ -0,0 +1,284 @@
+use crate::chip8::{self, Chip8, Fault};
+use rand_chacha::ChaCha20Rng;
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::error::Error;
+use std::mem;
+
+const VIDEO_SIZE: usize = chip8::SCREEN_WIDTH * chip8::SCREEN_HEIGHT;
+const STATE_MAGIC: &[u8; 4] = b"C8ST";
+const STATE_VERSION: u8 = 2;
+// Magic, version and memory size, then the registers after memory and video:
+// V0-VF, I, PC, SP, the stack, both timers, the vblank flag, the RNG seed and
+// how many words of its stream the RNG has used.
+const STATE_HEADER_SIZE: usize = 4 + 1 + 4;
+const STATE_REGISTERS_SIZE: usize = 16 + 2 + 2 + 1 + 16 * 2 + 1 + 1 + 1 + 8 + 16;
+
+pub struct Registers {
+    pub registers: [u8; 16],
+    pub index: u16,
+    pub pc: u16,
+    pub sp: u8,
+    pub stack: [u16; 16],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub rng: ChaCha20Rng,
+    pub fault: Option<Fault>,
+}
+
+pub struct Snapshot {
+    pub cpu: Registers,
+    pub bytes: Vec<u8>,
+}
+
+struct CompressedSnapshot {
+    cpu: Registers,
+    delta: Vec<u8>,
+}
+
+pub struct Rewind {
+    history: VecDeque<CompressedSnapshot>,
+    latest: Option<Snapshot>,
+    budget: usize,
+    used: usize,
+}
+
+impl Snapshot {
+    pub fn capture(cpu: &Chip8) -> Snapshot {
+        let mut bytes = Vec::with_capacity(cpu.memory.len() + VIDEO_SIZE);
+        bytes.extend_from_slice(&cpu.memory);
+        for row in cpu.video.iter() {
+            bytes.extend_from_slice(row);
+        }
+        Snapshot {
+            cpu: Registers {
+                registers: cpu.registers,
+                index: cpu.index,
+                pc: cpu.pc,
+                sp: cpu.sp,
+                stack: cpu.stack,
+                delay_timer: cpu.delay_timer,
+                sound_timer: cpu.sound_timer,
+                rng: cpu.rng.clone(),
+                fault: cpu.fault,
+            },
+            bytes,
+        }
+    }
+
+    pub fn restore(&self, cpu: &mut Chip8) {
+        let (memory, video) = self.bytes.split_at(cpu.memory.len());
+        cpu.memory.copy_from_slice(memory);
+        for (row, pixels) in cpu.video.iter_mut().zip(video.chunks(chip8::SCREEN_WIDTH)) {
+            row.copy_from_slice(pixels);
+        }
+        cpu.registers = self.cpu.registers;
+        cpu.index = self.cpu.index;
+        cpu.pc = self.cpu.pc;
+        cpu.sp = self.cpu.sp;
+        cpu.stack = self.cpu.stack;
+        cpu.delay_timer = self.cpu.delay_timer;
+        cpu.sound_timer = self.cpu.sound_timer;
+        cpu.rng = self.cpu.rng.clone();
+        cpu.fault = self.cpu.fault;
+        cpu.update_display = true;
+    }
+}
+
+// The size of a save state is fixed once the memory size is known.
+pub fn state_size(cpu: &Chip8) -> usize {
+    STATE_HEADER_SIZE + cpu.memory.len() + VIDEO_SIZE + STATE_REGISTERS_SIZE
+}
+
+// Saves the machine as a self-contained byte string without changing it. The
+// RNG is stored as its seed and its position in the stream, so a restored
+// copy draws the same numbers as the running machine.
+pub fn save_state(cpu: &Chip8) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(state_size(cpu));
+    bytes.extend_from_slice(STATE_MAGIC);
+    bytes.push(STATE_VERSION);
+    bytes.extend_from_slice(&(cpu.memory.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&cpu.memory);
+    for row in cpu.video.iter() {
+        bytes.extend_from_slice(row);
+    }
+    bytes.extend_from_slice(&cpu.registers);
+    bytes.extend_from_slice(&cpu.index.to_le_bytes());
+    bytes.extend_from_slice(&cpu.pc.to_le_bytes());
+    bytes.push(cpu.sp);
+    for address in cpu.stack.iter() {
+        bytes.extend_from_slice(&address.to_le_bytes());
+    }
+    bytes.push(cpu.delay_timer);
+    bytes.push(cpu.sound_timer);
+    bytes.push(cpu.vblank_wait as u8);
+    bytes.extend_from_slice(&cpu.seed.to_le_bytes());
+    bytes.extend_from_slice(&cpu.rng.get_word_pos().to_le_bytes());
+    bytes
+}
+
+pub fn load_state(cpu: &mut Chip8, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    if bytes.len() < STATE_HEADER_SIZE || &bytes[..4] != STATE_MAGIC {
+        return Err("not a save state".into());
+    }
+    if bytes[4] != STATE_VERSION {
+        return Err(format!("unsupported save state version {}", bytes[4]).into());
+    }
+    let memory_size = u32::from_le_bytes(bytes[5..9].try_into()?) as usize;
+    if memory_size != cpu.memory.len() {
+        return Err(format!(
+            "the save state has {} bytes of memory, this machine has {}",
+            memory_size,
+            cpu.memory.len()
+        )
+        .into());
+    }
+    if bytes.len() != state_size(cpu) {
+        return Err("the save state is truncated".into());
+    }
+    let (memory, rest) = bytes[STATE_HEADER_SIZE..].split_at(memory_size);
+    let (video, registers) = rest.split_at(VIDEO_SIZE);
+    cpu.memory.copy_from_slice(memory);
+    for (row, pixels) in cpu.video.iter_mut().zip(video.chunks(chip8::SCREEN_WIDTH)) {
+        row.copy_from_slice(pixels);
+    }
+    let u16_at = |offset: usize| u16::from_le_bytes([registers[offset], registers[offset + 1]]);
+    cpu.registers.copy_from_slice(&registers[..16]);
+    cpu.index = u16_at(16);
+    cpu.pc = u16_at(18);
+    cpu.sp = registers[20].min(cpu.stack.len() as u8);
+    for (slot, address) in cpu.stack.iter_mut().enumerate() {
+        *address = u16_at(21 + slot * 2);
+    }
+    cpu.delay_timer = registers[53];
+    cpu.sound_timer = registers[54];
+    cpu.vblank_wait = registers[55] != 0;
+    cpu.seed = u64::from_le_bytes(registers[56..64].try_into()?);
+    let position = u128::from_le_bytes(registers[64..80].try_into()?);
+    cpu.rng = chip8::seeded_rng(cpu.seed, position);
+    // Faults are not saved; pc is still on the instruction that faulted, so
+    // it faults again when it runs.
+    cpu.fault = None;
+    cpu.update_display = true;
+    Ok(())
+}
+
+impl Rewind {
+    pub fn new(budget: usize) -> Rewind {
+        Rewind {
+            history: VecDeque::new(),
+            latest: None,
+            budget,
+            used: 0,
+        }
+    }
+
+    pub fn push(&mut self, cpu: &Chip8) {
+        let snapshot = Snapshot::capture(cpu);
+        if let Some(previous) = self.latest.replace(snapshot) {
+            let newest = self.latest.as_ref().unwrap();
+            let compressed = CompressedSnapshot {
+                cpu: previous.cpu,
+                delta: compress(&xor(&previous.bytes, &newest.bytes)),
+            };
+            self.used += compressed.size();
+            self.history.push_back(compressed);
+        }
+        while self.used > self.budget {
+            match self.history.pop_front() {
+                Some(oldest) => self.used -= oldest.size(),
+                None => break,
+            }
+        }
+    }
+
+    // How many snapshots there are to go back through.
+    pub fn len(&self) -> usize {
+        self.history.len() + self.latest.is_some() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    pub fn rewind(&mut self, cpu: &mut Chip8) -> bool {
+        let latest = match self.latest.take() {
+            Some(latest) => latest,
+            None => return false,
+        };
+        latest.restore(cpu);
+        self.latest = match self.history.pop_back() {
+            Some(previous) => {
+                self.used -= previous.size();
+                Some(Snapshot {
+                    cpu: previous.cpu,
+                    bytes: xor(&latest.bytes, &decompress(&previous.delta)),
+                })
+            }
+            None => Some(latest),
+        };
+        true
+    }
+}
+
+impl CompressedSnapshot {
+    fn size(&self) -> usize {
+        mem::size_of::<CompressedSnapshot>() + self.delta.len()
+    }
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b.iter()).map(|(x, y)| x ^ y).collect()
+}
+
+// Deltas are mostly zeroes, so they are stored as alternating runs:
+// a zero run length followed by a literal run length and its bytes.
+pub fn compress(delta: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    let mut offset = 0;
+    while offset < delta.len() {
+        let zeroes = delta[offset..].iter().take_while(|&&b| b == 0).count();
+        offset += zeroes;
+        let literals = delta[offset..].iter().take_while(|&&b| b != 0).count();
+        write_varint(&mut compressed, zeroes);
+        write_varint(&mut compressed, literals);
+        compressed.extend_from_slice(&delta[offset..offset + literals]);
+        offset += literals;
+    }
+    compressed
+}
+
+pub fn decompress(compressed: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    let mut offset = 0;
+    while offset < compressed.len() {
+        let zeroes = read_varint(compressed, &mut offset);
+        let literals = read_varint(compressed, &mut offset);
+        delta.resize(delta.len() + zeroes, 0);
+        delta.extend_from_slice(&compressed[offset..offset + literals]);
+        offset += literals;
+    }
+    delta
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        buffer.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn read_varint(buffer: &[u8], offset: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = buffer[*offset];
+        *offset += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
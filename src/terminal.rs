@@ -0,0 +1,302 @@
+use crate::frontend::{Frontend, Hotkey};
+use chip_8_emulator::chip8::{Chip8, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::time::Duration;
+
+// Most terminals only report key presses, so a key counts as held until this
+// many frames after its last press or auto-repeat.
+const HOLD_FRAMES: u64 = 30;
+const HELD_UNTIL_RELEASE: u64 = u64::MAX;
+const BRAILLE_BASE: u32 = 0x2800;
+// Dot bits of a braille cell, indexed by [row][column].
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+const STATUS_ROWS: u16 = 1;
+
+const DEFAULT_LAYOUT: [(char, usize); 16] = [
+    ('1', 0x1),
+    ('2', 0x2),
+    ('3', 0x3),
+    ('4', 0xC),
+    ('q', 0x4),
+    ('w', 0x5),
+    ('e', 0x6),
+    ('r', 0xD),
+    ('a', 0x7),
+    ('s', 0x8),
+    ('d', 0x9),
+    ('f', 0xE),
+    ('z', 0xA),
+    ('x', 0x0),
+    ('c', 0xB),
+    ('v', 0xF),
+];
+
+#[derive(Clone, Copy)]
+enum Layout {
+    // Two pixel rows per character cell, each pixel scaled up.
+    HalfBlock { scale: u16 },
+    // A 2x4 block of pixels per character cell.
+    Braille,
+}
+
+impl Layout {
+    fn size(self) -> (u16, u16) {
+        match self {
+            Layout::HalfBlock { scale } => (
+                SCREEN_WIDTH as u16 * scale,
+                SCREEN_HEIGHT as u16 / 2 * scale,
+            ),
+            Layout::Braille => (SCREEN_WIDTH as u16 / 2, SCREEN_HEIGHT as u16 / 4),
+        }
+    }
+}
+
+pub struct TerminalFrontend {
+    title: String,
+    palette: [Color; 2],
+    keymap: HashMap<KeyCode, usize>,
+    key_expiry: [u64; 16],
+    rewind_expiry: u64,
+    release_events: bool,
+    frame: u64,
+    layout: Layout,
+    columns: u16,
+    rows: u16,
+    resized: bool,
+    beeping: bool,
+}
+
+impl TerminalFrontend {
+    pub fn new(
+        title: &str,
+        palette: [u32; 2],
+        rom_keys: &[(String, u8)],
+    ) -> Result<TerminalFrontend, Box<dyn Error>> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        // Terminals that speak the kitty keyboard protocol also report key
+        // releases, which makes held keys exact.
+        let release_events = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if release_events {
+            execute!(
+                stdout,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        let (columns, rows) = terminal::size()?;
+        Ok(TerminalFrontend {
+            title: title.to_string(),
+            palette: [rgb(palette[0]), rgb(palette[1])],
+            keymap: create_keymap(rom_keys),
+            key_expiry: [0; 16],
+            rewind_expiry: 0,
+            release_events,
+            frame: 0,
+            layout: choose_layout(columns, rows),
+            columns,
+            rows,
+            resized: false,
+            beeping: false,
+        })
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, hotkeys: &mut Vec<Hotkey>) {
+        let pressed = key.kind != KeyEventKind::Release;
+        let expiry = if !pressed {
+            0
+        } else if self.release_events {
+            HELD_UNTIL_RELEASE
+        } else {
+            self.frame + HOLD_FRAMES
+        };
+        match key.code {
+            KeyCode::Esc if pressed => hotkeys.push(Hotkey::Quit),
+            KeyCode::Char('c') if pressed && key.modifiers.contains(KeyModifiers::CONTROL) => {
+                hotkeys.push(Hotkey::Quit)
+            }
+            KeyCode::Char('p') if key.kind == KeyEventKind::Press => hotkeys.push(Hotkey::Pause),
+            KeyCode::F(12) if key.kind == KeyEventKind::Press => hotkeys.push(Hotkey::Screenshot),
+            KeyCode::F(10) if key.kind == KeyEventKind::Press => {
+                hotkeys.push(Hotkey::ToggleRecording)
+            }
+            KeyCode::Backspace => self.rewind_expiry = expiry,
+            code => {
+                let code = match code {
+                    KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+                    code => code,
+                };
+                if let Some(&key) = self.keymap.get(&code) {
+                    self.key_expiry[key] = expiry;
+                }
+            }
+        }
+    }
+
+    fn render(&self, cpu: &Chip8) -> io::Result<()> {
+        let mut out: Vec<u8> = Vec::new();
+        let (width, height) = self.layout.size();
+        let left = self.columns.saturating_sub(width) / 2;
+        let top = self.rows.saturating_sub(height + STATUS_ROWS) / 2;
+        let visible_width = width.min(self.columns);
+        let visible_height = height.min(self.rows.saturating_sub(STATUS_ROWS));
+        let mut colors = None;
+        for row in 0..visible_height {
+            queue!(out, MoveTo(left, top + row))?;
+            for column in 0..visible_width {
+                let (cell, foreground, background) = self.cell(cpu, column, row);
+                if colors != Some((foreground, background)) {
+                    queue!(
+                        out,
+                        SetForegroundColor(foreground),
+                        SetBackgroundColor(background)
+                    )?;
+                    colors = Some((foreground, background));
+                }
+                queue!(out, Print(cell))?;
+            }
+        }
+        let status = format!("{}  esc quit  p pause  backspace rewind", self.title);
+        let status: String = status.chars().take(self.columns as usize).collect();
+        queue!(
+            out,
+            ResetColor,
+            MoveTo(0, self.rows.saturating_sub(STATUS_ROWS)),
+            Print(status)
+        )?;
+        let mut stdout = io::stdout();
+        stdout.write_all(&out)?;
+        stdout.flush()
+    }
+
+    fn cell(&self, cpu: &Chip8, column: u16, row: u16) -> (char, Color, Color) {
+        let lit = |x: usize, y: usize| cpu.video[y][x] == 1;
+        match self.layout {
+            Layout::HalfBlock { scale } => {
+                let x = (column / scale) as usize;
+                let upper = (row * 2 / scale) as usize;
+                let lower = ((row * 2 + 1) / scale) as usize;
+                let color = |on: bool| self.palette[on as usize];
+                ('▀', color(lit(x, upper)), color(lit(x, lower)))
+            }
+            Layout::Braille => {
+                let mut dots = 0;
+                for (dy, bits) in BRAILLE_DOTS.iter().enumerate() {
+                    for (dx, bit) in bits.iter().enumerate() {
+                        if lit(column as usize * 2 + dx, row as usize * 4 + dy) {
+                            dots |= bit;
+                        }
+                    }
+                }
+                let cell = std::char::from_u32(BRAILLE_BASE + dots).unwrap_or(' ');
+                (cell, self.palette[1], self.palette[0])
+            }
+        }
+    }
+}
+
+impl Frontend for TerminalFrontend {
+    fn capture(&mut self, cpu: &mut Chip8) -> Vec<Hotkey> {
+        let mut hotkeys = Vec::new();
+        while let Ok(true) = event::poll(Duration::ZERO) {
+            match event::read() {
+                Ok(Event::Key(key)) => self.handle_key(key, &mut hotkeys),
+                Ok(Event::Resize(columns, rows)) => {
+                    self.columns = columns;
+                    self.rows = rows;
+                    self.layout = choose_layout(columns, rows);
+                    self.resized = true;
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        for (pressed, &expiry) in cpu.keys.iter_mut().zip(self.key_expiry.iter()) {
+            *pressed = self.frame < expiry;
+        }
+        hotkeys
+    }
+
+    fn is_rewind_held(&self) -> bool {
+        self.frame < self.rewind_expiry
+    }
+
+    fn present(&mut self, cpu: &Chip8) {
+        if self.resized {
+            self.resized = false;
+            let _ = execute!(io::stdout(), ResetColor, Clear(ClearType::All));
+        }
+        let _ = self.render(cpu);
+    }
+
+    fn end_frame(&mut self, cpu: &Chip8) -> bool {
+        self.frame += 1;
+        let beeping = cpu.sound_timer > 0;
+        if beeping && !self.beeping {
+            let _ = execute!(io::stdout(), Print('\x07'));
+        }
+        self.beeping = beeping;
+        self.resized
+    }
+}
+
+impl Drop for TerminalFrontend {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.release_events {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout, ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn create_keymap(rom_keys: &[(String, u8)]) -> HashMap<KeyCode, usize> {
+    let mut keymap: HashMap<KeyCode, usize> = DEFAULT_LAYOUT
+        .iter()
+        .map(|&(c, key)| (KeyCode::Char(c), key))
+        .collect();
+    for (button, key) in rom_keys {
+        let code = match button.as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "a" => KeyCode::Char(' '),
+            "b" => KeyCode::Enter,
+            _ => continue,
+        };
+        keymap.insert(code, *key as usize);
+    }
+    keymap
+}
+
+// Uses the largest half-block scale that fits and falls back to braille on
+// terminals smaller than 64x17.
+fn choose_layout(columns: u16, rows: u16) -> Layout {
+    let rows = rows.saturating_sub(STATUS_ROWS);
+    let scale = (columns / SCREEN_WIDTH as u16).min(rows / (SCREEN_HEIGHT as u16 / 2));
+    if scale >= 1 {
+        Layout::HalfBlock { scale }
+    } else {
+        Layout::Braille
+    }
+}
+
+fn rgb(color: u32) -> Color {
+    Color::Rgb {
+        r: (color >> 16) as u8,
+        g: (color >> 8) as u8,
+        b: color as u8,
+    }
+}
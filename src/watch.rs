@@ -0,0 +1,116 @@
+use crate::analysis;
+use crate::chip8::Chip8;
+use crate::rom::Rom;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WatchMode {
+    // Reloads the ROM with its database settings and reopens the frontend,
+    // as if the emulator had been restarted.
+    Reset,
+    // Reboots the machine with the new ROM in the same window and keymap.
+    KeepWindow,
+    // Patches the changed bytes into the running machine when they are all
+    // data, and reboots like KeepWindow when any code changed.
+    KeepPc,
+}
+
+impl WatchMode {
+    pub const NAMES: [&'static str; 3] = ["reset", "keep-window", "keep-pc"];
+
+    pub fn parse(name: &str) -> Option<WatchMode> {
+        match name {
+            "reset" => Some(WatchMode::Reset),
+            "keep-window" => Some(WatchMode::KeepWindow),
+            "keep-pc" => Some(WatchMode::KeepPc),
+            _ => None,
+        }
+    }
+}
+
+// Polls the modification time of the ROM and its patch. A change is only
+// reported once the file has stopped changing for a poll, so a build that
+// writes the ROM in several steps is not loaded half written.
+pub struct RomWatcher {
+    files: Vec<PathBuf>,
+    seen: Vec<Option<SystemTime>>,
+    changed: bool,
+    last_poll: Instant,
+}
+
+impl RomWatcher {
+    pub fn new(rom_file: &str, rom: &Rom) -> RomWatcher {
+        let mut files = vec![PathBuf::from(rom_file)];
+        files.extend(rom.patch.clone());
+        let seen = files.iter().map(modified).collect();
+        RomWatcher {
+            files,
+            seen,
+            changed: false,
+            last_poll: Instant::now(),
+        }
+    }
+
+    // Returns true when the ROM should be reloaded.
+    pub fn poll(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+        let current: Vec<Option<SystemTime>> = self.files.iter().map(modified).collect();
+        if current != self.seen {
+            self.seen = current;
+            self.changed = true;
+            return false;
+        }
+        // A file that is missing is most likely being rewritten.
+        if self.changed && current.iter().all(Option::is_some) {
+            self.changed = false;
+            return true;
+        }
+        false
+    }
+
+    // Follows another ROM, or a patch that appeared, moved or went away.
+    pub fn watch(&mut self, rom_file: &str, rom: &Rom) {
+        *self = RomWatcher::new(rom_file, rom);
+    }
+}
+
+fn modified(file: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// The addresses where the new ROM differs from the old one, when none of
+// them is code in either version. The new ROM must be booted into `fresh`,
+// which gives the memory around it for the analysis.
+pub fn changed_data(old: &Rom, new: &Rom, fresh: &Chip8) -> Option<Vec<usize>> {
+    if old.size() != new.size()
+        || old.settings.program_start != new.settings.program_start
+        || old.settings.memory_size != new.settings.memory_size
+    {
+        return None;
+    }
+    let start = fresh.program_start as usize;
+    let end = start + new.size();
+    let mut old_memory = fresh.memory.clone();
+    old_memory[start..end].copy_from_slice(old.bytes());
+    let old_code = analysis::analyze(&old_memory, fresh.program_start, end).code;
+    let new_code = analysis::analyze(&fresh.memory, fresh.program_start, end).code;
+    let changed: Vec<usize> = (start..end)
+        .filter(|&address| old_memory[address] != fresh.memory[address])
+        .collect();
+    if changed
+        .iter()
+        .any(|&address| old_code[address] || new_code[address])
+    {
+        return None;
+    }
+    Some(changed)
+}
@@ -0,0 +1,74 @@
+use crate::chip8::{Chip8, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::fontset::Font;
+use crate::rom::Rom;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct Emulator {
+    cpu: Chip8,
+    title: Option<String>,
+    palette: [u32; 2],
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    // Settings come from the bundled database when the ROM is known.
+    #[wasm_bindgen(constructor)]
+    pub fn new(rom: &[u8], seed: u32) -> Result<Emulator, JsValue> {
+        let rom = Rom::from_bytes("rom", rom.to_vec()).map_err(to_js_error)?;
+        let mut cpu = Chip8::with_seed(seed as u64);
+        rom.settings.apply(&mut cpu);
+        rom.load_into_memory(&mut cpu.memory, cpu.program_start)
+            .map_err(to_js_error)?;
+        cpu.load_font_set(&Font::for_platform(rom.settings.platform));
+        Ok(Emulator {
+            cpu,
+            title: rom.settings.title.clone(),
+            palette: rom.settings.palette,
+        })
+    }
+
+    pub fn width() -> usize {
+        SCREEN_WIDTH
+    }
+
+    pub fn height() -> usize {
+        SCREEN_HEIGHT
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.title.clone()
+    }
+
+    // Background and foreground as 0xRRGGBB.
+    pub fn palette(&self) -> Vec<u32> {
+        self.palette.to_vec()
+    }
+
+    pub fn run_frame(&mut self) {
+        self.cpu.run_frame();
+    }
+
+    // Why the machine halted, or undefined while it runs.
+    pub fn fault(&self) -> Option<String> {
+        self.cpu.fault.map(|fault| fault.to_string())
+    }
+
+    // One byte per pixel, row by row, 1 for lit pixels.
+    pub fn framebuffer(&self) -> Vec<u8> {
+        self.cpu.video.iter().flatten().copied().collect()
+    }
+
+    // Bit N is set while key N is held.
+    pub fn set_keys(&mut self, mask: u16) {
+        self.cpu.set_keys_mask(mask);
+    }
+
+    pub fn sound_active(&self) -> bool {
+        self.cpu.sound_timer > 0
+    }
+}
+
+fn to_js_error(error: Box<dyn std::error::Error>) -> JsValue {
+    JsValue::from_str(&error.to_string())
+}
@@ -0,0 +1,124 @@
+use crate::browser::RomBrowser;
+use crate::frontend::{Frontend, Hotkey};
+use crate::graphics;
+use crate::hud::Hud;
+use crate::input::{self, Keymap, Shortcut};
+use crate::inspector::SpriteInspector;
+use chip_8_emulator::chip8::Chip8;
+use sdl2::render::WindowCanvas;
+use sdl2::{EventPump, Sdl};
+use std::error::Error;
+
+pub struct SdlFrontend {
+    _context: Sdl,
+    canvas: WindowCanvas,
+    events: EventPump,
+    keymap: Keymap,
+    palette: [u32; 2],
+    hud: Hud,
+    sprite_inspector: Option<SpriteInspector>,
+    browser: RomBrowser,
+}
+
+impl SdlFrontend {
+    pub fn new(
+        title: &str,
+        palette: [u32; 2],
+        rom_keys: &[(String, u8)],
+        hud: bool,
+        sprite_inspector: bool,
+        rom_dir: &str,
+        cpu: &mut Chip8,
+    ) -> Result<SdlFrontend, Box<dyn Error>> {
+        let context = sdl2::init()?;
+        let mut canvas = graphics::create_window(title, &context)?;
+        graphics::create_default_screen(&mut canvas, palette);
+        let events = context.event_pump()?;
+        let sprite_inspector = if sprite_inspector {
+            cpu.record_draws = true;
+            Some(SpriteInspector::new(&context, cpu)?)
+        } else {
+            None
+        };
+        Ok(SdlFrontend {
+            _context: context,
+            canvas,
+            events,
+            keymap: input::create_keymap(rom_keys),
+            palette,
+            hud: Hud::new(hud),
+            sprite_inspector,
+            browser: RomBrowser::new(rom_dir),
+        })
+    }
+}
+
+impl Frontend for SdlFrontend {
+    fn capture(&mut self, cpu: &mut Chip8) -> Vec<Hotkey> {
+        let mut hotkeys = Vec::new();
+        for shortcut in input::capture(&mut self.events, cpu, &self.keymap, self.browser.visible) {
+            match shortcut {
+                Shortcut::Quit => hotkeys.push(Hotkey::Quit),
+                Shortcut::Pause => hotkeys.push(Hotkey::Pause),
+                Shortcut::Screenshot => hotkeys.push(Hotkey::Screenshot),
+                Shortcut::ToggleRecording => hotkeys.push(Hotkey::ToggleRecording),
+                Shortcut::CloseWindow(id) if id == self.canvas.window().id() => {
+                    hotkeys.push(Hotkey::Quit)
+                }
+                Shortcut::CloseWindow(id) => {
+                    if self
+                        .sprite_inspector
+                        .as_ref()
+                        .map(SpriteInspector::window_id)
+                        == Some(id)
+                    {
+                        self.sprite_inspector = None;
+                        cpu.record_draws = false;
+                    }
+                }
+                Shortcut::OpenBrowser => {
+                    self.browser.show();
+                    cpu.set_keys_mask(0);
+                }
+                Shortcut::BrowserKey(keycode) => {
+                    if let Some(file) = self.browser.handle_key(keycode) {
+                        hotkeys.push(Hotkey::Open(file));
+                    }
+                    cpu.update_display = true;
+                }
+                Shortcut::ToggleHud => {
+                    self.hud.visible = !self.hud.visible;
+                    cpu.update_display = true;
+                }
+            }
+        }
+        hotkeys
+    }
+
+    fn is_rewind_held(&self) -> bool {
+        input::is_rewind_held(&self.events)
+    }
+
+    fn is_menu_open(&self) -> bool {
+        self.browser.visible
+    }
+
+    fn present(&mut self, cpu: &Chip8) {
+        graphics::draw_video(&mut self.canvas, cpu.video, self.palette);
+        if self.hud.visible {
+            self.hud.draw(&mut self.canvas, cpu);
+        }
+        if self.browser.visible {
+            self.browser.draw(&mut self.canvas);
+        }
+        self.canvas.present();
+    }
+
+    fn end_frame(&mut self, cpu: &Chip8) -> bool {
+        self.hud.end_frame(cpu);
+        if let Some(inspector) = &mut self.sprite_inspector {
+            inspector.draw(cpu);
+        }
+        self.hud.visible
+    }
+}
@@ -0,0 +1,135 @@
+// Static analysis of small hand-assembled programs loaded at 0x200.
+
+use chip_8_emulator::analysis::{analyze, Analysis, EdgeKind, Finding};
+
+const START: u16 = 0x200;
+
+fn analyze_program(program: &[u16]) -> Analysis {
+    let mut memory = vec![0; 0x1000];
+    for (offset, opcode) in program.iter().enumerate() {
+        let address = START as usize + 2 * offset;
+        memory[address..address + 2].copy_from_slice(&opcode.to_be_bytes());
+    }
+    analyze(&memory, START, START as usize + 2 * program.len())
+}
+
+// The main program calls the first of `depth` subroutines, each of which
+// calls the next; every subroutine is a call and a return.
+fn call_chain(depth: u16) -> Vec<u16> {
+    let mut program = vec![0x2204, 0x1202];
+    for level in 1..=depth {
+        let next = 0x200 + 4 * (level + 1);
+        if level < depth {
+            program.extend_from_slice(&[0x2000 | next, 0x00EE]);
+        } else {
+            program.extend_from_slice(&[0x00EE, 0x00EE]);
+        }
+    }
+    program
+}
+
+fn deep_stack(analysis: &Analysis) -> Option<&Vec<u16>> {
+    analysis.findings.iter().find_map(|finding| match finding {
+        Finding::DeepStack { path } => Some(path),
+        _ => None,
+    })
+}
+
+#[test]
+fn call_chains_deeper_than_the_stack_are_reported() {
+    let analysis = analyze_program(&call_chain(16));
+    assert_eq!(analysis.max_call_depth, Some(16));
+    assert!(deep_stack(&analysis).is_none());
+
+    let analysis = analyze_program(&call_chain(17));
+    assert_eq!(analysis.max_call_depth, Some(17));
+    let path = deep_stack(&analysis).unwrap();
+    let expected: Vec<u16> = (0..=17).map(|level| 0x200 + 4 * level).collect();
+    assert_eq!(path, &expected);
+}
+
+#[test]
+fn call_depth_is_found_without_walking_every_path() {
+    // Forty levels of two subroutines that both call both subroutines of the
+    // next level: 2^40 paths, but only 80 subroutines. The main program
+    // calls the first of the top level only.
+    let levels = 40;
+    let mut program = vec![0x2204, 0x1202];
+    for level in 0..levels {
+        let next = 0x204 + 6 * 2 * (level + 1);
+        for _ in 0..2 {
+            if level + 1 < levels {
+                program.extend_from_slice(&[0x2000 | next, 0x2000 | (next + 6), 0x00EE]);
+            } else {
+                program.extend_from_slice(&[0x00EE, 0x00EE, 0x00EE]);
+            }
+        }
+    }
+    let analysis = analyze_program(&program);
+    assert_eq!(analysis.functions.len(), 2 * levels as usize);
+    assert_eq!(analysis.max_call_depth, Some(levels as usize));
+    assert_eq!(deep_stack(&analysis).unwrap().len(), levels as usize + 1);
+}
+
+#[test]
+fn direct_recursion_is_reported() {
+    // 0x204 calls itself before it returns.
+    let analysis = analyze_program(&[0x2204, 0x1202, 0x2204, 0x00EE]);
+    let recursive: Vec<u16> = analysis
+        .findings
+        .iter()
+        .filter_map(|finding| match finding {
+            Finding::Recursion { function } => Some(*function),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(recursive, [0x204]);
+}
+
+#[test]
+fn writes_into_code_are_reported() {
+    // FX55 stores V0 and V1 over the first instruction.
+    let analysis = analyze_program(&[0xA200, 0xF155, 0x1204]);
+    assert!(analysis.findings.iter().any(|finding| matches!(
+        finding,
+        Finding::SelfModifyingWrite {
+            at: 0x202,
+            target: 0x200
+        }
+    )));
+    // The same store into data after the program is fine.
+    let analysis = analyze_program(&[0xA300, 0xF155, 0x1204]);
+    assert!(!analysis
+        .findings
+        .iter()
+        .any(|finding| matches!(finding, Finding::SelfModifyingWrite { .. })));
+}
+
+#[test]
+fn skips_over_jumps_split_the_blocks() {
+    // 0x200 skips the jump to 0x208 when V0 is 0, landing on 0x204. Both ends
+    // halt by jumping to themselves, which starts a block of their own.
+    let analysis = analyze_program(&[0x3000, 0x1208, 0x6001, 0x1206, 0x6102, 0x120A]);
+    let blocks: Vec<u16> = analysis.blocks.keys().copied().collect();
+    assert_eq!(blocks, [0x200, 0x202, 0x204, 0x206, 0x208, 0x20A]);
+    assert!(
+        analysis.blocks[&0x200].successors
+            == [(0x202, EdgeKind::Fallthrough), (0x204, EdgeKind::Skip)]
+    );
+    assert!(analysis.blocks[&0x202].successors == [(0x208, EdgeKind::Jump)]);
+    assert_eq!(analysis.instructions.len(), 6);
+    assert!(analysis.findings.is_empty());
+}
+
+#[test]
+fn code_nothing_reaches_is_reported() {
+    // Three instructions after the halt that nothing jumps to.
+    let analysis = analyze_program(&[0x6001, 0x1202, 0x6102, 0x7101, 0x8010]);
+    assert!(analysis.findings.iter().any(|finding| matches!(
+        finding,
+        Finding::UnreachableCode {
+            start: 0x204,
+            end: 0x20A
+        }
+    )));
+}
@@ -0,0 +1,64 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use chip_8_emulator::capture::{self, GifRecorder, Video, Y4mWriter, MAX_SCALE};
+use chip_8_emulator::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use std::fs::File;
+use std::path::Path;
+
+const PALETTE: [u32; 2] = [0x000000, 0xFFFFFF];
+
+fn capture_file(name: &str) -> String {
+    Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+// The delay of every frame, in hundredths of a second.
+fn delays(file: &str) -> Vec<u16> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(File::open(file).unwrap()).unwrap();
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        delays.push(frame.delay);
+    }
+    delays
+}
+
+#[test]
+fn long_still_screens_keep_their_length() {
+    let file = capture_file("still.gif");
+    let blank: Video = [[0; SCREEN_WIDTH]; SCREEN_HEIGHT];
+    let mut lit = blank;
+    lit[0][0] = 1;
+    let mut recorder = GifRecorder::new(&file, PALETTE, 1).unwrap();
+    // Twelve minutes of the same screen, then one frame of another.
+    for _ in 0..12 * 60 * 60 {
+        recorder.push_frame(&blank).unwrap();
+    }
+    recorder.push_frame(&lit).unwrap();
+    recorder.finish().unwrap();
+    let delays = delays(&file);
+    assert_eq!(delays[0], u16::MAX);
+    assert_eq!(delays.len(), 3);
+    let total: u64 = delays.iter().map(|&delay| delay as u64).sum();
+    // 43201 frames at 60 Hz, rounded to hundredths of a second.
+    assert_eq!(total, 72002);
+}
+
+#[test]
+fn scales_are_bounded_for_every_format() {
+    let blank: Video = [[0; SCREEN_WIDTH]; SCREEN_HEIGHT];
+    let gif = capture_file("large.gif");
+    assert!(GifRecorder::new(&gif, PALETTE, MAX_SCALE).is_ok());
+    assert!(GifRecorder::new(&gif, PALETTE, MAX_SCALE + 1).is_err());
+    assert!(GifRecorder::new(&gif, PALETTE, 0).is_err());
+    let y4m = capture_file("large.y4m");
+    assert!(Y4mWriter::new(&y4m, PALETTE, MAX_SCALE).is_ok());
+    assert!(Y4mWriter::new(&y4m, PALETTE, MAX_SCALE + 1).is_err());
+    assert!(Y4mWriter::new(&y4m, PALETTE, 0).is_err());
+    let png = capture_file("large.png");
+    assert!(capture::write_png(&png, &blank, PALETTE, MAX_SCALE).is_ok());
+    assert!(capture::write_png(&png, &blank, PALETTE, MAX_SCALE + 1).is_err());
+}
@@ -0,0 +1,135 @@
+// Memory searches and cheats, on a machine whose memory is set by hand.
+
+use chip_8_emulator::cheats::{Cheat, Cheats, Comparison, MemorySearch};
+use chip_8_emulator::chip8::Chip8;
+use std::env;
+use std::fs;
+
+fn cheat(address: u16, value: u8, freeze: bool) -> Cheat {
+    Cheat {
+        address,
+        value,
+        freeze,
+        description: String::new(),
+    }
+}
+
+// Starts a search with 0x300..0x305 holding `before`, then filters once with
+// them holding `after`.
+fn search(before: [u8; 5], after: [u8; 5], comparison: Comparison) -> Vec<usize> {
+    let mut cpu = Chip8::with_seed(0);
+    cpu.memory[0x300..0x305].copy_from_slice(&before);
+    let mut search = MemorySearch::new(&cpu);
+    cpu.memory[0x300..0x305].copy_from_slice(&after);
+    let count = search.filter(&cpu, comparison);
+    assert_eq!(count, search.candidates().len());
+    search
+        .candidates()
+        .iter()
+        .copied()
+        .filter(|address| (0x300..0x305).contains(address))
+        .collect()
+}
+
+#[test]
+fn each_comparison_keeps_the_matching_addresses() {
+    let before = [5, 5, 5, 5, 7];
+    let after = [5, 6, 4, 7, 7];
+    assert_eq!(
+        search(before, after, Comparison::Changed),
+        [0x301, 0x302, 0x303]
+    );
+    assert_eq!(search(before, after, Comparison::Increased), [0x301, 0x303]);
+    assert_eq!(search(before, after, Comparison::Decreased), [0x302]);
+    assert_eq!(search(before, after, Comparison::Equals(7)), [0x303, 0x304]);
+    // Everything outside the range stayed put too.
+    let unchanged = search(before, after, Comparison::Unchanged);
+    assert_eq!(unchanged, [0x300, 0x304]);
+}
+
+#[test]
+fn filters_compare_against_the_previous_filter() {
+    let mut cpu = Chip8::with_seed(0);
+    let mut search = MemorySearch::new(&cpu);
+    cpu.memory[0x300] = 1;
+    cpu.memory[0x400] = 1;
+    assert_eq!(search.filter(&cpu, Comparison::Increased), 2);
+    // 0x300 goes up again, 0x400 stays at 1.
+    cpu.memory[0x300] = 2;
+    assert_eq!(search.filter(&cpu, Comparison::Increased), 1);
+    assert_eq!(search.candidates(), [0x300]);
+    assert_eq!(search.filter(&cpu, Comparison::Changed), 0);
+}
+
+#[test]
+fn equals_reads_decimal_and_hex_bytes() {
+    let value = |text| match Comparison::parse("equals", Some(text)) {
+        Some(Comparison::Equals(value)) => Some(value),
+        _ => None,
+    };
+    assert_eq!(value("42"), Some(42));
+    assert_eq!(value("0x2A"), Some(0x2A));
+    assert_eq!(value("0X2a"), Some(0x2A));
+    assert_eq!(value("255"), Some(255));
+    assert_eq!(value("0xFF"), Some(255));
+    for text in ["256", "0x100", "-1", "0x", "twelve"].iter() {
+        assert_eq!(value(text), None, "{}", text);
+    }
+    assert!(Comparison::parse("equals", None).is_none());
+    assert!(Comparison::parse("bigger", None).is_none());
+}
+
+#[test]
+fn adding_a_cheat_replaces_the_one_on_its_address() {
+    let mut cpu = Chip8::with_seed(0);
+    let mut cheats = Cheats::empty();
+    cheats.add(&mut cpu, cheat(0x300, 1, true));
+    cheats.add(&mut cpu, cheat(0x301, 2, false));
+    cheats.add(&mut cpu, cheat(0x300, 3, false));
+    assert_eq!(cheats.cheats.len(), 2);
+    assert_eq!(cpu.memory[0x300], 3);
+    assert_eq!(cpu.memory[0x301], 2);
+    let replaced = cheats.cheats.iter().find(|c| c.address == 0x300).unwrap();
+    assert!(replaced.value == 3 && !replaced.freeze);
+    assert!(cheats.remove(0x301));
+    assert!(!cheats.remove(0x301));
+}
+
+#[test]
+fn only_frozen_cheats_are_written_every_frame() {
+    let mut cpu = Chip8::with_seed(0);
+    let mut cheats = Cheats::empty();
+    cheats.add(&mut cpu, cheat(0x300, 9, true));
+    cheats.add(&mut cpu, cheat(0x301, 9, false));
+    cpu.memory[0x300] = 0;
+    cpu.memory[0x301] = 0;
+    cheats.apply_frozen(&mut cpu);
+    assert_eq!(cpu.memory[0x300..0x302], [9, 0]);
+    // Loading the ROM again writes both.
+    cpu.memory[0x300] = 0;
+    cheats.apply_all(&mut cpu);
+    assert_eq!(cpu.memory[0x300..0x302], [9, 9]);
+}
+
+#[test]
+fn cheats_are_saved_and_loaded_by_rom_hash() {
+    let home = env::temp_dir().join(format!("chip-8-cheats-{}", std::process::id()));
+    env::set_var("XDG_CONFIG_HOME", &home);
+    let mut cpu = Chip8::with_seed(0);
+    let mut cheats = Cheats::load("ABCDEF").unwrap();
+    assert!(cheats.cheats.is_empty());
+    cheats.add(&mut cpu, cheat(0x300, 99, true));
+    let mut described = cheat(0xFFF, 1, false);
+    described.description = "lives".to_string();
+    cheats.add(&mut cpu, described);
+    cheats.save().unwrap();
+
+    let loaded = Cheats::load("abcdef").unwrap();
+    let fields: Vec<_> = loaded
+        .cheats
+        .iter()
+        .map(|c| (c.address, c.value, c.freeze, c.description.as_str()))
+        .collect();
+    assert_eq!(fields, [(0x300, 99, true, ""), (0xFFF, 1, false, "lives")]);
+    fs::remove_dir_all(home).unwrap();
+}
@@ -0,0 +1,208 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+// Runs the test ROMs in tests/roms headlessly under every quirks profile and
+// compares the final screen with a golden framebuffer in tests/golden, and
+// checks the sound timer of the beep ROM. The goldens are drawn by
+// tests/roms/build.py from what the ROMs are meant to show, not recorded from
+// this emulator. A missing ROM or golden file fails the test.
+
+use chip_8_emulator::chip8::{Chip8, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip_8_emulator::fontset::Font;
+use chip_8_emulator::quirks::Platform;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+const SEED: u64 = 0;
+
+struct TestRom {
+    name: &'static str,
+    frames: usize,
+    // Held for the whole run, bit N for key N.
+    keys: u16,
+}
+
+const TEST_ROMS: [TestRom; 6] = [
+    TestRom {
+        name: "ibm",
+        frames: 30,
+        keys: 0,
+    },
+    TestRom {
+        name: "font",
+        frames: 60,
+        keys: 0,
+    },
+    TestRom {
+        name: "opcodes",
+        frames: 120,
+        keys: 0,
+    },
+    TestRom {
+        name: "flags",
+        frames: 120,
+        keys: 0,
+    },
+    TestRom {
+        name: "quirks",
+        frames: 120,
+        keys: 0,
+    },
+    TestRom {
+        name: "keypad",
+        frames: 60,
+        keys: 1 << 0x1 | 1 << 0x5 | 1 << 0xA,
+    },
+];
+
+enum Outcome {
+    Pass,
+    Fail,
+    MissingRom,
+    MissingGolden,
+}
+
+impl Outcome {
+    fn label(&self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Fail => "FAIL",
+            Outcome::MissingRom => "no rom",
+            Outcome::MissingGolden => "no golden",
+        }
+    }
+}
+
+fn rom_file(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/roms")
+        .join(format!("{}.ch8", name))
+}
+
+fn golden_file(rom: &TestRom, platform: Platform) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.{}.txt", rom.name, platform.id()))
+}
+
+fn boot(program: &[u8], platform: Platform) -> Chip8 {
+    let mut cpu = Chip8::with_seed(SEED);
+    cpu.set_memory_layout(platform.memory_size(), cpu.program_start, cpu.font_address);
+    cpu.quirks = platform.quirks();
+    cpu.tick_rate = platform.tick_rate();
+    cpu.load_font_set(&Font::for_platform(Some(platform)));
+    let start = cpu.program_start as usize;
+    cpu.memory[start..start + program.len()].copy_from_slice(program);
+    cpu
+}
+
+fn run_headless(program: &[u8], rom: &TestRom, platform: Platform) -> Chip8 {
+    let mut cpu = boot(program, platform);
+    cpu.set_keys_mask(rom.keys);
+    for _ in 0..rom.frames {
+        cpu.run_frame();
+    }
+    cpu
+}
+
+fn framebuffer(cpu: &Chip8) -> String {
+    let mut text = String::with_capacity((SCREEN_WIDTH + 1) * SCREEN_HEIGHT);
+    for row in cpu.video.iter() {
+        text.extend(row.iter().map(|&pixel| if pixel == 1 { '#' } else { '.' }));
+        text.push('\n');
+    }
+    text
+}
+
+fn check(rom: &TestRom, platform: Platform) -> Outcome {
+    let program = match fs::read(rom_file(rom.name)) {
+        Ok(program) => program,
+        Err(_) => return Outcome::MissingRom,
+    };
+    let actual = framebuffer(&run_headless(&program, rom, platform));
+    match fs::read_to_string(golden_file(rom, platform)) {
+        Ok(expected) if expected == actual => Outcome::Pass,
+        Ok(_) => Outcome::Fail,
+        Err(_) => Outcome::MissingGolden,
+    }
+}
+
+// beep.ch8 sets the sound timer to 30 and then tops it up to 4 while key B
+// is held. The timer counts down once a frame, so the sound lasts for the
+// first 30 frames, and for the frames B is held plus the 3 after them.
+const BEEP_FRAMES: usize = 120;
+const BEEP_KEY_HELD: Range<usize> = 60..90;
+const BEEPING: [Range<usize>; 2] = [0..30, 60..93];
+
+fn check_beep(platform: Platform) -> Outcome {
+    let program = match fs::read(rom_file("beep")) {
+        Ok(program) => program,
+        Err(_) => return Outcome::MissingRom,
+    };
+    let mut cpu = boot(&program, platform);
+    for frame in 0..BEEP_FRAMES {
+        let held = BEEP_KEY_HELD.contains(&frame);
+        cpu.set_keys_mask(if held { 1 << 0xB } else { 0 });
+        cpu.run_frame();
+        let expected = BEEPING.iter().any(|frames| frames.contains(&frame));
+        if (cpu.sound_timer > 0) != expected {
+            return Outcome::Fail;
+        }
+    }
+    Outcome::Pass
+}
+
+fn record(
+    report: &mut String,
+    failures: &mut Vec<String>,
+    name: &str,
+    platform: Platform,
+    outcome: Outcome,
+) {
+    if let Outcome::Fail | Outcome::MissingRom | Outcome::MissingGolden = outcome {
+        failures.push(format!(
+            "{} on {} ({})",
+            name,
+            platform.id(),
+            outcome.label()
+        ));
+    }
+    report.push_str(&format!("{:>15}", outcome.label()));
+}
+
+#[test]
+fn test_roms_match_golden_framebuffers() {
+    let mut report = format!("{:<12}", "rom");
+    for platform in Platform::ALL.iter() {
+        report.push_str(&format!("{:>15}", platform.id()));
+    }
+    report.push('\n');
+    let mut failures = Vec::new();
+    for rom in TEST_ROMS.iter() {
+        report.push_str(&format!("{:<12}", rom.name));
+        for &platform in Platform::ALL.iter() {
+            let outcome = check(rom, platform);
+            record(&mut report, &mut failures, rom.name, platform, outcome);
+        }
+        report.push('\n');
+    }
+    report.push_str(&format!("{:<12}", "beep"));
+    for &platform in Platform::ALL.iter() {
+        record(
+            &mut report,
+            &mut failures,
+            "beep",
+            platform,
+            check_beep(platform),
+        );
+    }
+    report.push('\n');
+    println!("{}", report);
+    let report_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("compatibility-report.txt");
+    fs::write(report_file, &report).unwrap();
+    assert!(
+        failures.is_empty(),
+        "screens or sound do not match what the ROMs should produce: {}",
+        failures.join(", ")
+    );
+}
@@ -0,0 +1,59 @@
+// Looking ROMs up in the bundled database and in an override file in a
+// configuration directory made for this test run.
+
+use chip_8_emulator::database::lookup;
+use chip_8_emulator::quirks::Platform;
+use std::env;
+use std::fs;
+use std::sync::Once;
+
+const BRIX: &str = "f13766c14aeb02ad8d4d103cb5eadd282d20cddc";
+const BLINKY: &str = "d40abc54374e4343639f993e897e00904ddf85d9";
+const HOMEBREW: &str = "0123456789abcdef0123456789abcdef01234567";
+
+static CONFIGURE: Once = Once::new();
+
+// Every test in this file looks things up with the same override file, which
+// is written before the first lookup.
+fn configure() {
+    CONFIGURE.call_once(|| {
+        let home = env::temp_dir().join(format!("chip-8-database-{}", std::process::id()));
+        let dir = home.join("chip-8-emulator");
+        fs::create_dir_all(&dir).unwrap();
+        let programs = format!(
+            r#"[
+                {{ "title": "Brix, faster", "roms": {{ "{}": {{ "platforms": ["originalChip8"], "tickrate": 99 }} }} }},
+                {{ "title": "Homebrew", "authors": ["Me"], "roms": {{ "{}": {{ "platforms": ["superchip"] }} }} }}
+            ]"#,
+            BRIX.to_uppercase(),
+            HOMEBREW
+        );
+        fs::write(dir.join("programs.json"), programs).unwrap();
+        env::set_var("XDG_CONFIG_HOME", &home);
+    });
+}
+
+#[test]
+fn roms_are_found_by_hash() {
+    configure();
+    let blinky = lookup(BLINKY).unwrap().unwrap();
+    assert_eq!(blinky.title.as_deref(), Some("Blinky"));
+    assert_eq!(blinky.platform, Some(Platform::Chip48));
+    assert_eq!(blinky.tick_rate, Platform::Chip48.tick_rate());
+    // Hashes are compared without case.
+    let again = lookup(&BLINKY.to_uppercase()).unwrap().unwrap();
+    assert_eq!(again.title, blinky.title);
+    assert!(lookup(&"0".repeat(40)).unwrap().is_none());
+}
+
+#[test]
+fn the_override_file_comes_first() {
+    configure();
+    let brix = lookup(BRIX).unwrap().unwrap();
+    assert_eq!(brix.title.as_deref(), Some("Brix, faster"));
+    assert_eq!(brix.tick_rate, 99);
+    let homebrew = lookup(HOMEBREW).unwrap().unwrap();
+    assert_eq!(homebrew.title.as_deref(), Some("Homebrew"));
+    assert_eq!(homebrew.authors, ["Me"]);
+    assert_eq!(homebrew.platform, Some(Platform::SuperChip));
+}
@@ -0,0 +1,165 @@
+// The reinforcement learning environment, on ROMs built in memory.
+
+use chip_8_emulator::chip8::Chip8;
+use chip_8_emulator::environment::{
+    Environment, EnvironmentOptions, EnvironmentSpec, StepResult, Value,
+};
+use chip_8_emulator::rom::Rom;
+
+// Once a frame, adds one to V5 when key 1 is held. VE drops from 1 to 0 when
+// V5 reaches 10.
+const SCORE: [u16; 13] = [
+    0x6E01, 0x6301, 0x6001, 0xF015, 0xF007, 0x3000, 0x1208, 0xE3A1, 0x7501, 0x350A, 0x1204, 0x6E00,
+    0x1218,
+];
+// Draws a random glyph at a random place, forever.
+const NOISE: [u16; 6] = [0xC0FF, 0xC13F, 0xC21F, 0xF029, 0xD125, 0x1200];
+// Action 0 holds no key, action N holds key N - 1.
+const KEY_1: usize = 2;
+
+fn rom(program: &[u16]) -> Rom {
+    let bytes = program
+        .iter()
+        .flat_map(|opcode| opcode.to_be_bytes())
+        .collect();
+    Rom::from_bytes("test.ch8", bytes).unwrap()
+}
+
+fn options(frame_skip: u32, sticky_action_probability: f64, seed: u64) -> EnvironmentOptions {
+    EnvironmentOptions {
+        frame_skip,
+        sticky_action_probability,
+        seed: Some(seed),
+    }
+}
+
+fn score_environment(options: EnvironmentOptions) -> Environment {
+    let spec = EnvironmentSpec::from_json(
+        r#"{ "reward": [{ "register": 5, "scale": 2 }], "done": [{ "register": 14, "equals": 0 }] }"#,
+    )
+    .unwrap();
+    Environment::new(rom(&SCORE), Some(spec), options).unwrap()
+}
+
+fn memory_value(address: usize, bytes: usize, bcd: bool) -> Value {
+    Value {
+        register: None,
+        memory: Some(address),
+        bytes,
+        bcd,
+    }
+}
+
+#[test]
+fn rewards_follow_the_score_until_the_episode_ends() {
+    let mut environment = score_environment(options(1, 0.0, 1));
+    let mut rewards = Vec::new();
+    let last = loop {
+        let result = environment.step(KEY_1).unwrap();
+        rewards.push(result.reward);
+        if result.done {
+            break result;
+        }
+        assert!(rewards.len() < 100, "the episode did not end");
+    };
+    assert!(!last.truncated);
+    // The first frame only starts the wait for the next one.
+    assert_eq!(rewards[0], 0.0);
+    assert!(rewards[1..].iter().all(|&reward| reward == 2.0));
+    assert_eq!(rewards.iter().sum::<f64>(), 20.0);
+    assert_eq!(environment.frame, 11);
+
+    // Without the key the score stays put.
+    environment.reset(None).unwrap();
+    for _ in 0..20 {
+        let result = environment.step(0).unwrap();
+        assert_eq!(result.reward, 0.0);
+        assert!(!result.done);
+    }
+}
+
+#[test]
+fn episodes_are_truncated_at_max_frames() {
+    let spec = EnvironmentSpec::from_json(r#"{ "maxFrames": 5 }"#).unwrap();
+    let mut environment = Environment::new(rom(&SCORE), Some(spec), options(2, 0.0, 1)).unwrap();
+    assert!(!environment.step(0).unwrap().done);
+    assert!(!environment.step(0).unwrap().done);
+    let result = environment.step(0).unwrap();
+    assert!(result.done && result.truncated);
+    assert_eq!(environment.frame, 5);
+}
+
+#[test]
+fn sticky_actions_sometimes_keep_the_previous_keys() {
+    // Alternating between no key and key 1, one frame a step, scores on
+    // every other frame unless an action sticks.
+    let mut scores = Vec::new();
+    for &sticky in [0.0, 0.5].iter() {
+        let mut environment = score_environment(options(1, sticky, 3));
+        let mut score = 0.0;
+        for step in 0..18 {
+            let action = if step % 2 == 0 { 0 } else { KEY_1 };
+            score += environment.step(action).unwrap().reward;
+        }
+        scores.push(score);
+    }
+    assert_eq!(scores[0], 2.0 * 8.0 + 2.0);
+    assert_ne!(scores[1], scores[0]);
+}
+
+fn play(environment: &mut Environment, steps: usize) -> Vec<(Vec<u8>, f64, bool)> {
+    (0..steps)
+        .map(|step| {
+            let StepResult { reward, done, .. } = environment.step(step % 17).unwrap();
+            (screen(&environment.cpu), reward, done)
+        })
+        .collect()
+}
+
+fn screen(cpu: &Chip8) -> Vec<u8> {
+    cpu.video.iter().flatten().copied().collect()
+}
+
+#[test]
+fn the_same_seed_and_actions_give_the_same_episodes() {
+    let spec = EnvironmentSpec::from_json(r#"{ "reward": [{ "register": 0 }] }"#).unwrap();
+    let create =
+        |seed| Environment::new(rom(&NOISE), Some(spec.clone()), options(3, 0.25, seed)).unwrap();
+    let mut first = create(7);
+    let mut second = create(7);
+    let episode = play(&mut first, 50);
+    assert_eq!(play(&mut second, 50), episode);
+    assert_ne!(play(&mut create(8), 50), episode);
+    // The next episodes are seeded from the first seed too.
+    first.reset(None).unwrap();
+    second.reset(None).unwrap();
+    assert_eq!(first.episode_seed, second.episode_seed);
+    assert_eq!(play(&mut first, 50), play(&mut second, 50));
+    // Resetting with the first seed plays the first episode again.
+    first.reset(Some(7)).unwrap();
+    assert_eq!(play(&mut first, 50), episode);
+}
+
+#[test]
+fn values_read_registers_and_memory() {
+    let mut cpu = Chip8::with_seed(0);
+    cpu.registers[3] = 42;
+    let register = Value {
+        register: Some(3),
+        memory: None,
+        bytes: 1,
+        bcd: false,
+    };
+    assert_eq!(register.read(&cpu), 42);
+    cpu.memory[0x300..0x304].copy_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+    assert_eq!(memory_value(0x300, 1, false).read(&cpu), 0x12);
+    assert_eq!(memory_value(0x300, 4, false).read(&cpu), 0x1234_5678);
+    // FX33 digits, most significant first.
+    cpu.memory[0x310..0x313].copy_from_slice(&[1, 2, 3]);
+    assert_eq!(memory_value(0x310, 3, true).read(&cpu), 123);
+    // Values that run off the end of memory wrap to its start.
+    let end = cpu.memory.len() - 1;
+    cpu.memory[end] = 0xAB;
+    cpu.memory[0] = 0xCD;
+    assert_eq!(memory_value(end, 2, false).read(&cpu), 0xABCD);
+}
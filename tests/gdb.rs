@@ -0,0 +1,181 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+// Drives the emulator binary through the gdb remote serial protocol, the way
+// a debugger would, on a port the system picked.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// 0x200: V0 = 5, 0x202: V0 += 1, 0x204: jump to 0x202.
+const PROGRAM: [u8; 6] = [0x60, 0x05, 0x70, 0x01, 0x12, 0x02];
+
+// Kills the emulator when a test fails before it was told to stop.
+struct Emulator(Child);
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+struct Debugger {
+    stream: TcpStream,
+}
+
+impl Debugger {
+    fn command(&mut self, data: &str) -> String {
+        self.send(data);
+        self.receive()
+    }
+
+    fn send(&mut self, data: &str) {
+        let packet = format!("${}#{:02x}", data, checksum(data.as_bytes()));
+        self.stream.write_all(packet.as_bytes()).unwrap();
+        assert_eq!(self.read_byte(), b'+', "{} was not acknowledged", data);
+    }
+
+    fn receive(&mut self) -> String {
+        assert_eq!(self.read_byte(), b'$');
+        let mut data = Vec::new();
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                byte => data.push(byte),
+            }
+        }
+        let sent = [self.read_byte(), self.read_byte()];
+        let sent = u8::from_str_radix(std::str::from_utf8(&sent).unwrap(), 16).unwrap();
+        assert_eq!(sent, checksum(&data), "bad checksum");
+        self.stream.write_all(b"+").unwrap();
+        String::from_utf8(data).unwrap()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum: u8, &byte| sum.wrapping_add(byte))
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind(("127.0.0.1", 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+fn start(name: &str) -> (Emulator, Debugger) {
+    let rom = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.ch8", name));
+    fs::write(&rom, PROGRAM).unwrap();
+    let port = free_port();
+    let child = Command::new(env!("CARGO_BIN_EXE_chip-8-emulator"))
+        .args(["--headless", "--frames", "1000000", "--gdb"])
+        .arg(port.to_string())
+        .arg(&rom)
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let emulator = Emulator(child);
+    let started = Instant::now();
+    let stream = loop {
+        match TcpStream::connect(("127.0.0.1", port)) {
+            Ok(stream) => break stream,
+            Err(_) if started.elapsed() < TIMEOUT => thread::sleep(Duration::from_millis(20)),
+            Err(error) => panic!("the emulator did not listen on {}: {}", port, error),
+        }
+    };
+    stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+    (emulator, Debugger { stream })
+}
+
+fn wait_for_exit(emulator: &mut Emulator) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < TIMEOUT {
+        if let Some(status) = emulator.0.try_wait().unwrap() {
+            return status.success();
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+#[test]
+fn reports_registers_and_steps() {
+    let (mut emulator, mut gdb) = start("gdb-step");
+    assert_eq!(gdb.command("?"), "S05");
+    // V0-VF, then I and PC in little endian, then SP and the two timers.
+    let registers = format!("{}{}{}{}", "00".repeat(16), "0000", "0002", "000000");
+    assert_eq!(gdb.command("g"), registers);
+    assert_eq!(gdb.command("s"), "S05");
+    assert_eq!(gdb.command("p0"), "05");
+    assert_eq!(gdb.command("p11"), "0202");
+    gdb.send("k");
+    assert!(wait_for_exit(&mut emulator));
+}
+
+#[test]
+fn reads_and_writes_memory() {
+    let (mut emulator, mut gdb) = start("gdb-memory");
+    assert_eq!(gdb.command("m200,6"), "600570011202");
+    assert_eq!(gdb.command("M300,2:abcd"), "OK");
+    assert_eq!(gdb.command("m2ff,4"), "00abcd00");
+    assert_eq!(gdb.command("mfff,2"), "E01");
+    assert_eq!(gdb.command("m1,ffffffffffffffff"), "E01");
+    assert_eq!(gdb.command("M1,ffffffffffffffff:00"), "E01");
+    gdb.send("k");
+    assert!(wait_for_exit(&mut emulator));
+}
+
+#[test]
+fn reads_the_target_description_in_parts() {
+    let (mut emulator, mut gdb) = start("gdb-features");
+    let first = gdb.command("qXfer:features:read:target.xml:0,10");
+    assert!(first.starts_with("m<?xml"), "{}", first);
+    let all = gdb.command("qXfer:features:read:target.xml:0,ffffffffffffffff");
+    assert!(
+        all.starts_with("l<?xml") && all.ends_with("</target>"),
+        "{}",
+        all
+    );
+    let rest = gdb.command("qXfer:features:read:target.xml:10,ffffffffffffffff");
+    assert_eq!(&rest[1..], &all[0x11..]);
+    let end = format!("qXfer:features:read:target.xml:{:x},10", all.len() - 1);
+    assert_eq!(gdb.command(&end), "l");
+    assert_eq!(
+        gdb.command("qXfer:features:read:target.xml:ffffffffffffffff,1"),
+        "l"
+    );
+    gdb.send("k");
+    assert!(wait_for_exit(&mut emulator));
+}
+
+#[test]
+fn continues_to_a_breakpoint() {
+    let (mut emulator, mut gdb) = start("gdb-breakpoint");
+    assert_eq!(gdb.command("Z0,204,2"), "OK");
+    gdb.send("c");
+    assert_eq!(gdb.receive(), "S05");
+    assert_eq!(gdb.command("p11"), "0402");
+    assert_eq!(gdb.command("p0"), "06");
+    // Continuing steps over the breakpoint it stopped at and comes back to it
+    // after one more pass through the loop.
+    gdb.send("c");
+    assert_eq!(gdb.receive(), "S05");
+    assert_eq!(gdb.command("p0"), "07");
+    assert_eq!(gdb.command("z0,204,2"), "OK");
+    gdb.send("k");
+    assert!(wait_for_exit(&mut emulator));
+}
@@ -0,0 +1,154 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+// Single instructions whose results are easy to get wrong, run on a fresh
+// machine with the modern quirks.
+
+use chip_8_emulator::chip8::{Chip8, Fault, PROGRAM_START};
+use chip_8_emulator::fontset::{Font, LARGE_LETTERS};
+
+const VF: usize = 0xF;
+// Far enough from the program to hold sprite data.
+const DATA: u16 = 0x300;
+
+fn load(program: &[u16]) -> Chip8 {
+    let mut cpu = Chip8::with_seed(0);
+    let start = PROGRAM_START as usize;
+    for (offset, opcode) in program.iter().enumerate() {
+        cpu.memory[start + 2 * offset..start + 2 * offset + 2]
+            .copy_from_slice(&opcode.to_be_bytes());
+    }
+    cpu
+}
+
+fn run(cpu: &mut Chip8, instructions: usize) {
+    for _ in 0..instructions {
+        cpu.run();
+    }
+}
+
+#[test]
+fn draw_reads_vf_as_a_coordinate_before_clearing_it() {
+    // DF11 draws at (VF, V1).
+    let mut cpu = load(&[0xA000 | DATA, 0xDF11]);
+    cpu.memory[DATA as usize] = 0x80;
+    cpu.registers[VF] = 10;
+    cpu.registers[1] = 3;
+    run(&mut cpu, 2);
+    assert_eq!(cpu.video[3][10], 1);
+    assert_eq!(cpu.video[3][0], 0);
+    assert_eq!(cpu.registers[VF], 0);
+}
+
+#[test]
+fn draw_sets_vf_on_collision() {
+    let mut cpu = load(&[0xA000 | DATA, 0xD011, 0xD011]);
+    cpu.memory[DATA as usize] = 0xC0;
+    run(&mut cpu, 2);
+    assert_eq!(cpu.registers[VF], 0);
+    run(&mut cpu, 1);
+    assert_eq!(cpu.registers[VF], 1);
+    assert_eq!(cpu.video[0][..2], [0, 0]);
+}
+
+#[test]
+fn timers_are_set_from_the_register_value() {
+    let mut cpu = load(&[0x6328, 0xF315, 0xF318]);
+    run(&mut cpu, 3);
+    assert_eq!(cpu.delay_timer, 0x28);
+    assert_eq!(cpu.sound_timer, 0x28);
+}
+
+#[test]
+fn add_to_index_uses_the_register_value() {
+    let mut cpu = load(&[0xA100, 0x6330, 0xF31E, 0xAFFF, 0x6302, 0xF31E]);
+    run(&mut cpu, 3);
+    assert_eq!(cpu.index, 0x130);
+    assert_eq!(cpu.registers[VF], 0);
+    run(&mut cpu, 3);
+    assert_eq!(cpu.index, 0x1001);
+    assert_eq!(cpu.registers[VF], 1);
+}
+
+#[test]
+fn a_call_with_a_full_stack_halts_the_machine() {
+    // 0x200 calls itself forever.
+    let mut cpu = load(&[0x2200]);
+    run(&mut cpu, 16);
+    assert_eq!(cpu.sp, 16);
+    assert_eq!(cpu.fault, None);
+    run(&mut cpu, 1);
+    assert_eq!(cpu.fault, Some(Fault::StackOverflow));
+    assert_eq!(cpu.pc, PROGRAM_START);
+    assert_eq!(cpu.sp, 16);
+    let cycles = cpu.cycles;
+    cpu.run_frame();
+    assert_eq!(cpu.cycles, cycles);
+}
+
+#[test]
+fn a_return_with_an_empty_stack_halts_the_machine() {
+    // Calls 0x204, returns to 0x202 and returns again.
+    let mut cpu = load(&[0x2204, 0x00EE, 0x00EE]);
+    run(&mut cpu, 2);
+    assert_eq!(cpu.fault, None);
+    assert_eq!(cpu.pc, PROGRAM_START + 2);
+    run(&mut cpu, 1);
+    assert_eq!(cpu.fault, Some(Fault::StackUnderflow));
+    assert_eq!(cpu.pc, PROGRAM_START + 2);
+    assert_eq!(cpu.sp, 0);
+}
+
+#[test]
+fn arithmetic_flags_are_written_after_the_result() {
+    // 8F34, 8F35 and 8F37 with VF as the destination keep only the flag.
+    let mut cpu = load(&[0x6303, 0x6FFF, 0x8F34, 0x6F30, 0x8F35, 0x6F01, 0x8F37]);
+    run(&mut cpu, 3);
+    assert_eq!(cpu.registers[VF], 1);
+    run(&mut cpu, 2);
+    assert_eq!(cpu.registers[VF], 1);
+    run(&mut cpu, 2);
+    assert_eq!(cpu.registers[VF], 1);
+}
+
+#[test]
+fn subtracting_an_equal_value_does_not_borrow() {
+    let mut cpu = load(&[0x6205, 0x6305, 0x8235, 0x6205, 0x8237]);
+    run(&mut cpu, 3);
+    assert_eq!(cpu.registers[2], 0);
+    assert_eq!(cpu.registers[VF], 1);
+    run(&mut cpu, 2);
+    assert_eq!(cpu.registers[2], 0);
+    assert_eq!(cpu.registers[VF], 1);
+}
+
+#[test]
+fn key_skips_test_the_key_in_the_register() {
+    // V0 names key 5, which is held, while key 0 is not.
+    let mut cpu = load(&[0x6005, 0xE09E, 0x6101, 0xE0A1, 0x6201]);
+    cpu.keys[5] = true;
+    run(&mut cpu, 4);
+    assert_eq!(cpu.registers[1], 0);
+    assert_eq!(cpu.registers[2], 1);
+}
+
+#[test]
+fn small_glyphs_use_the_low_nibble_of_the_register() {
+    let mut cpu = load(&[0x602A, 0xF029]);
+    cpu.load_font_set(&Font::builtin("chip8").unwrap());
+    run(&mut cpu, 2);
+    assert_eq!(cpu.index, cpu.font_address + 5 * 0xA);
+    let glyph = cpu.index as usize;
+    assert_eq!(cpu.memory[glyph..glyph + 5], [0xF0, 0x90, 0xF0, 0x90, 0x90]);
+}
+
+#[test]
+fn large_glyphs_cover_every_hex_digit() {
+    let font = Font::builtin("chip8").unwrap();
+    let mut cpu = load(&[0x601C, 0xF030]);
+    cpu.load_font_set(&font);
+    run(&mut cpu, 2);
+    assert_eq!(cpu.index, cpu.large_font_address + 10 * 0xC);
+    let glyph = cpu.index as usize;
+    assert!(glyph + 10 <= cpu.font_address as usize + font.size());
+    assert_eq!(cpu.memory[glyph..glyph + 10], LARGE_LETTERS[20..30]);
+}
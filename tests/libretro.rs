@@ -0,0 +1,167 @@
+#![cfg(feature = "libretro")]
+
+// A minimal libretro frontend: it hands the core a ROM, answers the
+// environment calls the core makes and keeps whatever the callbacks
+// receive, so the tests can drive the core the way RetroArch would.
+// Run with `cargo test --features libretro --test libretro`.
+
+use chip_8_emulator::libretro::*;
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_char;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+use std::sync::Mutex;
+
+const ENVIRONMENT_SET_PIXEL_FORMAT: u32 = 10;
+const ENVIRONMENT_GET_VARIABLE: u32 = 15;
+const ENVIRONMENT_SET_VARIABLES: u32 = 16;
+const PIXEL_FORMAT_XRGB8888: u32 = 1;
+const MEMORY_SYSTEM_RAM: u32 = 2;
+
+struct Frontend {
+    platform: &'static [u8],
+    variables: Vec<String>,
+    frame: Vec<u32>,
+    pitch: usize,
+    audio_frames: usize,
+}
+
+static FRONTEND: Mutex<Frontend> = Mutex::new(Frontend {
+    platform: b"auto\0",
+    variables: Vec::new(),
+    frame: Vec::new(),
+    pitch: 0,
+    audio_frames: 0,
+});
+// The core is a global, so the tests take turns.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+extern "C" fn environment(cmd: u32, data: *mut c_void) -> bool {
+    let mut frontend = FRONTEND.lock().unwrap();
+    unsafe {
+        match cmd {
+            ENVIRONMENT_SET_PIXEL_FORMAT => *(data as *const u32) == PIXEL_FORMAT_XRGB8888,
+            ENVIRONMENT_SET_VARIABLES => {
+                let mut variable = data as *const Variable;
+                while !(*variable).key.is_null() {
+                    let key = CStr::from_ptr((*variable).key).to_string_lossy();
+                    frontend.variables.push(key.into_owned());
+                    variable = variable.add(1);
+                }
+                true
+            }
+            ENVIRONMENT_GET_VARIABLE => {
+                let variable = &mut *(data as *mut Variable);
+                if CStr::from_ptr(variable.key).to_bytes() == b"chip8_platform" {
+                    variable.value = frontend.platform.as_ptr() as *const c_char;
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+extern "C" fn video_refresh(data: *const c_void, width: u32, height: u32, pitch: usize) {
+    let mut frontend = FRONTEND.lock().unwrap();
+    let pixels = unsafe { slice::from_raw_parts(data as *const u32, pitch / 4 * height as usize) };
+    frontend.frame = pixels.to_vec();
+    frontend.pitch = pitch;
+    assert_eq!((width, height), (64, 32));
+}
+
+extern "C" fn audio_sample_batch(_data: *const i16, frames: usize) -> usize {
+    FRONTEND.lock().unwrap().audio_frames += frames;
+    frames
+}
+
+extern "C" fn input_poll() {}
+
+extern "C" fn input_state(_port: u32, _device: u32, _index: u32, _id: u32) -> i16 {
+    0
+}
+
+fn load(rom: &str, platform: &'static [u8]) {
+    {
+        let mut frontend = FRONTEND.lock().unwrap();
+        frontend.platform = platform;
+        frontend.variables.clear();
+        frontend.audio_frames = 0;
+    }
+    let bytes = std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join(rom)).unwrap();
+    retro_set_environment(environment);
+    retro_set_video_refresh(video_refresh);
+    retro_set_audio_sample_batch(audio_sample_batch);
+    retro_set_input_poll(input_poll);
+    retro_set_input_state(input_state);
+    retro_init();
+    let game = GameInfo {
+        path: ptr::null(),
+        data: bytes.as_ptr() as *const c_void,
+        size: bytes.len(),
+        meta: ptr::null(),
+    };
+    assert!(unsafe { retro_load_game(&game) });
+}
+
+fn run_frames(frames: usize) -> Vec<u32> {
+    for _ in 0..frames {
+        retro_run();
+    }
+    FRONTEND.lock().unwrap().frame.clone()
+}
+
+fn serialize() -> Vec<u8> {
+    let mut state = vec![0; retro_serialize_size()];
+    assert!(unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, state.len()) });
+    state
+}
+
+#[test]
+fn runs_frames_with_video_and_audio() {
+    let _serial = SERIAL.lock().unwrap();
+    load("chip8-roms/MAZE", b"auto\0");
+    let frame = run_frames(60);
+    let frontend = FRONTEND.lock().unwrap();
+    assert_eq!(
+        frontend.variables,
+        vec!["chip8_platform".to_string(), "chip8_ips".to_string()]
+    );
+    assert_eq!(frontend.pitch, 64 * 4);
+    assert_eq!(frontend.audio_frames, 60 * 735);
+    assert!(frame.iter().any(|&pixel| pixel != frame[0]));
+    drop(frontend);
+    retro_unload_game();
+    retro_deinit();
+}
+
+#[test]
+fn unserialize_replays_the_same_frames() {
+    let _serial = SERIAL.lock().unwrap();
+    load("chip8-roms/MAZE", b"auto\0");
+    run_frames(5);
+    let state = serialize();
+    let expected = run_frames(30);
+    assert!(unsafe { retro_unserialize(state.as_ptr() as *const c_void, state.len()) });
+    assert_eq!(run_frames(30), expected);
+    assert!(!unsafe { retro_unserialize(state.as_ptr() as *const c_void, 8) });
+    retro_unload_game();
+    retro_deinit();
+}
+
+#[test]
+fn quirks_profile_option_sets_memory_size() {
+    let _serial = SERIAL.lock().unwrap();
+    load("chip8-roms/MAZE", b"auto\0");
+    assert_eq!(retro_get_memory_size(MEMORY_SYSTEM_RAM), 0x1000);
+    load("chip8-roms/MAZE", b"xochip\0");
+    assert_eq!(retro_get_memory_size(MEMORY_SYSTEM_RAM), 0x10000);
+    assert_eq!(serialize().len(), retro_serialize_size());
+    run_frames(10);
+    retro_unload_game();
+    assert_eq!(retro_serialize_size(), 0);
+    retro_deinit();
+}
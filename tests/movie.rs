@@ -0,0 +1,114 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+// Recording a session into a movie file and replaying it.
+
+use chip_8_emulator::chip8::Chip8;
+use chip_8_emulator::movie::Movie;
+use chip_8_emulator::rom::Rom;
+use chip_8_emulator::state::save_state;
+use std::env;
+use std::fs;
+
+// Draws a random glyph at a random place every loop, and draws one more
+// random number into V4 while key 1 is held, so the random numbers depend on
+// the keys.
+const PROGRAM: [u16; 9] = [
+    0xC0FF, 0xC13F, 0xC21F, 0x6301, 0xE3A1, 0xC4FF, 0xF029, 0xD125, 0x1200,
+];
+const FRAMES: usize = 120;
+
+fn rom(program: &[u16]) -> Rom {
+    let bytes = program
+        .iter()
+        .flat_map(|opcode| opcode.to_be_bytes())
+        .collect();
+    Rom::from_bytes("test.ch8", bytes).unwrap()
+}
+
+fn boot(rom: &Rom, cpu: &mut Chip8) {
+    rom.settings.apply(cpu);
+    rom.load_into_memory(&mut cpu.memory, cpu.program_start)
+        .unwrap();
+}
+
+fn movie_file(test: &str) -> String {
+    let name = format!("chip-8-movie-{}-{}.txt", std::process::id(), test);
+    env::temp_dir().join(name).to_str().unwrap().to_string()
+}
+
+// Plays FRAMES frames with keys that change every few frames, and returns the
+// movie and the state of the machine after each frame.
+fn record(rom: &Rom, seed: u64) -> (Movie, Vec<Vec<u8>>) {
+    let mut cpu = Chip8::with_seed(seed);
+    boot(rom, &mut cpu);
+    let mut movie = Movie::new(rom.hash(), cpu.seed);
+    let states = (0..FRAMES)
+        .map(|frame| {
+            cpu.set_keys_mask(((frame / 3) as u16).wrapping_mul(0x9E37));
+            movie.record_frame(&cpu);
+            cpu.run_frame();
+            save_state(&cpu)
+        })
+        .collect();
+    (movie, states)
+}
+
+#[test]
+fn replays_match_the_recording() {
+    let rom = rom(&PROGRAM);
+    let (movie, states) = record(&rom, 1234);
+    let file = movie_file("replay");
+    movie.save(&file).unwrap();
+    let movie = Movie::load_for(&file, &rom).unwrap();
+    fs::remove_file(&file).unwrap();
+    assert_eq!(movie.seed, 1234);
+    assert_eq!(movie.frames.len(), FRAMES);
+
+    let mut cpu = movie.machine();
+    boot(&rom, &mut cpu);
+    for (frame, state) in states.iter().enumerate() {
+        assert!(movie.play_frame(&mut cpu, frame));
+        cpu.run_frame();
+        assert!(&save_state(&cpu) == state, "frame {} differs", frame);
+    }
+    assert!(!movie.play_frame(&mut cpu, FRAMES));
+}
+
+#[test]
+fn other_seeds_play_differently() {
+    let rom = rom(&PROGRAM);
+    let (_, states) = record(&rom, 1234);
+    let (_, other) = record(&rom, 1235);
+    assert!(states.last() != other.last());
+}
+
+#[test]
+fn movies_only_replay_on_their_rom() {
+    let (movie, _) = record(&rom(&PROGRAM), 1);
+    let file = movie_file("other-rom");
+    movie.save(&file).unwrap();
+    let other = rom(&PROGRAM[1..]);
+    let error = Movie::load_for(&file, &other).err().unwrap().to_string();
+    fs::remove_file(&file).unwrap();
+    assert!(error.contains(&movie.rom_hash), "{}", error);
+}
+
+#[test]
+fn damaged_movies_are_rejected() {
+    let (movie, _) = record(&rom(&PROGRAM), 1);
+    let file = movie_file("damaged");
+    movie.save(&file).unwrap();
+    let contents = fs::read_to_string(&file).unwrap();
+    let damaged = [
+        contents.replacen("CHIP8MOVIE 1", "CHIP8MOVIE 2", 1),
+        contents.replacen("seed 1", "seed one", 1),
+        contents.replacen("frames 120", "frames 121", 1),
+        contents.replacen("rom_sha1", "rom_md5", 1),
+        format!("{}XYZ\n", contents),
+    ];
+    for contents in damaged.iter() {
+        fs::write(&file, contents).unwrap();
+        assert!(Movie::load(&file).is_err(), "{}", contents);
+    }
+    fs::remove_file(&file).unwrap();
+}
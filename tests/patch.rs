@@ -0,0 +1,168 @@
+// IPS and BPS patches made by this module and by hand, applied to small
+// ROMs.
+
+use chip_8_emulator::patch::{apply, apply_bps, apply_ips, create_bps, create_ips};
+
+const SOURCE_COPY: u64 = 2;
+const TARGET_COPY: u64 = 3;
+
+fn rom(size: usize) -> Vec<u8> {
+    (0..size).map(|offset| (offset * 7 % 251) as u8).collect()
+}
+
+// Both formats, through the format detection in `apply`.
+fn round_trip(original: &[u8], modified: &[u8]) {
+    let ips = create_ips(original, modified).unwrap();
+    assert_eq!(apply(original, &ips).unwrap(), modified);
+    let bps = create_bps(original, modified);
+    assert_eq!(apply(original, &bps).unwrap(), modified);
+}
+
+fn write_number(patch: &mut Vec<u8>, mut number: u64) {
+    loop {
+        let byte = (number & 0x7F) as u8;
+        number >>= 7;
+        if number == 0 {
+            patch.push(byte | 0x80);
+            return;
+        }
+        patch.push(byte);
+        number -= 1;
+    }
+}
+
+fn write_signed(patch: &mut Vec<u8>, number: i64) {
+    write_number(patch, number.unsigned_abs() << 1 | (number < 0) as u64);
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// A BPS patch from a list of actions that are already encoded. The target
+// size is given separately so that it can disagree with the target.
+fn bps(source: &[u8], target_size: u64, target: &[u8], actions: &[u8]) -> Vec<u8> {
+    let mut patch = b"BPS1".to_vec();
+    write_number(&mut patch, source.len() as u64);
+    write_number(&mut patch, target_size);
+    write_number(&mut patch, 0);
+    patch.extend_from_slice(actions);
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&crc32(target).to_le_bytes());
+    let checksum = crc32(&patch);
+    patch.extend_from_slice(&checksum.to_le_bytes());
+    patch
+}
+
+#[test]
+fn patches_round_trip_when_the_rom_grows() {
+    let original = rom(300);
+    let mut modified = original.clone();
+    modified[0] ^= 0xFF;
+    modified[100..110].copy_from_slice(&[0xAA; 10]);
+    modified.extend_from_slice(&[1, 2, 3, 4, 5]);
+    round_trip(&original, &modified);
+}
+
+#[test]
+fn patches_round_trip_when_the_rom_shrinks() {
+    let original = rom(300);
+    let mut modified = original[..200].to_vec();
+    modified[199] ^= 0xFF;
+    round_trip(&original, &modified);
+    round_trip(&original, &original[..1]);
+}
+
+#[test]
+fn ips_truncation_extension_shortens_the_rom() {
+    // One record writing 0xFF at 2, then EOF and a size of 3.
+    let patch = b"PATCH\x00\x00\x02\x00\x01\xFFEOF\x00\x00\x03";
+    assert_eq!(apply_ips(&[1, 2, 3, 4, 5], patch).unwrap(), [1, 2, 0xFF]);
+    let untruncated = &patch[..patch.len() - 3];
+    assert_eq!(
+        apply_ips(&[1, 2, 3, 4, 5], untruncated).unwrap(),
+        [1, 2, 0xFF, 4, 5]
+    );
+}
+
+#[test]
+fn ips_records_avoid_the_offset_that_reads_as_eof() {
+    const EOF_OFFSET: usize = 0x454F46;
+    let original = vec![0; EOF_OFFSET + 4];
+    let mut modified = original.clone();
+    modified[EOF_OFFSET] = 1;
+    let patch = create_ips(&original, &modified).unwrap();
+    // The record starts a byte early and carries the unchanged byte along.
+    assert_eq!(patch[5..8], [0x45, 0x4F, 0x45]);
+    assert_eq!(patch[8..12], [0x00, 0x02, 0x00, 0x01]);
+    assert_eq!(apply_ips(&original, &patch).unwrap(), modified);
+}
+
+#[test]
+fn bps_copies_from_the_source_and_the_target() {
+    let source = b"ABCDEFGH";
+    let target = b"EFGABABABAB";
+    let mut actions = Vec::new();
+    // Copy "EFG" from source offset 4, then "AB" from offset 0.
+    write_number(&mut actions, 2 << 2 | SOURCE_COPY);
+    write_signed(&mut actions, 4);
+    write_number(&mut actions, 1 << 2 | SOURCE_COPY);
+    write_signed(&mut actions, -7);
+    // Copy the six bytes from target offset 3, which overlap the copy.
+    write_number(&mut actions, 5 << 2 | TARGET_COPY);
+    write_signed(&mut actions, 3);
+    let patch = bps(source, target.len() as u64, target, &actions);
+    assert_eq!(apply_bps(source, &patch).unwrap(), target);
+}
+
+#[test]
+fn bps_rejects_a_different_rom() {
+    let original = rom(64);
+    let mut modified = original.clone();
+    modified[10] = 0;
+    let patch = create_bps(&original, &modified);
+    let mut other = original.clone();
+    other[20] ^= 1;
+    assert!(apply_bps(&other, &patch).is_err());
+}
+
+#[test]
+fn bps_rejects_a_corrupt_patch() {
+    let original = rom(64);
+    let mut modified = original.clone();
+    modified[10] = 0;
+    let mut patch = create_bps(&original, &modified);
+    let last = patch.len() - 1;
+    patch[last] ^= 1;
+    assert!(apply_bps(&original, &patch).is_err());
+}
+
+#[test]
+fn bps_rejects_oversized_and_overflowing_numbers() {
+    let source = rom(8);
+    // A target far larger than any CHIP-8 memory.
+    let patch = bps(&source, 1 << 40, &[], &[]);
+    assert!(apply_bps(&source, &patch).is_err());
+    // A number with more continuation bytes than fit in 64 bits.
+    let patch = bps(&source, 1, &[0], &[0x7F; 12]);
+    assert!(apply_bps(&source, &patch).is_err());
+    // A source copy whose relative offset overflows the one before it.
+    let mut actions = Vec::new();
+    write_number(&mut actions, SOURCE_COPY);
+    write_signed(&mut actions, 4);
+    write_number(&mut actions, SOURCE_COPY);
+    write_signed(&mut actions, i64::MAX);
+    let patch = bps(&source, 2, &[source[4], 0], &actions);
+    assert!(apply_bps(&source, &patch).is_err());
+}
@@ -0,0 +1,111 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+// Profiles of a small loop that calls a subroutine three times.
+
+use chip_8_emulator::chip8::{Chip8, PROGRAM_START};
+use chip_8_emulator::profiler::Profiler;
+use std::env;
+use std::fs;
+
+// 0x200: V0 = 3, 0x202: call 0x20C, 0x204: V0 -= 1, 0x206: skip when V0 is
+// 0, 0x208: jump to 0x202, 0x20A: halt. 0x20C: V1 = 5, 0x20E: return. The
+// jump at 0x210 and the byte after it are never reached.
+const PROGRAM: [u8; 19] = [
+    0x60, 0x03, 0x22, 0x0C, 0x70, 0xFF, 0x30, 0x00, 0x12, 0x02, 0x12, 0x0A, 0x61, 0x05, 0x00, 0xEE,
+    0x12, 0x34, 0xAB,
+];
+
+// Runs the program until it halts and returns the report the profiler
+// wrote then, to a file named after the test.
+fn profile(test: &str) -> String {
+    let name = format!("chip-8-profile-{}-{}.txt", std::process::id(), test);
+    let file = env::temp_dir().join(name);
+    let file = file.to_str().unwrap();
+    let mut cpu = Chip8::with_seed(0);
+    let start = PROGRAM_START as usize;
+    cpu.memory[start..start + PROGRAM.len()].copy_from_slice(&PROGRAM);
+    let mut profiler = Profiler::new(file, &cpu, PROGRAM.len());
+    for _ in 0..100 {
+        profiler.record(&cpu);
+        if cpu.pc == 0x20A {
+            break;
+        }
+        cpu.run();
+    }
+    let report = fs::read_to_string(file).unwrap();
+    assert_eq!(report, profiler.report(&cpu.memory));
+    fs::remove_file(file).unwrap();
+    report
+}
+
+// The lines of one section of the report, without their indentation.
+fn section(report: &str, title: &str) -> Vec<String> {
+    report
+        .split("\n\n")
+        .find(|section| section.starts_with(title))
+        .unwrap_or_else(|| panic!("no {} in\n{}", title, report))
+        .lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect()
+}
+
+#[test]
+fn instructions_are_counted_by_address() {
+    let report = profile("counts");
+    assert!(report.starts_with("19 instructions executed\n"));
+    let counts: Vec<(String, String)> = section(&report, "hottest addresses")
+        .iter()
+        .map(|line| {
+            let fields: Vec<&str> = line.split(' ').collect();
+            (fields[0].to_string(), fields[1].to_string())
+        })
+        .collect();
+    let expected = [
+        ("0x202", "3"),
+        ("0x204", "3"),
+        ("0x206", "3"),
+        ("0x20C", "3"),
+        ("0x20E", "3"),
+        ("0x208", "2"),
+        ("0x200", "1"),
+        ("0x20A", "1"),
+    ];
+    let expected: Vec<(String, String)> = expected
+        .iter()
+        .map(|&(address, count)| (address.to_string(), count.to_string()))
+        .collect();
+    assert_eq!(counts, expected);
+}
+
+#[test]
+fn instructions_are_counted_by_class() {
+    let classes = section(&profile("classes"), "opcode classes");
+    assert_eq!(
+        classes,
+        [
+            "6XNN 4 21.1%",
+            "00EE 3 15.8%",
+            "1NNN 3 15.8%",
+            "2NNN 3 15.8%",
+            "3XNN 3 15.8%",
+            "7XNN 3 15.8%",
+        ]
+    );
+}
+
+#[test]
+fn subroutines_include_the_instructions_up_to_their_return() {
+    // Two instructions for each of three calls.
+    let subroutines = section(
+        &profile("subroutines"),
+        "subroutines by inclusive instructions",
+    );
+    assert_eq!(subroutines, ["0x20C 6 31.6% 3 calls"]);
+}
+
+#[test]
+fn unreached_rom_bytes_are_listed() {
+    let never_executed = section(&profile("never"), "never executed");
+    assert_eq!(never_executed, ["0x210-0x212 3 bytes", "3 of 19 ROM bytes"]);
+}
@@ -0,0 +1,105 @@
+#![cfg(feature = "python")]
+
+// Drives the Python module from an embedded interpreter, the way a script
+// would. Run with `cargo test --features python --test python`; the
+// framebuffer is only checked where NumPy is installed.
+
+use chip_8_emulator::python::chip8;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::fs;
+use std::sync::Once;
+
+const ROM: &str = "tests/roms/font.ch8";
+
+static INITIALIZE: Once = Once::new();
+
+fn with_python<F: for<'py> FnOnce(Python<'py>, Bound<'py, PyAny>)>(test: F) {
+    INITIALIZE.call_once(|| {
+        pyo3::append_to_inittab!(chip8);
+        Python::initialize();
+    });
+    Python::attach(|py| {
+        let module = py.import("chip8").unwrap();
+        let emulator = module.getattr("Chip8").unwrap().call1((0u64,)).unwrap();
+        let rom = PyBytes::new(py, &fs::read(ROM).unwrap());
+        emulator.call_method1("load_rom", (rom,)).unwrap();
+        test(py, emulator);
+    });
+}
+
+fn get<'py, T: FromPyObjectOwned<'py>>(object: &Bound<'py, PyAny>, name: &str) -> T {
+    let value = object.getattr(name).unwrap();
+    value.extract().map_err(Into::<PyErr>::into).unwrap()
+}
+
+fn read_memory(emulator: &Bound<'_, PyAny>, address: usize, length: usize) -> PyResult<Vec<u8>> {
+    emulator
+        .call_method1("read_memory", (address, length))?
+        .extract()
+}
+
+#[test]
+fn loads_and_runs_a_rom() {
+    with_python(|_, emulator| {
+        assert_eq!(get::<u16>(&emulator, "pc"), 0x200);
+        let rom = fs::read(ROM).unwrap();
+        assert_eq!(read_memory(&emulator, 0x200, rom.len()).unwrap(), rom);
+        emulator.call_method1("run_frame", (10,)).unwrap();
+        assert_ne!(get::<u16>(&emulator, "pc"), 0x200);
+        assert!(get::<u64>(&emulator, "cycles") > 0);
+    });
+}
+
+#[test]
+fn framebuffer_is_a_uint8_array_of_the_screen() {
+    with_python(|py, emulator| {
+        if py.import("numpy").is_err() {
+            eprintln!("NumPy is not installed, the framebuffer is not checked");
+            return;
+        }
+        emulator.call_method1("run_frame", (10,)).unwrap();
+        let framebuffer = emulator.call_method0("framebuffer").unwrap();
+        assert_eq!(get::<(usize, usize)>(&framebuffer, "shape"), (32, 64));
+        let dtype = framebuffer.getattr("dtype").unwrap().str().unwrap();
+        assert_eq!(dtype.to_str().unwrap(), "uint8");
+        let lit: u64 = framebuffer.call_method0("sum").unwrap().extract().unwrap();
+        assert!(lit > 0);
+    });
+}
+
+#[test]
+fn a_loaded_state_runs_the_same_frames() {
+    with_python(|_, emulator| {
+        emulator.call_method1("run_frame", (5,)).unwrap();
+        let state = emulator.call_method0("save_state").unwrap();
+        emulator.call_method1("run_frame", (5,)).unwrap();
+        let pc: u16 = get(&emulator, "pc");
+        let v: [u8; 16] = get(&emulator, "v");
+        let memory = read_memory(&emulator, 0, 4096).unwrap();
+        emulator.call_method1("load_state", (state,)).unwrap();
+        emulator.call_method1("run_frame", (5,)).unwrap();
+        assert_eq!(get::<u16>(&emulator, "pc"), pc);
+        assert_eq!(get::<[u8; 16]>(&emulator, "v"), v);
+        assert_eq!(read_memory(&emulator, 0, 4096).unwrap(), memory);
+        let truncated = PyBytes::new(emulator.py(), b"C8ST");
+        assert!(emulator.call_method1("load_state", (truncated,)).is_err());
+    });
+}
+
+#[test]
+fn memory_ranges_are_bounded() {
+    with_python(|py, emulator| {
+        assert_eq!(read_memory(&emulator, 4095, 2).unwrap().len(), 2);
+        for (address, length) in [(0, 4097), (1, usize::MAX), (usize::MAX, 1)] {
+            let error = read_memory(&emulator, address, length).unwrap_err();
+            assert!(error.is_instance_of::<PyValueError>(py));
+        }
+        let data = PyBytes::new(py, &[0; 4097]);
+        let error = emulator
+            .call_method1("write_memory", (0, data))
+            .unwrap_err();
+        assert!(error.is_instance_of::<PyValueError>(py));
+    });
+}
@@ -0,0 +1,155 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use chip_8_emulator::chip8::{Chip8, Fault, PROGRAM_START};
+use chip_8_emulator::fontset::Font;
+use chip_8_emulator::instructions::handler_key;
+use chip_8_emulator::quirks::Platform;
+use proptest::prelude::*;
+
+const INSTRUCTIONS_PER_KEY_STATE: usize = 64;
+const MAX_ROM_SIZE: usize = 0x1000 - PROGRAM_START as usize;
+
+fn load(rom: &[u8], seed: u64, platform: Platform) -> Chip8 {
+    let mut cpu = Chip8::with_seed(seed);
+    cpu.quirks = platform.quirks();
+    cpu.load_font_set(&Font::for_platform(Some(platform)));
+    let start = cpu.program_start as usize;
+    cpu.memory[start..start + rom.len()].copy_from_slice(rom);
+    cpu
+}
+
+fn assert_invariants(cpu: &Chip8, memory_size: usize) {
+    assert_eq!(cpu.memory.len(), memory_size);
+    assert!((cpu.pc as usize) < memory_size, "pc 0x{:X}", cpu.pc);
+    assert!((cpu.sp as usize) <= cpu.stack.len(), "sp {}", cpu.sp);
+    // A fault leaves pc on the instruction that caused it.
+    let pc = cpu.pc as usize;
+    let opcode = (cpu.memory[pc] as u16) << 8 | cpu.memory[(pc + 1) % memory_size] as u16;
+    match cpu.fault {
+        Some(Fault::StackOverflow) => {
+            assert_eq!(cpu.sp as usize, cpu.stack.len());
+            assert_eq!(opcode & 0xF000, 0x2000, "overflow at {:04X}", opcode);
+        }
+        Some(Fault::StackUnderflow) => {
+            assert_eq!(cpu.sp, 0);
+            assert_eq!(handler_key(opcode), 0x00EE, "underflow at {:04X}", opcode);
+        }
+        None => {}
+    }
+}
+
+// Runs one instruction, which must do nothing once the machine has faulted.
+fn run(cpu: &mut Chip8, memory_size: usize) {
+    let faulted = cpu.fault;
+    let (pc, sp, cycles) = (cpu.pc, cpu.sp, cpu.cycles);
+    cpu.run();
+    assert_invariants(cpu, memory_size);
+    if faulted.is_some() {
+        assert_eq!(cpu.fault, faulted);
+        assert_eq!((cpu.pc, cpu.sp, cpu.cycles), (pc, sp, cycles));
+    }
+}
+
+// XO-CHIP is left out because it needs the 64 KiB memory layout.
+fn platforms() -> impl Strategy<Value = Platform> {
+    prop::sample::select(
+        Platform::ALL
+            .iter()
+            .copied()
+            .filter(|&platform| platform != Platform::XoChip)
+            .collect::<Vec<_>>(),
+    )
+}
+
+proptest! {
+    #[test]
+    fn random_roms_keep_the_core_in_bounds(
+        rom in prop::collection::vec(any::<u8>(), 0..MAX_ROM_SIZE),
+        keys in prop::collection::vec(any::<u16>(), 1..16),
+        seed in any::<u64>(),
+        platform in platforms(),
+    ) {
+        let mut cpu = load(&rom, seed, platform);
+        let memory_size = cpu.memory.len();
+        for mask in keys {
+            cpu.set_keys_mask(mask);
+            for _ in 0..INSTRUCTIONS_PER_KEY_STATE {
+                run(&mut cpu, memory_size);
+            }
+        }
+    }
+
+    // Random instruction streams rarely hit the edge cases, so this one
+    // points I and the operands at the end of memory on purpose.
+    #[test]
+    fn memory_instructions_wrap_at_the_end_of_memory(
+        index in 0xF00u16..=0xFFFF,
+        x in 0u16..16,
+        low in prop::sample::select(vec![0x1E, 0x29, 0x30, 0x33, 0x55, 0x65]),
+        height in 1u16..16,
+        registers in any::<[u8; 16]>(),
+    ) {
+        let program = [
+            0xF000 | x << 8 | low,
+            0xD000 | x << 8 | (x ^ 1) << 4 | height,
+            0xB000 | 0x0FFF,
+        ];
+        let rom: Vec<u8> = program.iter().flat_map(|opcode| opcode.to_be_bytes()).collect();
+        let mut cpu = load(&rom, 0, Platform::ModernChip8);
+        let memory_size = cpu.memory.len();
+        cpu.registers = registers;
+        cpu.index = index;
+        for _ in 0..program.len() {
+            run(&mut cpu, memory_size);
+        }
+    }
+
+    #[test]
+    fn calls_and_returns_fault_at_the_ends_of_the_stack(
+        calls in prop::collection::vec(prop::bool::weighted(0.8), 1..64),
+    ) {
+        // Each slot either calls the next slot or returns.
+        let rom: Vec<u8> = calls
+            .iter()
+            .enumerate()
+            .flat_map(|(slot, &call)| {
+                let next = PROGRAM_START + 2 * (slot as u16 + 1);
+                let opcode = if call { 0x2000 | next } else { 0x00EE };
+                opcode.to_be_bytes()
+            })
+            .collect();
+        let mut cpu = load(&rom, 0, Platform::ModernChip8);
+        let memory_size = cpu.memory.len();
+        // The same program on a stack that can hold 16 return addresses.
+        let mut stack = Vec::new();
+        for _ in 0..calls.len() {
+            let pc = cpu.pc;
+            let call = match calls.get(((pc - PROGRAM_START) / 2) as usize) {
+                Some(&call) => call,
+                None => break,
+            };
+            run(&mut cpu, memory_size);
+            let fault = if call && stack.len() == 16 {
+                Some(Fault::StackOverflow)
+            } else if call {
+                stack.push(pc + 2);
+                None
+            } else {
+                match stack.pop() {
+                    Some(address) => {
+                        prop_assert_eq!(cpu.pc, address);
+                        None
+                    }
+                    None => Some(Fault::StackUnderflow),
+                }
+            };
+            prop_assert_eq!(cpu.fault, fault);
+            prop_assert_eq!(cpu.sp as usize, stack.len());
+            if fault.is_some() {
+                prop_assert_eq!(cpu.pc, pc);
+                run(&mut cpu, memory_size);
+                break;
+            }
+        }
+    }
+}
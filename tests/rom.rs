@@ -0,0 +1,91 @@
+// ROM loading checks, on ROMs built in memory.
+
+use chip_8_emulator::fontset::Font;
+use chip_8_emulator::rom::Rom;
+
+fn rom(size: usize) -> Rom {
+    Rom::from_bytes("test.ch8", vec![0x12; size]).unwrap()
+}
+
+#[test]
+fn fonts_may_not_overlap_the_program() {
+    let font = Font::builtin("chip8").unwrap();
+    let mut rom = rom(0x100);
+    rom.check_font(&font).unwrap();
+    // The font loads after the ROM, so it would overwrite its first bytes.
+    rom.settings.program_start = 0x600;
+    rom.settings.font_address = 0x600;
+    let error = rom.check_font(&font).unwrap_err().to_string();
+    assert!(error.contains("0x600-0x6EF"), "{}", error);
+    assert!(error.contains("0x600-0x6FF"), "{}", error);
+    // Ending right before the program, or starting right after it, is fine.
+    rom.settings.font_address = (0x600 - font.size()) as u16;
+    rom.check_font(&font).unwrap();
+    rom.settings.font_address = 0x700;
+    rom.check_font(&font).unwrap();
+    rom.settings.font_address = 0x6FF;
+    assert!(rom.check_font(&font).is_err());
+}
+
+#[test]
+fn fonts_must_fit_in_memory() {
+    let font = Font::builtin("chip8").unwrap();
+    let mut rom = rom(2);
+    rom.settings.font_address = (rom.settings.memory_size - font.size()) as u16;
+    rom.check_font(&font).unwrap();
+    rom.settings.font_address += 1;
+    assert!(rom.check_font(&font).is_err());
+}
+
+#[test]
+fn roms_must_fit_between_the_program_start_and_the_end_of_memory() {
+    let largest = 0x1000 - 0x200;
+    assert_eq!(rom(largest).size(), largest);
+    let error = Rom::from_bytes("big.ch8", vec![0x12; largest + 1])
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        error.contains("big.ch8 is 3585 bytes, but only 3584 bytes fit between 0x200"),
+        "{}",
+        error
+    );
+    // A platform with more memory takes larger ROMs.
+    let rom = rom(largest);
+    let mut memory = vec![0; 0x10000];
+    rom.load_into_memory(&mut memory, 0x200).unwrap();
+    assert_eq!(memory[0x200 + largest - 1], 0x12);
+    rom.load_into_memory(&mut memory, 0xF200).unwrap();
+    rom.load_into_memory(&mut memory, 0xF201).unwrap_err();
+}
+
+#[test]
+fn roms_may_not_start_outside_memory() {
+    let rom = rom(2);
+    let mut memory = vec![0; 0x1000];
+    rom.load_into_memory(&mut memory, 0xFFE).unwrap();
+    for address in [0xFFF, 0x1000, 0xFFFF].iter() {
+        assert!(rom.load_into_memory(&mut memory, *address).is_err());
+    }
+    assert!(rom.load_into_memory(&mut [], 0).is_err());
+}
+
+#[test]
+fn files_that_do_not_look_like_programs_are_warned_about() {
+    let warnings = |bytes: &[u8]| {
+        Rom::from_bytes("test.ch8", bytes.to_vec())
+            .unwrap()
+            .warnings()
+    };
+    assert_eq!(warnings(&[]), ["the ROM is empty"]);
+    assert_eq!(
+        warnings(&[0x12]),
+        ["the ROM is a single byte, too short for an opcode"]
+    );
+    assert_eq!(
+        warnings(&[0xFF, 0xFF]),
+        ["the first opcode 0xFFFF is not a chip-8 instruction"]
+    );
+    // An odd size is fine past the first opcode, programs often end in data.
+    assert!(warnings(&[0x00, 0xE0, 0x12, 0x02, 0xF0]).is_empty());
+}
@@ -0,0 +1,893 @@
+"""Builds the test ROMs in this directory with a small two-pass assembler,
+and the golden framebuffers in tests/golden from what each ROM is meant to
+draw on each platform.
+
+Run `python3 tests/roms/build.py` after changing a ROM, which rewrites its
+.ch8 file, its .lst listing and its golden framebuffers. The goldens are
+drawn here from the published fonts and platform quirks, never by the
+emulator, so a test failure means the emulator disagrees with them.
+"""
+
+import os
+import sys
+
+START = 0x200
+
+
+class Program:
+    def __init__(self, title):
+        self.title = title
+        self.items = []  # (kind, value, text, comment)
+        self.labels = {}
+
+    # -- building -------------------------------------------------------
+    def label(self, name):
+        self.items.append(("label", name, None, None))
+
+    def op(self, encode, text, comment=None):
+        self.items.append(("op", encode, text, comment))
+
+    def data(self, bytes_, comment=None):
+        self.items.append(("data", bytes(bytes_), None, comment))
+
+    def comment(self, text):
+        self.items.append(("comment", text, None, None))
+
+    # -- instructions ----------------------------------------------------
+    def cls(self, c=None):
+        self.op(lambda a: 0x00E0, "cls", c)
+
+    def ret(self, c=None):
+        self.op(lambda a: 0x00EE, "ret", c)
+
+    def jp(self, target, c=None):
+        self.op(lambda a: 0x1000 | self.addr(target), f"jp {target}", c)
+
+    def jp_v0(self, target, offset=0, c=None):
+        self.op(lambda a: 0xB000 | (self.addr(target) + offset),
+                f"jp v0, {target}{'%+d' % offset if offset else ''}", c)
+
+    def call(self, target, c=None):
+        self.op(lambda a: 0x2000 | self.addr(target), f"call {target}", c)
+
+    def se(self, x, y, c=None):
+        if isinstance(y, str):
+            self.op(lambda a: 0x5000 | reg(x) << 8 | reg(y) << 4, f"se {x}, {y}", c)
+        else:
+            self.op(lambda a: 0x3000 | reg(x) << 8 | y, f"se {x}, 0x{y:02X}", c)
+
+    def sne(self, x, y, c=None):
+        if isinstance(y, str):
+            self.op(lambda a: 0x9000 | reg(x) << 8 | reg(y) << 4, f"sne {x}, {y}", c)
+        else:
+            self.op(lambda a: 0x4000 | reg(x) << 8 | y, f"sne {x}, 0x{y:02X}", c)
+
+    def ld(self, x, y, c=None):
+        if x == "i":
+            self.op(lambda a: 0xA000 | self.addr(y), f"ld i, {y}", c)
+        elif x == "dt":
+            self.op(lambda a: 0xF015 | reg(y) << 8, f"ld dt, {y}", c)
+        elif x == "st":
+            self.op(lambda a: 0xF018 | reg(y) << 8, f"ld st, {y}", c)
+        elif x == "f":
+            self.op(lambda a: 0xF029 | reg(y) << 8, f"ld f, {y}", c)
+        elif x == "b":
+            self.op(lambda a: 0xF033 | reg(y) << 8, f"ld b, {y}", c)
+        elif x == "[i]":
+            self.op(lambda a: 0xF055 | reg(y) << 8, f"ld [i], {y}", c)
+        elif y == "[i]":
+            self.op(lambda a: 0xF065 | reg(x) << 8, f"ld {x}, [i]", c)
+        elif y == "dt":
+            self.op(lambda a: 0xF007 | reg(x) << 8, f"ld {x}, dt", c)
+        elif y == "k":
+            self.op(lambda a: 0xF00A | reg(x) << 8, f"ld {x}, k", c)
+        elif isinstance(y, str):
+            self.op(lambda a: 0x8000 | reg(x) << 8 | reg(y) << 4, f"ld {x}, {y}", c)
+        else:
+            self.op(lambda a: 0x6000 | reg(x) << 8 | y, f"ld {x}, 0x{y:02X}", c)
+
+    def add(self, x, y, c=None):
+        if x == "i":
+            self.op(lambda a: 0xF01E | reg(y) << 8, f"add i, {y}", c)
+        elif isinstance(y, str):
+            self.op(lambda a: 0x8004 | reg(x) << 8 | reg(y) << 4, f"add {x}, {y}", c)
+        else:
+            self.op(lambda a: 0x7000 | reg(x) << 8 | y, f"add {x}, 0x{y:02X}", c)
+
+    def alu(self, name, low, x, y, c=None):
+        self.op(lambda a: 0x8000 | reg(x) << 8 | reg(y) << 4 | low, f"{name} {x}, {y}", c)
+
+    def or_(self, x, y, c=None):
+        self.alu("or", 1, x, y, c)
+
+    def and_(self, x, y, c=None):
+        self.alu("and", 2, x, y, c)
+
+    def xor(self, x, y, c=None):
+        self.alu("xor", 3, x, y, c)
+
+    def sub(self, x, y, c=None):
+        self.alu("sub", 5, x, y, c)
+
+    def shr(self, x, y, c=None):
+        self.alu("shr", 6, x, y, c)
+
+    def subn(self, x, y, c=None):
+        self.alu("subn", 7, x, y, c)
+
+    def shl(self, x, y, c=None):
+        self.alu("shl", 0xE, x, y, c)
+
+    def rnd(self, x, nn, c=None):
+        self.op(lambda a: 0xC000 | reg(x) << 8 | nn, f"rnd {x}, 0x{nn:02X}", c)
+
+    def drw(self, x, y, n, c=None):
+        self.op(lambda a: 0xD000 | reg(x) << 8 | reg(y) << 4 | n, f"drw {x}, {y}, {n}", c)
+
+    def skp(self, x, c=None):
+        self.op(lambda a: 0xE09E | reg(x) << 8, f"skp {x}", c)
+
+    def sknp(self, x, c=None):
+        self.op(lambda a: 0xE0A1 | reg(x) << 8, f"sknp {x}", c)
+
+    # -- assembling ------------------------------------------------------
+    def addr(self, target):
+        if isinstance(target, int):
+            return target
+        return self.labels[target]
+
+    def layout(self):
+        address = START
+        for kind, value, _, _ in self.items:
+            if kind == "label":
+                self.labels[value] = address
+            elif kind == "op":
+                address += 2
+            elif kind == "data":
+                address += len(value)
+        return address
+
+    def assemble(self):
+        self.layout()
+        rom = bytearray()
+        listing = [f"; {line}" if line else ";" for line in self.title.splitlines()]
+        listing.append("")
+        for kind, value, text, comment in self.items:
+            address = START + len(rom)
+            if kind == "label":
+                listing.append(f"{value}:")
+            elif kind == "comment":
+                listing.append(f"    ; {value}")
+            elif kind == "op":
+                opcode = value(address)
+                assert 0 <= opcode <= 0xFFFF, text
+                rom += opcode.to_bytes(2, "big")
+                line = f"    {address:03X}  {opcode:04X}  {text}"
+                listing.append(f"{line:<40}; {comment}" if comment else line)
+            elif kind == "data":
+                rom += value
+                for i in range(0, len(value), 8):
+                    chunk = value[i:i + 8]
+                    line = f"    {address + i:03X}  {chunk.hex(' ').upper()}"
+                    listing.append(f"{line:<40}; {comment}" if comment and i == 0 else line)
+        return bytes(rom), "\n".join(listing) + "\n"
+
+
+def reg(name):
+    assert name[0] == "v" and len(name) == 2, name
+    return int(name[1], 16)
+
+
+def write(program, directory, name):
+    rom, listing = program.assemble()
+    with open(f"{directory}/{name}.ch8", "wb") as f:
+        f.write(rom)
+    with open(f"{directory}/{name}.lst", "w") as f:
+        f.write(listing)
+    print(name, len(rom), "bytes", file=sys.stderr)
+
+
+
+PASS = [0x08, 0x08, 0x10, 0x90, 0x60]
+FAIL = [0x88, 0x50, 0x20, 0x50, 0x88]
+
+
+def checker(p):
+    """check: marks the next cell with a tick when V0 == V1, a cross otherwise."""
+    p.label("check")
+    p.ld("i", "pass")
+    p.se("v0", "v1")
+    p.ld("i", "fail")
+    p.drw("vc", "vd", 5)
+    p.add("vc", 8, "next column")
+    p.se("vc", 64)
+    p.ret()
+    p.ld("vc", 0, "next row")
+    p.add("vd", 6)
+    p.ret()
+    p.label("pass")
+    p.data(PASS, "tick")
+    p.label("fail")
+    p.data(FAIL, "cross")
+
+
+def start(p):
+    p.cls()
+    p.ld("vc", 0, "vc, vd: where the next mark goes")
+    p.ld("vd", 0)
+
+
+def halt(p):
+    p.label("halt")
+    p.jp("halt")
+
+
+def expect(p, value, c=None):
+    p.ld("v1", value, c)
+    p.call("check")
+
+
+# ---------------------------------------------------------------------------
+def opcodes():
+    p = Program("""opcodes.ch8: one mark per instruction, a tick when it computed the
+expected result and a cross when it did not. None of the results depend
+on a quirk, so every platform shows 29 ticks.
+
+Marks, left to right and top to bottom:
+ 1 3XNN   2 4XNN   3 5XY0   4 9XY0   5 7XNN   6 8XY0   7 8XY1   8 8XY2
+ 9 8XY3  10 8XY4  11 8XY5  12 8XY7  13 8XY6  14 8XYE  15 2NNN  16 00EE
+17 1NNN  18 BNNN  19 FX1E  20 FX33 hundreds  21 FX33 tens  22 FX33 ones
+23 FX55 first 24 FX55 last  25 FX07  26 FX29  27 CXNN  28 EX9E  29 EXA1""")
+    start(p)
+    p.comment("3XNN skips when equal")
+    p.ld("v0", 1)
+    p.se("v0", 1)
+    p.ld("v0", 0)
+    expect(p, 1)
+    p.comment("4XNN skips when not equal")
+    p.ld("v0", 1)
+    p.sne("v0", 2)
+    p.ld("v0", 0)
+    expect(p, 1)
+    p.comment("5XY0 skips when the registers are equal")
+    p.ld("v0", 5)
+    p.ld("v2", 5)
+    p.se("v0", "v2")
+    p.ld("v0", 0)
+    expect(p, 5)
+    p.comment("9XY0 skips when the registers differ")
+    p.ld("v0", 5)
+    p.ld("v2", 6)
+    p.sne("v0", "v2")
+    p.ld("v0", 0)
+    expect(p, 5)
+    p.comment("7XNN wraps around")
+    p.ld("v0", 0xFF)
+    p.add("v0", 2)
+    expect(p, 1)
+    p.comment("8XY0")
+    p.ld("v2", 0x42)
+    p.ld("v0", "v2")
+    expect(p, 0x42)
+    p.comment("8XY1")
+    p.ld("v0", 0x0F)
+    p.ld("v2", 0xF0)
+    p.or_("v0", "v2")
+    expect(p, 0xFF)
+    p.comment("8XY2")
+    p.ld("v0", 0x3C)
+    p.ld("v2", 0x0F)
+    p.and_("v0", "v2")
+    expect(p, 0x0C)
+    p.comment("8XY3")
+    p.ld("v0", 0x3C)
+    p.ld("v2", 0x0F)
+    p.xor("v0", "v2")
+    expect(p, 0x33)
+    p.comment("8XY4 wraps around")
+    p.ld("v0", 0xF0)
+    p.ld("v2", 0x20)
+    p.add("v0", "v2")
+    expect(p, 0x10)
+    p.comment("8XY5 wraps around")
+    p.ld("v0", 0x10)
+    p.ld("v2", 0x20)
+    p.sub("v0", "v2")
+    expect(p, 0xF0)
+    p.comment("8XY7")
+    p.ld("v0", 0x10)
+    p.ld("v2", 0x30)
+    p.subn("v0", "v2")
+    expect(p, 0x20)
+    p.comment("8XY6 with VX = VY, so the shift quirk does not matter")
+    p.ld("v0", 0x11)
+    p.ld("v2", 0x11)
+    p.shr("v0", "v2")
+    expect(p, 0x08)
+    p.comment("8XYE with VX = VY")
+    p.ld("v0", 0x81)
+    p.ld("v2", 0x81)
+    p.shl("v0", "v2")
+    expect(p, 0x02)
+    p.comment("2NNN calls and 00EE returns")
+    p.ld("v0", 0)
+    p.call("set_v0")
+    expect(p, 7)
+    p.ld("v0", "v3", "set by set_v0 after the return")
+    expect(p, 9)
+    p.comment("1NNN")
+    p.ld("v0", 1)
+    p.jp("jumped")
+    p.ld("v0", 0)
+    p.label("jumped")
+    expect(p, 1)
+    p.comment("BNNN with the same offset in V0 and VX, so the jump quirk does not matter")
+    p.ld("v0", 4)
+    p.ld("v2", 4)
+    p.ld("v3", 4)
+    p.ld("v5", 1)
+    p.jp_v0("jump_table")
+    p.label("jump_table")
+    p.ld("v5", 0)
+    p.ld("v5", 0)
+    p.ld("v0", "v5")
+    expect(p, 1)
+    p.comment("ANNN and FX1E")
+    p.ld("i", "table")
+    p.ld("v2", 1)
+    p.add("i", "v2")
+    p.ld("v0", "[i]")
+    expect(p, 0x5A)
+    p.comment("FX33 writes hundreds, tens and ones")
+    p.ld("v2", 234)
+    p.ld("i", "scratch")
+    p.ld("b", "v2")
+    p.ld("i", "scratch")
+    p.ld("v2", "[i]")
+    p.ld("v3", "v1")
+    p.ld("v4", "v2")
+    expect(p, 2)
+    p.ld("v0", "v3")
+    expect(p, 3)
+    p.ld("v0", "v4")
+    expect(p, 4)
+    p.comment("FX55 and FX65")
+    p.ld("v0", 0x11)
+    p.ld("v1", 0x22)
+    p.ld("v2", 0x33)
+    p.ld("i", "scratch")
+    p.ld("[i]", "v2")
+    p.ld("v0", 0)
+    p.ld("v1", 0)
+    p.ld("v2", 0)
+    p.ld("i", "scratch")
+    p.ld("v2", "[i]")
+    p.ld("v3", "v2")
+    expect(p, 0x11)
+    p.ld("v0", "v3")
+    expect(p, 0x33)
+    p.comment("FX15 and FX07, allowing for a frame ending in between")
+    p.ld("v2", 0x20)
+    p.ld("dt", "v2")
+    p.ld("v0", "dt")
+    p.sne("v0", 0x1F)
+    p.add("v0", 1)
+    expect(p, 0x20)
+    p.comment("FX29 points I at the glyph for A, F0 90 F0 90 90 in every font")
+    p.ld("v2", 0xA)
+    p.ld("f", "v2")
+    p.ld("v4", "[i]")
+    p.ld("v0", "v4")
+    expect(p, 0x90)
+    p.comment("CXNN is masked by NN")
+    p.rnd("v0", 0x0F)
+    p.ld("v2", 0xF0)
+    p.and_("v0", "v2")
+    expect(p, 0)
+    p.comment("EX9E does not skip and EXA1 skips with no key held")
+    p.ld("v2", 5)
+    p.ld("v0", 1)
+    p.skp("v2")
+    p.ld("v0", 0)
+    expect(p, 0)
+    p.ld("v0", 1)
+    p.sknp("v2")
+    p.ld("v0", 0)
+    expect(p, 1)
+    halt(p)
+    p.label("set_v0")
+    p.ld("v0", 7)
+    p.ld("v3", 9)
+    p.ret()
+    checker(p)
+    p.label("table")
+    p.data([0xA5, 0x5A, 0xFF], "read through ANNN and FX1E")
+    p.label("scratch")
+    p.data([0] * 4, "written by FX33 and FX55")
+    return p
+
+
+# ---------------------------------------------------------------------------
+def flags():
+    p = Program("""flags.ch8: one mark per check of VF, a tick when it holds the
+expected value. The flag is written after the result, so the checks with
+VF as the destination see the flag.
+
+Marks, left to right and top to bottom:
+ 1 8XY4 carry        2 8XY4 no carry     3 8XY4 result      4 8XF4 carry
+ 5 8XY5 no borrow    6 8XY5 borrow       7 8XY5 result      8 8XY5 equal
+ 9 8XF5 no borrow   10 8XY7 no borrow   11 8XY7 borrow     12 8XY7 result
+13 8XY7 equal       14 8XF7 no borrow   15 8XY6 bit out    16 8XY6 no bit
+17 8XF6             18 8XYE bit out     19 8XYE no bit     20 8XFE
+21 DXYN collision   22 DXYN no collision                   23 DXYN erases""")
+    start(p)
+    p.comment("8XY4")
+    p.ld("v2", 0xFF)
+    p.ld("v3", 0x02)
+    p.add("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.ld("v2", 0x10)
+    p.add("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 0)
+    p.ld("v0", "v2")
+    expect(p, 0x12)
+    p.ld("vf", 0xFF)
+    p.ld("v3", 0x03)
+    p.add("vf", "v3")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.comment("8XY5: VF is 1 when there is no borrow")
+    p.ld("v2", 0x30)
+    p.ld("v3", 0x10)
+    p.sub("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.ld("v2", 0x10)
+    p.ld("v3", 0x30)
+    p.sub("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 0)
+    p.ld("v0", "v2")
+    expect(p, 0xE0)
+    p.ld("v2", 0x30)
+    p.ld("v3", 0x30)
+    p.sub("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.ld("vf", 0x30)
+    p.ld("v3", 0x10)
+    p.sub("vf", "v3")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.comment("8XY7")
+    p.ld("v2", 0x10)
+    p.ld("v3", 0x30)
+    p.subn("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.ld("v2", 0x30)
+    p.ld("v3", 0x10)
+    p.subn("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 0)
+    p.ld("v0", "v2")
+    expect(p, 0xE0)
+    p.ld("v2", 0x30)
+    p.ld("v3", 0x30)
+    p.subn("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.ld("vf", 0x10)
+    p.ld("v3", 0x30)
+    p.subn("vf", "v3")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.comment("8XY6 with VX = VY, so the shift quirk does not matter")
+    p.ld("v2", 0x05)
+    p.ld("v3", 0x05)
+    p.shr("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.ld("v2", 0x04)
+    p.ld("v3", 0x04)
+    p.shr("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 0)
+    p.ld("vf", 0x03)
+    p.shr("vf", "vf")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.comment("8XYE")
+    p.ld("v2", 0x81)
+    p.ld("v3", 0x81)
+    p.shl("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.ld("v2", 0x41)
+    p.ld("v3", 0x41)
+    p.shl("v2", "v3")
+    p.ld("v0", "vf")
+    expect(p, 0)
+    p.ld("vf", 0xC0)
+    p.shl("vf", "vf")
+    p.ld("v0", "vf")
+    expect(p, 1)
+    p.comment("DXYN sets VF when it turns a pixel off, at the bottom right")
+    p.ld("v2", 56)
+    p.ld("v3", 26)
+    p.ld("i", "block")
+    p.drw("v2", "v3", 1)
+    p.ld("v0", "vf")
+    p.ld("v4", "v0")
+    p.drw("v2", "v3", 1)
+    p.ld("v0", "vf")
+    p.ld("v5", "v0")
+    p.ld("v0", "v5")
+    expect(p, 1)
+    p.ld("v0", "v4")
+    expect(p, 0)
+    p.comment("the second draw erased the block, so a third one does not collide")
+    p.ld("i", "block")
+    p.drw("v2", "v3", 1)
+    p.ld("v0", "vf")
+    p.ld("v6", "v0")
+    p.drw("v2", "v3", 1)
+    p.ld("v0", "v6")
+    expect(p, 0)
+    halt(p)
+    checker(p)
+    p.label("block")
+    p.data([0xFF])
+    return p
+
+
+# ---------------------------------------------------------------------------
+def quirks():
+    p = Program("""quirks.ch8: shows what each quirk did, one column per quirk with its
+number at the top and what happened below it.
+
+1 VF reset: 1 when 8XY1 cleared VF, 0 when it kept it
+2 memory: how far FX55 moved I past the first register it stored,
+  2 (by X + 1), 1 (by X) or 0 (unchanged) for X = 1
+3 shift: 1 when 8XY6 shifted VX, 0 when it shifted VY into VX
+4 jump: 1 when BNNN added VX, 0 when it added V0
+5 clipping: 1 when a sprite wrapped at the right edge, 0 when clipped
+6 display wait: 1 when each DXYN waited for the end of the frame""")
+    start(p)
+    p.comment("1: VF reset")
+    p.ld("vf", 5)
+    p.ld("v2", 1)
+    p.or_("v2", "v2")
+    p.ld("v0", 0)
+    p.se("vf", 5)
+    p.ld("v0", 1)
+    p.ld("v1", 1)
+    p.call("column")
+    p.comment("2: memory, FX55 stores V0 = 0 and V1 = 1, the byte after them is 2")
+    p.ld("i", "memory")
+    p.ld("v0", 0)
+    p.ld("v1", 1)
+    p.ld("[i]", "v1")
+    p.ld("v0", "[i]")
+    p.ld("v1", 2)
+    p.call("column")
+    p.comment("3: shift")
+    p.ld("v2", 0x10)
+    p.ld("v3", 0x08)
+    p.shr("v2", "v3")
+    p.ld("v0", 0)
+    p.se("v2", 0x04)
+    p.ld("v0", 1)
+    p.ld("v1", 3)
+    p.call("column")
+    p.comment("4: jump, V0 = 0 lands on the first entry and VX = 4 on the second")
+    p.ld("v0", 0)
+    p.ld("v2", 4)
+    p.ld("v3", 4)
+    p.jp_v0("jumps")
+    p.label("jumps")
+    p.ld("v4", 0)
+    p.jp("jumped")
+    p.ld("v4", 1)
+    p.label("jumped")
+    p.ld("v0", "v4")
+    p.ld("v1", 4)
+    p.call("column")
+    p.comment("5: clipping, a byte drawn at x = 60 collides with a pixel at x = 0")
+    p.comment("only when it wraps")
+    p.ld("i", "block")
+    p.ld("v2", 0)
+    p.ld("v3", 31)
+    p.drw("v2", "v3", 1)
+    p.ld("v2", 60)
+    p.drw("v2", "v3", 1)
+    p.ld("v5", "vf")
+    p.drw("v2", "v3", 1)
+    p.ld("v2", 0)
+    p.drw("v2", "v3", 1)
+    p.ld("v0", "v5")
+    p.ld("v1", 5)
+    p.call("column")
+    p.comment("6: display wait, counts the empty draws in one frame")
+    p.ld("v2", 1)
+    p.ld("dt", "v2")
+    p.label("sync")
+    p.ld("v2", "dt")
+    p.se("v2", 0)
+    p.jp("sync")
+    p.ld("v2", 5)
+    p.ld("dt", "v2")
+    p.ld("v5", 0)
+    p.ld("i", "empty")
+    p.label("count")
+    p.drw("v2", "v2", 1)
+    p.add("v5", 1)
+    p.ld("v2", "dt")
+    p.se("v2", 4)
+    p.jp("count")
+    p.ld("v0", 0)
+    p.se("v5", 1)
+    p.jp("waited")
+    p.ld("v0", 1)
+    p.label("waited")
+    p.ld("v1", 6)
+    p.call("column")
+    halt(p)
+    p.label("column")
+    p.comment("draws the digit in V1 with the one in V0 below it, and moves right")
+    p.ld("f", "v1")
+    p.drw("vc", "vd", 5)
+    p.ld("vb", 8)
+    p.ld("f", "v0")
+    p.drw("vc", "vb", 5)
+    p.add("vc", 10)
+    p.ret()
+    p.label("block")
+    p.data([0xFF])
+    p.label("empty")
+    p.data([0x00])
+    p.label("memory")
+    p.data([0xEE, 0xEE, 0x02, 0xEE], "FX55 writes the first two bytes")
+    return p
+
+
+# ---------------------------------------------------------------------------
+def font():
+    p = Program("""font.ch8: draws the 16 small font glyphs through FX29 in two rows,
+0 to 7 and 8 to F, under a 16 pixel wide bar drawn from the sprite data.""")
+    start(p)
+    p.ld("i", "bar")
+    p.ld("v2", 0)
+    p.ld("v3", 0)
+    p.drw("v2", "v3", 2)
+    p.ld("v2", 8)
+    p.drw("v2", "v3", 2)
+    p.ld("v4", 0, "v4: the glyph")
+    p.ld("v2", 0, "v2, v3: where it goes")
+    p.ld("v3", 4)
+    p.label("glyph")
+    p.ld("f", "v4")
+    p.drw("v2", "v3", 5)
+    p.add("v4", 1)
+    p.add("v2", 6)
+    p.se("v4", 8)
+    p.jp("next")
+    p.ld("v2", 0)
+    p.ld("v3", 10)
+    p.label("next")
+    p.se("v4", 16)
+    p.jp("glyph")
+    halt(p)
+    p.label("bar")
+    p.data([0xFF, 0xAA])
+    return p
+
+
+# ---------------------------------------------------------------------------
+def keypad():
+    p = Program("""keypad.ch8: for each key, draws its glyph when EX9E sees it held,
+then waits for a key with FX0A and draws that one at the bottom. The test
+holds keys 1, 5 and A, so 1, 5 and A appear in the grid and FX0A
+returns the lowest of them.""")
+    start(p)
+    p.ld("v4", 0, "v4: the key")
+    p.ld("v2", 0)
+    p.ld("v3", 0)
+    p.label("key")
+    p.sknp("v4")
+    p.call("show")
+    p.add("v4", 1)
+    p.add("v2", 6)
+    p.se("v4", 8)
+    p.jp("next")
+    p.ld("v2", 0)
+    p.ld("v3", 6)
+    p.label("next")
+    p.se("v4", 16)
+    p.jp("key")
+    p.ld("v4", "k")
+    p.ld("v2", 0)
+    p.ld("v3", 20)
+    p.call("show")
+    halt(p)
+    p.label("show")
+    p.ld("f", "v4")
+    p.drw("v2", "v3", 5)
+    p.ret()
+    return p
+
+
+# ---------------------------------------------------------------------------
+# The IBM logo program that ships with most interpreters, as it is usually
+# distributed.
+IBM_LOGO = bytes.fromhex(
+    "00e0a22a600c6108d01f7009a239d01fa2487008d01f7004a257d01f7008a266"
+    "d01f7008a275d01f1228"
+    "ff00ff003c003c003c003c00ff00ff"
+    "ff00ff0038003f003f003800ff00ff"
+    "8000e000e00080008000e000e00080"
+    "f800fc003e003f003b003900f800f8"
+    "030007000f00bf00fb00f300e30043"
+    "e000e0008000800080008000e000e0"
+)
+IBM_LETTERS = [(12, "i"), (21, "b1"), (29, "b2"), (33, "m1"), (41, "m2"), (49, "m3")]
+
+
+def ibm():
+    p = Program("""ibm.ch8: the IBM logo program, drawing the logo in six 15 row sprites
+from x = 12 at y = 8. Assembled here byte for byte from the usual copy.""")
+    p.cls()
+    p.ld("i", "i")
+    p.ld("v0", IBM_LETTERS[0][0])
+    p.ld("v1", 8)
+    p.drw("v0", "v1", 15)
+    previous = IBM_LETTERS[0][0]
+    for x, label in IBM_LETTERS[1:]:
+        # Only the second half of the B points I at its sprite first.
+        if label == "b2":
+            p.ld("i", label)
+            p.add("v0", x - previous)
+        else:
+            p.add("v0", x - previous)
+            p.ld("i", label)
+        p.drw("v0", "v1", 15)
+        previous = x
+    halt(p)
+    offset = len(IBM_LOGO) - 6 * 15
+    for n, (_, label) in enumerate(IBM_LETTERS):
+        p.label(label)
+        p.data(IBM_LOGO[offset + 15 * n:offset + 15 * (n + 1)])
+    return p
+
+
+# ---------------------------------------------------------------------------
+def beep():
+    p = Program("""beep.ch8: sounds for 30 frames when it starts, then for as long as
+key B is held, topping the sound timer up to 4 every loop like the beep
+test of other suites. Nothing is drawn.""")
+    p.ld("v0", 30)
+    p.ld("st", "v0")
+    p.ld("v1", 0xB, "v1: the key")
+    p.ld("v2", 4)
+    p.label("loop")
+    p.sknp("v1")
+    p.ld("st", "v2")
+    p.jp("loop")
+    return p
+
+
+# ---------------------------------------------------------------------------
+# The expected screens.
+
+# The small font most interpreters use, and the one of the COSMAC VIP.
+CHIP8_FONT = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70,
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0, 0x10, 0xF0, 0x10, 0xF0,
+    0x90, 0x90, 0xF0, 0x10, 0x10, 0xF0, 0x80, 0xF0, 0x10, 0xF0,
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x20, 0x40, 0x40,
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, 0xF0, 0x90, 0xF0, 0x10, 0xF0,
+    0xF0, 0x90, 0xF0, 0x90, 0x90, 0xE0, 0x90, 0xE0, 0x90, 0xE0,
+    0xF0, 0x80, 0x80, 0x80, 0xF0, 0xE0, 0x90, 0x90, 0x90, 0xE0,
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80,
+]
+VIP_FONT = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, 0x60, 0x20, 0x20, 0x20, 0x70,
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, 0xF0, 0x10, 0xF0, 0x10, 0xF0,
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, 0xF0, 0x80, 0xF0, 0x10, 0xF0,
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, 0xF0, 0x10, 0x10, 0x10, 0x10,
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, 0xF0, 0x90, 0xF0, 0x10, 0xF0,
+    0xF0, 0x90, 0xF0, 0x90, 0x90, 0xF0, 0x50, 0x70, 0x50, 0xF0,
+    0xF0, 0x80, 0x80, 0x80, 0xF0, 0xF0, 0x50, 0x50, 0x50, 0xF0,
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80,
+]
+
+# What quirks.ch8 shows on each platform, column by column: VF reset,
+# memory, shift, jump, clipping and display wait. These follow the quirks
+# of the platforms in the CHIP-8 database (chip8-database, platforms.json),
+# not this emulator.
+PLATFORMS = {
+    "originalChip8": (VIP_FONT, [1, 2, 0, 0, 0, 1]),
+    "hybridVIP": (VIP_FONT, [1, 2, 0, 0, 0, 1]),
+    "modernChip8": (CHIP8_FONT, [0, 2, 0, 0, 0, 0]),
+    "chip48": (CHIP8_FONT, [0, 1, 1, 1, 0, 0]),
+    "superchip1": (CHIP8_FONT, [0, 1, 1, 1, 0, 0]),
+    "superchip": (CHIP8_FONT, [0, 0, 1, 1, 0, 0]),
+    "xochip": (CHIP8_FONT, [0, 2, 0, 0, 1, 0]),
+}
+WIDTH, HEIGHT = 64, 32
+OPCODE_CHECKS = 29
+FLAG_CHECKS = 23
+HELD_KEYS = [0x1, 0x5, 0xA]
+
+
+class Screen:
+    def __init__(self, font):
+        self.font = font
+        self.pixels = [[0] * WIDTH for _ in range(HEIGHT)]
+
+    def draw(self, x, y, rows):
+        for row, byte in enumerate(rows):
+            for column in range(8):
+                if byte >> (7 - column) & 1:
+                    self.pixels[y + row][x + column] ^= 1
+
+    def glyph(self, x, y, digit):
+        self.draw(x, y, self.font[5 * digit:5 * digit + 5])
+
+    def text(self):
+        return "".join("".join("#" if p else "." for p in row) + "\n" for row in self.pixels)
+
+
+def marks(screen, count):
+    for n in range(count):
+        screen.draw(8 * (n % 8), 6 * (n // 8), PASS)
+
+
+def expected_screens(name, font, quirks):
+    screen = Screen(font)
+    if name in ("opcodes", "flags"):
+        marks(screen, OPCODE_CHECKS if name == "opcodes" else FLAG_CHECKS)
+    elif name == "quirks":
+        for column, value in enumerate(quirks):
+            screen.glyph(10 * column, 0, column + 1)
+            screen.glyph(10 * column, 8, value)
+    elif name == "font":
+        screen.draw(0, 0, [0xFF, 0xAA])
+        screen.draw(8, 0, [0xFF, 0xAA])
+        for digit in range(16):
+            screen.glyph(6 * (digit % 8), 4 if digit < 8 else 10, digit)
+    elif name == "keypad":
+        for key in HELD_KEYS:
+            screen.glyph(6 * (key % 8), 0 if key < 8 else 6, key)
+        screen.glyph(0, 20, min(HELD_KEYS))
+    elif name == "ibm":
+        offset = len(IBM_LOGO) - 6 * 15
+        for n, (x, _) in enumerate(IBM_LETTERS):
+            screen.draw(x, 8, IBM_LOGO[offset + 15 * n:offset + 15 * (n + 1)])
+    return screen.text()
+
+
+if __name__ == "__main__":
+    here = os.path.dirname(os.path.abspath(__file__))
+    golden = os.path.join(os.path.dirname(here), "golden")
+    os.makedirs(golden, exist_ok=True)
+    for name, build in [
+        ("font", font),
+        ("opcodes", opcodes),
+        ("flags", flags),
+        ("quirks", quirks),
+        ("keypad", keypad),
+        ("ibm", ibm),
+        ("beep", beep),
+    ]:
+        program = build()
+        write(program, here, name)
+        if name == "ibm":
+            assert program.assemble()[0] == IBM_LOGO
+        if name == "beep":
+            continue
+        for platform, (font_, quirks_) in PLATFORMS.items():
+            with open(f"{golden}/{name}.{platform}.txt", "w") as f:
+                f.write(expected_screens(name, font_, quirks_))
@@ -0,0 +1,140 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+// Save states and rewind, on machines running small loops.
+
+use chip_8_emulator::chip8::{Chip8, PROGRAM_START};
+use chip_8_emulator::state::{compress, decompress, load_state, save_state, Rewind};
+
+// 0x200: V0 = random, 0x202: jump to 0x200.
+const PROGRAM: [u8; 4] = [0xC0, 0xFF, 0x12, 0x00];
+
+fn load() -> Chip8 {
+    let mut cpu = Chip8::with_seed(7);
+    let start = PROGRAM_START as usize;
+    cpu.memory[start..start + PROGRAM.len()].copy_from_slice(&PROGRAM);
+    cpu
+}
+
+fn draws(cpu: &mut Chip8, count: usize) -> Vec<u8> {
+    (0..count)
+        .map(|_| {
+            cpu.run();
+            cpu.run();
+            cpu.registers[0]
+        })
+        .collect()
+}
+
+#[test]
+fn saving_does_not_change_the_random_numbers() {
+    let mut unsaved = load();
+    let expected = draws(&mut unsaved, 20);
+    let mut saved = load();
+    let mut numbers = draws(&mut saved, 10);
+    save_state(&saved);
+    numbers.extend(draws(&mut saved, 10));
+    assert_eq!(numbers, expected);
+}
+
+#[test]
+fn a_loaded_state_draws_the_same_random_numbers() {
+    let mut cpu = load();
+    draws(&mut cpu, 10);
+    let state = save_state(&cpu);
+    let expected = draws(&mut cpu, 10);
+    let mut restored = Chip8::with_seed(1);
+    load_state(&mut restored, &state).unwrap();
+    assert_eq!(draws(&mut restored, 10), expected);
+    load_state(&mut cpu, &state).unwrap();
+    assert_eq!(draws(&mut cpu, 10), expected);
+}
+
+#[test]
+fn other_versions_and_sizes_are_rejected() {
+    let mut cpu = load();
+    let mut state = save_state(&cpu);
+    state[4] = 1;
+    assert!(load_state(&mut cpu, &state).is_err());
+    state[4] = 2;
+    state.pop();
+    assert!(load_state(&mut cpu, &state).is_err());
+}
+
+// Counts in V0, writes the count to memory with FX33 and draws it, so memory,
+// the screen and the generator all change every frame.
+const COUNTER: [u8; 12] = [
+    0x70, 0x01, 0xA3, 0x00, 0xF0, 0x33, 0xC1, 0xFF, 0xD0, 0x15, 0x12, 0x00,
+];
+
+fn counter() -> Chip8 {
+    let mut cpu = Chip8::with_seed(3);
+    let start = PROGRAM_START as usize;
+    cpu.memory[start..start + COUNTER.len()].copy_from_slice(&COUNTER);
+    cpu
+}
+
+// Pushes a snapshot after each of `frames` frames and returns the state of
+// the machine at each of them.
+fn record(cpu: &mut Chip8, rewind: &mut Rewind, frames: usize) -> Vec<Vec<u8>> {
+    (0..frames)
+        .map(|_| {
+            cpu.run_frame();
+            rewind.push(cpu);
+            save_state(cpu)
+        })
+        .collect()
+}
+
+#[test]
+fn deltas_survive_compression() {
+    let mut long_runs = vec![0; 20_000];
+    long_runs.extend((1..=200).map(|byte| byte as u8));
+    long_runs.extend(vec![0; 300]);
+    long_runs.push(7);
+    let deltas = [
+        Vec::new(),
+        vec![0; 5],
+        vec![1, 2, 3],
+        vec![0, 0, 9, 0, 8, 8, 0],
+        long_runs,
+    ];
+    for delta in deltas.iter() {
+        assert_eq!(&decompress(&compress(delta)), delta);
+    }
+}
+
+#[test]
+fn rewinding_restores_each_earlier_state() {
+    let mut cpu = counter();
+    let mut rewind = Rewind::new(usize::MAX);
+    assert!(!rewind.rewind(&mut cpu));
+    let states = record(&mut cpu, &mut rewind, 30);
+    assert_eq!(rewind.len(), 30);
+    for state in states.iter().rev() {
+        assert!(rewind.rewind(&mut cpu));
+        assert_eq!(&save_state(&cpu), state);
+    }
+    // The oldest snapshot stays once everything newer is gone.
+    assert!(rewind.rewind(&mut cpu));
+    assert_eq!(save_state(&cpu), states[0]);
+}
+
+#[test]
+fn old_snapshots_are_dropped_over_the_budget() {
+    let mut cpu = counter();
+    let mut rewind = Rewind::new(0);
+    record(&mut cpu, &mut rewind, 10);
+    assert_eq!(rewind.len(), 1);
+
+    let mut cpu = counter();
+    let mut rewind = Rewind::new(4096);
+    let states = record(&mut cpu, &mut rewind, 200);
+    let kept = rewind.len();
+    assert!(kept > 1 && kept < 200, "{} snapshots kept", kept);
+    for state in states.iter().rev().take(kept) {
+        assert!(rewind.rewind(&mut cpu));
+        assert_eq!(&save_state(&cpu), state);
+    }
+    assert!(rewind.rewind(&mut cpu));
+    assert_eq!(save_state(&cpu), states[200 - kept]);
+}
@@ -0,0 +1,69 @@
+// Which edits to a watched ROM can be patched into the running machine.
+
+use chip_8_emulator::chip8::Chip8;
+use chip_8_emulator::rom::Rom;
+use chip_8_emulator::watch::changed_data;
+
+// 0x200: I = 0x206, 0x202: draw it, 0x204: halt, 0x206: the sprite of an A.
+const PROGRAM: [u8; 11] = [
+    0xA2, 0x06, 0xD0, 0x15, 0x12, 0x04, 0xF0, 0x90, 0xF0, 0x90, 0x90,
+];
+
+fn rom(bytes: &[u8]) -> Rom {
+    Rom::from_bytes("test.ch8", bytes.to_vec()).unwrap()
+}
+
+fn boot(rom: &Rom) -> Chip8 {
+    let mut cpu = Chip8::with_seed(0);
+    rom.settings.apply(&mut cpu);
+    rom.load_into_memory(&mut cpu.memory, cpu.program_start)
+        .unwrap();
+    cpu
+}
+
+fn edited(edit: impl FnOnce(&mut Vec<u8>)) -> Rom {
+    let mut bytes = PROGRAM.to_vec();
+    edit(&mut bytes);
+    rom(&bytes)
+}
+
+#[test]
+fn data_edits_give_the_changed_addresses() {
+    let old = rom(&PROGRAM);
+    let new = edited(|bytes| {
+        bytes[7] = 0xFF;
+        bytes[10] = 0x00;
+    });
+    assert_eq!(
+        changed_data(&old, &new, &boot(&new)),
+        Some(vec![0x207, 0x20A])
+    );
+    assert_eq!(changed_data(&old, &old, &boot(&old)), Some(Vec::new()));
+}
+
+#[test]
+fn code_edits_reboot() {
+    let old = rom(&PROGRAM);
+    // The sprite grows by a row.
+    let new = edited(|bytes| bytes[3] = 0x16);
+    assert_eq!(changed_data(&old, &new, &boot(&new)), None);
+    // A data edit alongside a code edit does not help.
+    let new = edited(|bytes| {
+        bytes[1] = 0x07;
+        bytes[6] = 0x00;
+    });
+    assert_eq!(changed_data(&old, &new, &boot(&new)), None);
+}
+
+#[test]
+fn layout_changes_reboot() {
+    let old = rom(&PROGRAM);
+    let new = edited(|bytes| bytes.push(0x90));
+    assert_eq!(changed_data(&old, &new, &boot(&new)), None);
+    let mut moved = rom(&PROGRAM);
+    moved.settings.program_start = 0x300;
+    assert_eq!(changed_data(&old, &moved, &boot(&moved)), None);
+    let mut larger = rom(&PROGRAM);
+    larger.settings.memory_size = 0x10000;
+    assert_eq!(changed_data(&old, &larger, &boot(&larger)), None);
+}
@@ -0,0 +1,39 @@
+#![cfg(target_arch = "wasm32")]
+
+// Run with `wasm-pack test --headless --firefox -- --no-default-features`.
+
+use chip_8_emulator::web::Emulator;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const MAZE: &[u8] = include_bytes!("../chip8-roms/MAZE");
+
+#[wasm_bindgen_test]
+fn runs_a_known_rom() {
+    let mut emulator = Emulator::new(MAZE, 1).unwrap();
+    assert_eq!(emulator.title().as_deref(), Some("Maze"));
+    for _ in 0..60 {
+        emulator.run_frame();
+    }
+    let framebuffer = emulator.framebuffer();
+    assert_eq!(framebuffer.len(), Emulator::width() * Emulator::height());
+    assert!(framebuffer.contains(&1));
+}
+
+#[wasm_bindgen_test]
+fn rejects_roms_that_do_not_fit() {
+    assert!(Emulator::new(&[0; 0x1000], 1).is_err());
+}
+
+#[wasm_bindgen_test]
+fn keys_reach_the_core() {
+    // Waits for a key with FX0A, then draws its font glyph at 0, 0.
+    let program = [0xF0, 0x0A, 0xF0, 0x29, 0x61, 0x00, 0xD1, 0x15, 0x12, 0x08];
+    let mut emulator = Emulator::new(&program, 1).unwrap();
+    emulator.run_frame();
+    assert!(emulator.framebuffer().iter().all(|&pixel| pixel == 0));
+    emulator.set_keys(1 << 0xA);
+    emulator.run_frame();
+    assert!(emulator.framebuffer().contains(&1));
+}
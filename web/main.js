@@ -0,0 +1,93 @@
+// Build the package first with `wasm-pack build --target web --out-dir web/pkg`
+// from the repository root, then serve this directory over HTTP.
+import init, { Emulator } from "./pkg/chip_8_emulator.js";
+
+const FRAME_MS = 1000 / 60;
+const TONE_HZ = 440;
+const LAYOUT = {
+  Digit1: 0x1, Digit2: 0x2, Digit3: 0x3, Digit4: 0xc,
+  KeyQ: 0x4, KeyW: 0x5, KeyE: 0x6, KeyR: 0xd,
+  KeyA: 0x7, KeyS: 0x8, KeyD: 0x9, KeyF: 0xe,
+  KeyZ: 0xa, KeyX: 0x0, KeyC: 0xb, KeyV: 0xf,
+};
+
+const canvas = document.getElementById("screen");
+const context = canvas.getContext("2d");
+const image = context.createImageData(canvas.width, canvas.height);
+let emulator = null;
+let keys = 0;
+let audio = null;
+let tone = null;
+let lastFrame = 0;
+
+function setKey(event, pressed) {
+  const key = LAYOUT[event.code];
+  if (key === undefined) {
+    return;
+  }
+  keys = pressed ? keys | (1 << key) : keys & ~(1 << key);
+  event.preventDefault();
+}
+
+// Browsers only allow audio to start after a user gesture.
+function startAudio() {
+  if (audio) {
+    return;
+  }
+  audio = new AudioContext();
+  const oscillator = audio.createOscillator();
+  oscillator.type = "square";
+  oscillator.frequency.value = TONE_HZ;
+  tone = audio.createGain();
+  tone.gain.value = 0;
+  oscillator.connect(tone).connect(audio.destination);
+  oscillator.start();
+}
+
+function draw() {
+  const [background, foreground] = emulator.palette();
+  const pixels = emulator.framebuffer();
+  for (let i = 0; i < pixels.length; i++) {
+    const color = pixels[i] ? foreground : background;
+    image.data[i * 4] = (color >> 16) & 0xff;
+    image.data[i * 4 + 1] = (color >> 8) & 0xff;
+    image.data[i * 4 + 2] = color & 0xff;
+    image.data[i * 4 + 3] = 0xff;
+  }
+  context.putImageData(image, 0, 0);
+}
+
+function loop(now) {
+  requestAnimationFrame(loop);
+  if (!emulator || now - lastFrame < FRAME_MS) {
+    return;
+  }
+  lastFrame = now;
+  emulator.set_keys(keys);
+  emulator.run_frame();
+  draw();
+  if (tone) {
+    tone.gain.value = emulator.sound_active() ? 0.1 : 0;
+  }
+}
+
+async function loadRom(file) {
+  const bytes = new Uint8Array(await file.arrayBuffer());
+  try {
+    emulator = new Emulator(bytes, Math.floor(Math.random() * 0xffffffff));
+  } catch (error) {
+    alert(`${file.name}: ${error}`);
+    return;
+  }
+  document.getElementById("title").textContent = emulator.title() ?? file.name;
+}
+
+await init();
+document.addEventListener("keydown", (event) => setKey(event, true));
+document.addEventListener("keyup", (event) => setKey(event, false));
+document.addEventListener("keydown", startAudio);
+document.getElementById("rom").addEventListener("change", (event) => {
+  startAudio();
+  loadRom(event.target.files[0]);
+});
+requestAnimationFrame(loop);